@@ -0,0 +1,270 @@
+//! Block data of chunks in the format that Minecraft uses since 1.18. A chunk is a column of
+//! sections, each a cube of 16 blocks. Each section lists the distinct blocks in it in a palette,
+//! and packs the palette index of each block into an array of longs, using the fewest bits that
+//! fit the largest index, and at least 4. Indices do not cross from one long into the next, so the
+//! high bits of each long can be unused.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::int_vec3::IntVec3;
+
+use super::nbt::Tag;
+
+/// Width, height, and depth of a section, and the width and depth of a chunk
+pub const SECTION_SIZE: i32 = 16;
+
+const SECTION_BLOCKS: usize = (SECTION_SIZE * SECTION_SIZE * SECTION_SIZE) as usize;
+const MIN_BITS: u32 = 4;
+
+/// Minecraft 1.18, the first version that stores sections as they are read here
+const MIN_DATA_VERSION: i64 = 2860;
+
+/// A block as Minecraft names it, like `minecraft:lever` with properties `face=floor`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PaletteEntry {
+    pub name: String,
+    pub properties: BTreeMap<String, String>,
+}
+
+impl PaletteEntry {
+    fn from_nbt(tag: &Tag) -> Result<Self> {
+        let name = tag
+            .get("Name")
+            .and_then(Tag::as_str)
+            .ok_or(anyhow!("palette entry without a name"))?;
+        let properties = match tag.get("Properties").and_then(Tag::as_compound) {
+            Some(properties) => properties
+                .iter()
+                .map(|(prop, value)| {
+                    let value =
+                        value
+                            .as_str()
+                            .ok_or(anyhow!("{} of {} is not a string", prop, name))?;
+                    Ok((prop.clone(), value.to_owned()))
+                })
+                .collect::<Result<_>>()?,
+            None => BTreeMap::new(),
+        };
+        Ok(PaletteEntry {
+            name: name.to_owned(),
+            properties,
+        })
+    }
+
+    fn to_nbt(&self) -> Tag {
+        let mut entries = BTreeMap::from([("Name".to_owned(), Tag::String(self.name.clone()))]);
+        // Minecraft leaves out the properties of blocks that have none.
+        if !self.properties.is_empty() {
+            let properties = self
+                .properties
+                .iter()
+                .map(|(prop, value)| (prop.clone(), Tag::String(value.clone())))
+                .collect();
+            entries.insert("Properties".to_owned(), Tag::Compound(properties));
+        }
+        Tag::Compound(entries)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Section {
+    palette: Vec<PaletteEntry>,
+
+    /// Palette index of each block, ordered by y, then z, then x
+    indices: Vec<usize>,
+}
+
+impl Section {
+    fn from_nbt(block_states: &Tag) -> Result<Self> {
+        let palette = block_states
+            .get("palette")
+            .and_then(Tag::as_list)
+            .ok_or(anyhow!("section without a palette"))?
+            .iter()
+            .map(PaletteEntry::from_nbt)
+            .collect::<Result<Vec<_>>>()?;
+        if palette.is_empty() {
+            return Err(anyhow!("section with an empty palette"));
+        }
+        // Sections with a single block in the palette have no data.
+        let data = match block_states.get("data") {
+            Some(data) => data
+                .as_long_array()
+                .ok_or(anyhow!("section data is not a long array"))?,
+            None if palette.len() == 1 => {
+                return Ok(Section {
+                    palette,
+                    indices: vec![0; SECTION_BLOCKS],
+                })
+            }
+            None => return Err(anyhow!("section without data")),
+        };
+        let bits = bits_for(palette.len());
+        let per_long = (64 / bits) as usize;
+        if data.len() < (SECTION_BLOCKS + per_long - 1) / per_long {
+            return Err(anyhow!("section data is too short"));
+        }
+        let mask = (1 << bits) - 1;
+        let indices = (0..SECTION_BLOCKS)
+            .map(|index| {
+                let long = data[index / per_long] as u64;
+                let palette_index = (long >> ((index % per_long) as u32 * bits)) & mask;
+                let palette_index = palette_index as usize;
+                if palette_index >= palette.len() {
+                    return Err(anyhow!("section data is outside of the palette"));
+                }
+                Ok(palette_index)
+            })
+            .collect::<Result<_>>()?;
+        Ok(Section { palette, indices })
+    }
+
+    /// The block at a position inside the section, with each coordinate from 0 to 15.
+    pub fn block(&self, x: i32, y: i32, z: i32) -> &PaletteEntry {
+        &self.palette[self.indices[block_index(x, y, z)]]
+    }
+
+    pub fn set_block(&mut self, x: i32, y: i32, z: i32, entry: PaletteEntry) {
+        let palette_index = match self.palette.iter().position(|known| *known == entry) {
+            Some(palette_index) => palette_index,
+            None => {
+                self.palette.push(entry);
+                self.palette.len() - 1
+            }
+        };
+        self.indices[block_index(x, y, z)] = palette_index;
+    }
+
+    /// Packs the section into the `block_states` tag of a section. Palette entries that are no
+    /// longer used are dropped.
+    fn to_nbt(&self) -> Tag {
+        let mut palette: Vec<&PaletteEntry> = vec![];
+        let mut new_indices: Vec<Option<usize>> = vec![None; self.palette.len()];
+        let mut indices = Vec::with_capacity(SECTION_BLOCKS);
+        for &old_index in self.indices.iter() {
+            let new_index = match new_indices[old_index] {
+                Some(new_index) => new_index,
+                None => {
+                    palette.push(&self.palette[old_index]);
+                    new_indices[old_index] = Some(palette.len() - 1);
+                    palette.len() - 1
+                }
+            };
+            indices.push(new_index);
+        }
+
+        let mut entries = BTreeMap::from([(
+            "palette".to_owned(),
+            Tag::List(palette.iter().map(|entry| entry.to_nbt()).collect()),
+        )]);
+        if palette.len() > 1 {
+            let bits = bits_for(palette.len());
+            let per_long = (64 / bits) as usize;
+            let mut data = vec![0_u64; (SECTION_BLOCKS + per_long - 1) / per_long];
+            for (index, palette_index) in indices.into_iter().enumerate() {
+                data[index / per_long] |=
+                    (palette_index as u64) << ((index % per_long) as u32 * bits);
+            }
+            let data = data.into_iter().map(|long| long as i64).collect();
+            entries.insert("data".to_owned(), Tag::LongArray(data));
+        }
+        Tag::Compound(entries)
+    }
+}
+
+fn block_index(x: i32, y: i32, z: i32) -> usize {
+    ((y * SECTION_SIZE + z) * SECTION_SIZE + x) as usize
+}
+
+/// Reads the block sections of a chunk, by their height in sections. Sections that only hold
+/// light data are skipped.
+pub fn sections(chunk: &Tag) -> Result<HashMap<i32, Section>> {
+    let data_version = chunk.get("DataVersion").and_then(Tag::as_i64).unwrap_or(0);
+    if data_version < MIN_DATA_VERSION {
+        return Err(anyhow!(
+            "chunk was saved by a version of Minecraft before 1.18, which is not supported"
+        ));
+    }
+    let mut sections = HashMap::new();
+    for section in chunk
+        .get("sections")
+        .and_then(Tag::as_list)
+        .unwrap_or_default()
+    {
+        let y = section
+            .get("Y")
+            .and_then(Tag::as_i64)
+            .ok_or(anyhow!("section without a height"))?;
+        if let Some(block_states) = section.get("block_states") {
+            let parsed = Section::from_nbt(block_states)
+                .with_context(|| format!("invalid section at height {}", y))?;
+            sections.insert(y as i32, parsed);
+        }
+    }
+    Ok(sections)
+}
+
+/// Replaces the block data of sections of a chunk, by their height in sections. The light data of
+/// those sections and the heightmaps of the chunk are removed, and the chunk is marked as unlit,
+/// so that Minecraft calculates them again when it loads the chunk.
+pub fn write_sections(chunk: &mut Tag, changed: &HashMap<i32, Section>) -> Result<()> {
+    let entries = chunk
+        .as_compound_mut()
+        .ok_or(anyhow!("chunk is not a compound"))?;
+    let sections = entries
+        .get_mut("sections")
+        .and_then(Tag::as_list_mut)
+        .ok_or(anyhow!("chunk without sections"))?;
+    for section in sections.iter_mut() {
+        let y = section.get("Y").and_then(Tag::as_i64);
+        let (section, replacement) = match (section.as_compound_mut(), y) {
+            (Some(section), Some(y)) => match changed.get(&(y as i32)) {
+                Some(replacement) => (section, replacement),
+                None => continue,
+            },
+            _ => continue,
+        };
+        section.insert("block_states".to_owned(), replacement.to_nbt());
+        section.remove("BlockLight");
+        section.remove("SkyLight");
+    }
+    entries.insert("isLightOn".to_owned(), Tag::Byte(0));
+    entries.insert("Heightmaps".to_owned(), Tag::Compound(BTreeMap::new()));
+    Ok(())
+}
+
+/// Removes block entities, such as the contents of chests, in the box between two corners, given
+/// in world coordinates.
+pub fn remove_block_entities(chunk: &mut Tag, low: IntVec3, high: IntVec3) {
+    let entities = match chunk
+        .as_compound_mut()
+        .and_then(|entries| entries.get_mut("block_entities"))
+        .and_then(Tag::as_list_mut)
+    {
+        Some(entities) => entities,
+        None => return,
+    };
+    entities.retain(|entity| {
+        let coord = |name| {
+            entity
+                .get(name)
+                .and_then(Tag::as_i64)
+                .map(|coord| coord as i32)
+        };
+        match (coord("x"), coord("y"), coord("z")) {
+            (Some(x), Some(y), Some(z)) => {
+                !((low.x..=high.x).contains(&x)
+                    && (low.y..=high.y).contains(&y)
+                    && (low.z..=high.z).contains(&z))
+            }
+            _ => true,
+        }
+    });
+}
+
+/// Number of bits used for each block in a section with a palette of the given length.
+fn bits_for(palette_len: usize) -> u32 {
+    (usize::BITS - (palette_len - 1).leading_zeros()).max(MIN_BITS)
+}
@@ -0,0 +1,474 @@
+//! Minecraft Java Edition world saves, for pulling a contraption out of a world to analyze it, and
+//! for building a design in a world to try it in game. Blocks are read from and written to the
+//! region files of the overworld, which Minecraft keeps in the `region` folder of a world.
+
+mod chunk;
+pub mod nbt;
+mod region;
+
+use std::{
+    collections::{hash_map::Entry, BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use minecraft_assets::schemas::blockstates::multipart::StateValue;
+
+use crate::{
+    block_state::BlockState,
+    constants::BLOCK_PALETTE,
+    int_vec3::IntVec3,
+    schematic::Schematic,
+    selection::Selection,
+    timeline::{InputSchedule, WorldState},
+};
+
+use self::{
+    chunk::{PaletteEntry, SECTION_SIZE},
+    region::Region,
+};
+
+/// Blocks that Minecraft uses for empty positions
+const AIR: [&str; 3] = ["minecraft:air", "minecraft:cave_air", "minecraft:void_air"];
+
+/// A block in a world save that has no equivalent in the designer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnsupportedBlock {
+    /// Position in world coordinates
+    pub pos: IntVec3,
+
+    /// Name of the block in Minecraft, like `minecraft:stone`
+    pub name: String,
+}
+
+/// Blocks read from a world save.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorldImport {
+    /// The imported blocks, moved so that the lowest corner of the imported box is at the origin.
+    /// The blocks are not checked for valid placement until the schematic is converted to a
+    /// `Timeline`.
+    pub schematic: Schematic,
+
+    /// Blocks that were left out, or replaced with the substitute block, ordered bottom layer first
+    pub unsupported: Vec<UnsupportedBlock>,
+}
+
+/// Reads the blocks in a box of the overworld of a world save. Properties that the designer does
+/// not use are dropped. Blocks that the designer does not support are replaced with `substitute`
+/// if it is given, so that the blocks resting on them can still be placed, and are left out
+/// otherwise. Chunks that have not been generated are read as empty.
+pub fn import_world(
+    world_dir: &Path,
+    selection: &Selection,
+    substitute: Option<&BlockState>,
+) -> Result<WorldImport> {
+    let (low, high) = (selection.min(), selection.max());
+    let region_dir = world_dir.join("region");
+    let mut regions: HashMap<(i32, i32), Option<Region>> = HashMap::new();
+    let mut blocks = vec![];
+    let mut unsupported = vec![];
+    for chunk_x in low.x.div_euclid(SECTION_SIZE)..=high.x.div_euclid(SECTION_SIZE) {
+        for chunk_z in low.z.div_euclid(SECTION_SIZE)..=high.z.div_euclid(SECTION_SIZE) {
+            let path = Region::path(&region_dir, chunk_x, chunk_z);
+            let region = match regions.entry(region_key(chunk_x, chunk_z)) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(read_region(&path)?),
+            };
+            let chunk = match region {
+                Some(region) => region.chunk(chunk_x, chunk_z),
+                None => Ok(None),
+            };
+            let sections = match chunk {
+                Ok(Some(chunk)) => chunk::sections(&chunk),
+                Ok(None) => continue,
+                Err(err) => Err(err),
+            }
+            .with_context(|| {
+                format!(
+                    "could not read chunk {} {} in {}",
+                    chunk_x,
+                    chunk_z,
+                    path.display()
+                )
+            })?;
+
+            let (chunk_low_x, chunk_low_z) = (chunk_x * SECTION_SIZE, chunk_z * SECTION_SIZE);
+            for x in low.x.max(chunk_low_x)..=high.x.min(chunk_low_x + SECTION_SIZE - 1) {
+                for z in low.z.max(chunk_low_z)..=high.z.min(chunk_low_z + SECTION_SIZE - 1) {
+                    for y in low.y..=high.y {
+                        let section = match sections.get(&y.div_euclid(SECTION_SIZE)) {
+                            Some(section) => section,
+                            None => continue,
+                        };
+                        let entry = section.block(
+                            x.rem_euclid(SECTION_SIZE),
+                            y.rem_euclid(SECTION_SIZE),
+                            z.rem_euclid(SECTION_SIZE),
+                        );
+                        if AIR.contains(&entry.name.as_str()) {
+                            continue;
+                        }
+                        let pos = IntVec3::new(x, y, z);
+                        let state = match to_block_state(entry) {
+                            Some(state) => Some(state),
+                            None => {
+                                unsupported.push(UnsupportedBlock {
+                                    pos,
+                                    name: entry.name.clone(),
+                                });
+                                substitute.cloned()
+                            }
+                        };
+                        if let Some(state) = state {
+                            blocks.push((pos - low, state));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    blocks.sort_by_key(|(pos, _)| pos.layer_order_key());
+    unsupported.sort_by_key(|block| block.pos.layer_order_key());
+    Ok(WorldImport {
+        schematic: Schematic {
+            bounds: (IntVec3::ZERO, high - low),
+            blocks,
+            schedule: InputSchedule::default(),
+        },
+        unsupported,
+    })
+}
+
+/// Builds a design into the overworld of a world save, with the lowest corner of the design's
+/// bounds at `origin`. Every position in the bounds is replaced, so positions that are empty in the
+/// design are cleared, along with the contents of any chest or other block entity there. Minecraft
+/// calculates the light and heightmaps of the changed chunks again when it loads them. Nothing is
+/// written if the bounds reach into a chunk that has not been generated, or past the height of the
+/// world.
+pub fn export_world(world_dir: &Path, world: &WorldState, origin: IntVec3) -> Result<()> {
+    let (bounds_low, bounds_high) = world.bounds();
+    let (low, high) = (origin, origin + (bounds_high - bounds_low));
+    let region_dir = world_dir.join("region");
+    let mut regions: HashMap<(i32, i32), (PathBuf, Region)> = HashMap::new();
+    let air = PaletteEntry {
+        name: AIR[0].to_owned(),
+        properties: BTreeMap::new(),
+    };
+    for chunk_x in low.x.div_euclid(SECTION_SIZE)..=high.x.div_euclid(SECTION_SIZE) {
+        for chunk_z in low.z.div_euclid(SECTION_SIZE)..=high.z.div_euclid(SECTION_SIZE) {
+            let not_generated = || anyhow!("chunk {} {} has not been generated", chunk_x, chunk_z);
+            let (path, region) = match regions.entry(region_key(chunk_x, chunk_z)) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let path = Region::path(&region_dir, chunk_x, chunk_z);
+                    let region = read_region(&path)?.ok_or_else(not_generated)?;
+                    entry.insert((path, region))
+                }
+            };
+            let chunk_context = || {
+                format!(
+                    "could not read chunk {} {} in {}",
+                    chunk_x,
+                    chunk_z,
+                    path.display()
+                )
+            };
+            let mut chunk = region
+                .chunk(chunk_x, chunk_z)
+                .with_context(chunk_context)?
+                .ok_or_else(not_generated)?;
+            let mut sections = chunk::sections(&chunk).with_context(chunk_context)?;
+
+            let (chunk_low_x, chunk_low_z) = (chunk_x * SECTION_SIZE, chunk_z * SECTION_SIZE);
+            for x in low.x.max(chunk_low_x)..=high.x.min(chunk_low_x + SECTION_SIZE - 1) {
+                for z in low.z.max(chunk_low_z)..=high.z.min(chunk_low_z + SECTION_SIZE - 1) {
+                    for y in low.y..=high.y {
+                        let section = sections
+                            .get_mut(&y.div_euclid(SECTION_SIZE))
+                            .ok_or(anyhow!("height {} is outside of the world", y))?;
+                        let pos = IntVec3::new(x, y, z);
+                        let entry = match world.block_at(pos - origin + bounds_low) {
+                            Some(state) => to_palette_entry(state),
+                            None => air.clone(),
+                        };
+                        section.set_block(
+                            x.rem_euclid(SECTION_SIZE),
+                            y.rem_euclid(SECTION_SIZE),
+                            z.rem_euclid(SECTION_SIZE),
+                            entry,
+                        );
+                    }
+                }
+            }
+            let changed_sections = low.y.div_euclid(SECTION_SIZE)..=high.y.div_euclid(SECTION_SIZE);
+            sections.retain(|section_y, _| changed_sections.contains(section_y));
+            chunk::write_sections(&mut chunk, &sections).with_context(chunk_context)?;
+            chunk::remove_block_entities(&mut chunk, low, high);
+            region.set_chunk(chunk_x, chunk_z, &chunk);
+        }
+    }
+    for (path, region) in regions.values() {
+        // Writing to a new file first leaves the region file as it was if the write fails.
+        let temp_path = path.with_extension("mca.tmp");
+        fs::write(&temp_path, region.to_bytes()?)
+            .with_context(|| format!("could not write {}", temp_path.display()))?;
+        fs::rename(&temp_path, path)
+            .with_context(|| format!("could not write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Reads a region file, or returns `None` if the world has no region file at that path.
+fn read_region(path: &Path) -> Result<Option<Region>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let data = fs::read(path).with_context(|| format!("could not read {}", path.display()))?;
+    let region =
+        Region::read(&data).with_context(|| format!("could not read {}", path.display()))?;
+    Ok(Some(region))
+}
+
+fn region_key(chunk_x: i32, chunk_z: i32) -> (i32, i32) {
+    (
+        chunk_x.div_euclid(region::REGION_CHUNKS),
+        chunk_z.div_euclid(region::REGION_CHUNKS),
+    )
+}
+
+/// Converts a block from the palette of a section if it is one of the blocks in the block picker.
+fn to_block_state(entry: &PaletteEntry) -> Option<BlockState> {
+    let block_type = entry.name.strip_prefix("minecraft:")?;
+    if !BLOCK_PALETTE.contains(&block_type) {
+        return None;
+    }
+    let mut state = BlockState::initial_state_for(block_type);
+    for (prop, value) in entry.properties.iter() {
+        // Values are set unchecked since the allowed values of wire connections are patterns.
+        if state.get(prop).is_some() {
+            state.set_unchecked(prop, StateValue::from(value.as_str()));
+        }
+    }
+    Some(state)
+}
+
+/// Names a block the way Minecraft does, with every property that the block has in the designer.
+fn to_palette_entry(state: &BlockState) -> PaletteEntry {
+    let properties = state
+        .values()
+        .map(|(prop, value)| {
+            let value = match value {
+                StateValue::Bool(value) => value.to_string(),
+                StateValue::String(value) => value.clone(),
+            };
+            (prop.to_owned(), value)
+        })
+        .collect();
+    PaletteEntry {
+        name: format!("minecraft:{}", state.block_type),
+        properties,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use crate::{int_vec3::IntVec3, schematic::Format, selection::Selection};
+
+    use super::{export_world, import_world, nbt::Tag, region::Region, UnsupportedBlock};
+
+    fn compound(entries: Vec<(&str, Tag)>) -> Tag {
+        Tag::Compound(
+            entries
+                .into_iter()
+                .map(|(name, tag)| (name.to_owned(), tag))
+                .collect(),
+        )
+    }
+
+    fn palette_entry(name: &str, properties: &[(&str, &str)]) -> Tag {
+        let properties = properties
+            .iter()
+            .map(|(prop, value)| (*prop, Tag::String(value.to_string())))
+            .collect();
+        compound(vec![
+            ("Name", Tag::String(name.to_owned())),
+            ("Properties", compound(properties)),
+        ])
+    }
+
+    fn block_entity(id: &str, x: i32, y: i32, z: i32) -> Tag {
+        compound(vec![
+            ("id", Tag::String(id.to_owned())),
+            ("x", Tag::Int(x)),
+            ("y", Tag::Int(y)),
+            ("z", Tag::Int(z)),
+        ])
+    }
+
+    /// A chunk with one section at height 4, from y = 64 to 79, that holds a stone block with a
+    /// wire on top, next to a lever that powers a lamp through another wire, and two chests. The
+    /// lowest 4 bits of each long hold the first block of each group of 16.
+    fn chunk() -> Tag {
+        let palette = vec![
+            palette_entry("minecraft:air", &[]),
+            palette_entry("minecraft:stone", &[]),
+            palette_entry(
+                "minecraft:lever",
+                &[("face", "floor"), ("facing", "east"), ("powered", "false")],
+            ),
+            palette_entry(
+                "minecraft:redstone_wire",
+                &[
+                    ("east", "side"),
+                    ("north", "none"),
+                    ("power", "0"),
+                    ("south", "none"),
+                    ("west", "side"),
+                ],
+            ),
+            palette_entry("minecraft:redstone_lamp", &[("lit", "false")]),
+            palette_entry("minecraft:chest", &[("facing", "north")]),
+        ];
+        let mut indices = [0_i64; 4096];
+        for (x, y, z, index) in [
+            (3, 0, 5, 1),
+            (4, 0, 5, 2),
+            (5, 0, 5, 3),
+            (6, 0, 5, 4),
+            (3, 1, 5, 3),
+            (4, 0, 6, 5),
+            (0, 6, 0, 5),
+        ] {
+            indices[(y * 16 + z) * 16 + x] = index;
+        }
+        let data = indices
+            .chunks(16)
+            .map(|group| {
+                group
+                    .iter()
+                    .enumerate()
+                    .fold(0, |long, (i, index)| long | index << (i * 4))
+            })
+            .collect();
+        compound(vec![
+            ("DataVersion", Tag::Int(3120)),
+            ("isLightOn", Tag::Byte(1)),
+            (
+                "block_entities",
+                Tag::List(vec![
+                    block_entity("minecraft:chest", 4, 64, 6),
+                    block_entity("minecraft:chest", 0, 70, 0),
+                ]),
+            ),
+            (
+                "sections",
+                Tag::List(vec![
+                    compound(vec![("Y", Tag::Byte(3))]),
+                    compound(vec![
+                        ("Y", Tag::Byte(4)),
+                        (
+                            "block_states",
+                            compound(vec![
+                                ("palette", Tag::List(palette)),
+                                ("data", Tag::LongArray(data)),
+                            ]),
+                        ),
+                    ]),
+                ]),
+            ),
+        ])
+    }
+
+    /// Writes a world whose only chunk is chunk 0 0.
+    fn world_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("redstone-anvil-{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("region")).unwrap();
+        let mut region = Region::read(&[]).unwrap();
+        region.set_chunk(0, 0, &chunk());
+        fs::write(dir.join("region/r.0.0.mca"), region.to_bytes().unwrap()).unwrap();
+        dir
+    }
+
+    #[test]
+    fn imports_blocks_in_a_box() {
+        let dir = world_dir("import");
+        let selection = Selection::new(IntVec3::new(6, 65, 5), IntVec3::new(3, 64, 5));
+        let sandstone = "sandstone".parse().unwrap();
+        let imported = import_world(&dir, &selection, Some(&sandstone)).unwrap();
+        let expected = "\
+bounds 0 0 0 3 1 0
+block 0 0 0 sandstone
+block 1 0 0 lever[face=floor,facing=east,powered=false]
+block 2 0 0 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 3 0 0 redstone_lamp[lit=false]
+block 0 1 0 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+";
+        assert_eq!(
+            imported.schematic,
+            Format::BlockList.read(expected.as_bytes()).unwrap()
+        );
+        assert_eq!(
+            imported.unsupported,
+            vec![UnsupportedBlock {
+                pos: IntVec3::new(3, 64, 5),
+                name: "minecraft:stone".to_owned()
+            }]
+        );
+        assert!(imported.schematic.to_timeline().is_ok());
+
+        let without_substitute = import_world(&dir, &selection, None).unwrap();
+        assert_eq!(without_substitute.schematic.blocks.len(), 4);
+        assert!(
+            without_substitute.schematic.to_timeline().is_err(),
+            "wire over the stone has nothing under it"
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn reads_chunks_that_were_not_generated_as_empty() {
+        let dir = world_dir("empty");
+        let selection = Selection::new(IntVec3::new(-40, 0, 0), IntVec3::new(-20, 2, 0));
+        let imported = import_world(&dir, &selection, None).unwrap();
+        assert!(imported.schematic.blocks.is_empty());
+        assert_eq!(imported.schematic.bounds.1, IntVec3::new(20, 2, 0));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn exports_a_design_that_can_be_imported_again() {
+        let dir = world_dir("export");
+        let sandstone = "sandstone".parse().unwrap();
+        let row = Selection::new(IntVec3::new(3, 64, 5), IntVec3::new(6, 65, 5));
+        let imported = import_world(&dir, &row, Some(&sandstone)).unwrap();
+        let timeline = imported.schematic.to_timeline().unwrap();
+        export_world(&dir, timeline.design(), IntVec3::new(3, 64, 6)).unwrap();
+
+        let next_row = Selection::new(IntVec3::new(3, 64, 6), IntVec3::new(6, 65, 6));
+        let exported = import_world(&dir, &next_row, None).unwrap();
+        assert_eq!(exported.schematic, imported.schematic);
+        assert!(exported.unsupported.is_empty(), "the chest is cleared");
+        assert_eq!(
+            import_world(&dir, &row, Some(&sandstone)).unwrap(),
+            imported
+        );
+
+        let data = fs::read(dir.join("region/r.0.0.mca")).unwrap();
+        let chunk = Region::read(&data).unwrap().chunk(0, 0).unwrap().unwrap();
+        assert_eq!(chunk.get("isLightOn"), Some(&Tag::Byte(0)));
+        let entities = chunk.get("block_entities").and_then(Tag::as_list).unwrap();
+        assert_eq!(entities.len(), 1);
+        assert_eq!(entities[0].get("y"), Some(&Tag::Int(70)));
+
+        let err = export_world(&dir, timeline.design(), IntVec3::new(-40, 64, 0)).unwrap_err();
+        assert_eq!(err.to_string(), "chunk -3 0 has not been generated");
+        let err = export_world(&dir, timeline.design(), IntVec3::new(0, 79, 0)).unwrap_err();
+        assert_eq!(err.to_string(), "height 80 is outside of the world");
+        assert_eq!(fs::read(dir.join("region/r.0.0.mca")).unwrap(), data);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,326 @@
+//! Named Binary Tag, the binary format that Minecraft stores world data in. Values are big endian,
+//! and strings are stored with a 16 bit length. Minecraft writes strings in a modified form of
+//! UTF-8 that only differs from UTF-8 for characters that do not come up in block names, so
+//! strings are read as UTF-8.
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+
+/// Compounds and lists nested deeper than this are treated as damaged data rather than read
+/// recursively. Minecraft uses the same limit.
+const MAX_DEPTH: usize = 512;
+
+const END: u8 = 0;
+const BYTE: u8 = 1;
+const SHORT: u8 = 2;
+const INT: u8 = 3;
+const LONG: u8 = 4;
+const FLOAT: u8 = 5;
+const DOUBLE: u8 = 6;
+const BYTE_ARRAY: u8 = 7;
+const STRING: u8 = 8;
+const LIST: u8 = 9;
+const COMPOUND: u8 = 10;
+const INT_ARRAY: u8 = 11;
+const LONG_ARRAY: u8 = 12;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<u8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(BTreeMap<String, Tag>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    /// Looks up a named tag in a compound. Returns `None` for tags that are not compounds.
+    pub fn get(&self, name: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(entries) => entries.get(name),
+            _ => None,
+        }
+    }
+
+    /// Reads any integer tag as an `i64`, since Minecraft is not consistent about the size it
+    /// uses for small numbers such as section heights.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Tag::Byte(value) => Some(value.into()),
+            Tag::Short(value) => Some(value.into()),
+            Tag::Int(value) => Some(value.into()),
+            Tag::Long(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Tag]> {
+        match self {
+            Tag::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_compound(&self) -> Option<&BTreeMap<String, Tag>> {
+        match self {
+            Tag::Compound(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    pub fn as_list_mut(&mut self) -> Option<&mut Vec<Tag>> {
+        match self {
+            Tag::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_compound_mut(&mut self) -> Option<&mut BTreeMap<String, Tag>> {
+        match self {
+            Tag::Compound(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    pub fn as_long_array(&self) -> Option<&[i64]> {
+        match self {
+            Tag::LongArray(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    fn id(&self) -> u8 {
+        match self {
+            Tag::Byte(_) => BYTE,
+            Tag::Short(_) => SHORT,
+            Tag::Int(_) => INT,
+            Tag::Long(_) => LONG,
+            Tag::Float(_) => FLOAT,
+            Tag::Double(_) => DOUBLE,
+            Tag::ByteArray(_) => BYTE_ARRAY,
+            Tag::String(_) => STRING,
+            Tag::List(_) => LIST,
+            Tag::Compound(_) => COMPOUND,
+            Tag::IntArray(_) => INT_ARRAY,
+            Tag::LongArray(_) => LONG_ARRAY,
+        }
+    }
+}
+
+/// Reads an uncompressed NBT file. The root tag must be a compound, and its name is ignored.
+pub fn read(data: &[u8]) -> Result<Tag> {
+    let mut reader = Reader { data, pos: 0 };
+    if reader.u8()? != COMPOUND {
+        return Err(anyhow!("NBT data does not start with a compound"));
+    }
+    reader.string()?;
+    reader.payload(COMPOUND, 0)
+}
+
+/// Writes a compound as an uncompressed NBT file with an empty root name.
+pub fn write(root: &Tag) -> Vec<u8> {
+    let mut out = vec![root.id()];
+    write_string(&mut out, "");
+    write_payload(&mut out, root);
+    out
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .pos
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.pos..end))
+            .ok_or(anyhow!("NBT data ends in the middle of a tag"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    /// Reads the length of an array or list, which is stored as a signed int.
+    fn len(&mut self) -> Result<usize> {
+        let len = i32::from_be_bytes(self.array()?);
+        usize::try_from(len).map_err(|_| anyhow!("negative length in NBT data, {}", len))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = u16::from_be_bytes(self.array()?);
+        Ok(String::from_utf8_lossy(self.bytes(len.into())?).into_owned())
+    }
+
+    fn payload(&mut self, id: u8, depth: usize) -> Result<Tag> {
+        if depth > MAX_DEPTH {
+            return Err(anyhow!("NBT data is nested too deeply"));
+        }
+        let tag = match id {
+            BYTE => Tag::Byte(i8::from_be_bytes(self.array()?)),
+            SHORT => Tag::Short(i16::from_be_bytes(self.array()?)),
+            INT => Tag::Int(i32::from_be_bytes(self.array()?)),
+            LONG => Tag::Long(i64::from_be_bytes(self.array()?)),
+            FLOAT => Tag::Float(f32::from_be_bytes(self.array()?)),
+            DOUBLE => Tag::Double(f64::from_be_bytes(self.array()?)),
+            BYTE_ARRAY => {
+                let len = self.len()?;
+                Tag::ByteArray(self.bytes(len)?.to_vec())
+            }
+            STRING => Tag::String(self.string()?),
+            LIST => {
+                let item_id = self.u8()?;
+                let len = self.len()?;
+                if item_id == END && len > 0 {
+                    return Err(anyhow!("NBT list of end tags"));
+                }
+                let items = (0..len)
+                    .map(|_| self.payload(item_id, depth + 1))
+                    .collect::<Result<_>>()?;
+                Tag::List(items)
+            }
+            COMPOUND => {
+                let mut entries = BTreeMap::new();
+                loop {
+                    let entry_id = self.u8()?;
+                    if entry_id == END {
+                        break;
+                    }
+                    let name = self.string()?;
+                    entries.insert(name, self.payload(entry_id, depth + 1)?);
+                }
+                Tag::Compound(entries)
+            }
+            INT_ARRAY => {
+                let len = self.len()?;
+                let values = (0..len)
+                    .map(|_| Ok(i32::from_be_bytes(self.array()?)))
+                    .collect::<Result<_>>()?;
+                Tag::IntArray(values)
+            }
+            LONG_ARRAY => {
+                let len = self.len()?;
+                let values = (0..len)
+                    .map(|_| Ok(i64::from_be_bytes(self.array()?)))
+                    .collect::<Result<_>>()?;
+                Tag::LongArray(values)
+            }
+            _ => return Err(anyhow!("unknown NBT tag type, {}", id)),
+        };
+        Ok(tag)
+    }
+}
+
+fn write_string(out: &mut Vec<u8>, value: &str) {
+    out.extend((value.len() as u16).to_be_bytes());
+    out.extend(value.as_bytes());
+}
+
+fn write_payload(out: &mut Vec<u8>, tag: &Tag) {
+    match tag {
+        Tag::Byte(value) => out.extend(value.to_be_bytes()),
+        Tag::Short(value) => out.extend(value.to_be_bytes()),
+        Tag::Int(value) => out.extend(value.to_be_bytes()),
+        Tag::Long(value) => out.extend(value.to_be_bytes()),
+        Tag::Float(value) => out.extend(value.to_be_bytes()),
+        Tag::Double(value) => out.extend(value.to_be_bytes()),
+        Tag::ByteArray(values) => {
+            out.extend((values.len() as i32).to_be_bytes());
+            out.extend(values);
+        }
+        Tag::String(value) => write_string(out, value),
+        Tag::List(items) => {
+            // Minecraft writes empty lists with the type of end tags.
+            out.push(items.first().map_or(END, Tag::id));
+            out.extend((items.len() as i32).to_be_bytes());
+            for item in items {
+                write_payload(out, item);
+            }
+        }
+        Tag::Compound(entries) => {
+            for (name, entry) in entries {
+                out.push(entry.id());
+                write_string(out, name);
+                write_payload(out, entry);
+            }
+            out.push(END);
+        }
+        Tag::IntArray(values) => {
+            out.extend((values.len() as i32).to_be_bytes());
+            for value in values {
+                out.extend(value.to_be_bytes());
+            }
+        }
+        Tag::LongArray(values) => {
+            out.extend((values.len() as i32).to_be_bytes());
+            for value in values {
+                out.extend(value.to_be_bytes());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{read, write, Tag};
+
+    #[test]
+    fn reads_what_it_writes() {
+        let root = Tag::Compound(BTreeMap::from([
+            ("DataVersion".to_owned(), Tag::Int(3120)),
+            (
+                "Status".to_owned(),
+                Tag::String("minecraft:full".to_owned()),
+            ),
+            (
+                "sections".to_owned(),
+                Tag::List(vec![Tag::Compound(BTreeMap::from([
+                    ("Y".to_owned(), Tag::Byte(-4)),
+                    ("data".to_owned(), Tag::LongArray(vec![-1, 0, 1 << 40])),
+                ]))]),
+            ),
+            ("empty".to_owned(), Tag::List(vec![])),
+            ("heights".to_owned(), Tag::IntArray(vec![64, -64])),
+        ]));
+        let data = write(&root);
+        assert_eq!(&data[..3], &[10, 0, 0], "compound with an empty name");
+        assert_eq!(read(&data).unwrap(), root);
+        assert_eq!(
+            read(&data)
+                .unwrap()
+                .get("sections")
+                .unwrap()
+                .as_list()
+                .unwrap()[0]
+                .get("Y")
+                .and_then(Tag::as_i64),
+            Some(-4)
+        );
+        assert!(read(&data[..data.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,180 @@
+//! Region files, named like `r.<x>.<z>.mca`, each hold 32 by 32 chunks. A region file starts with
+//! a table of 1024 chunk locations, each a 3 byte offset and a 1 byte length counted in 4096 byte
+//! sectors, followed by a table of 1024 timestamps. Each chunk is stored at its offset as a 4 byte
+//! length, a byte for the compression type, and the compressed NBT data of the chunk.
+
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Result};
+use flate2::{
+    read::{GzDecoder, ZlibDecoder},
+    write::ZlibEncoder,
+    Compression,
+};
+
+use super::nbt::{self, Tag};
+
+/// Width of a region in chunks
+pub const REGION_CHUNKS: i32 = 32;
+
+const SECTOR_LEN: usize = 4096;
+const CHUNK_COUNT: usize = (REGION_CHUNKS * REGION_CHUNKS) as usize;
+const HEADER_LEN: usize = 2 * SECTOR_LEN;
+
+/// Chunks must fit in the number of sectors that one byte of a location can count.
+const MAX_CHUNK_SECTORS: usize = 255;
+
+const GZIP: u8 = 1;
+const ZLIB: u8 = 2;
+const UNCOMPRESSED: u8 = 3;
+
+/// Set in the compression type of chunks that are too large for the region file, and are stored
+/// in a separate `.mcc` file instead.
+const EXTERNAL: u8 = 0x80;
+
+/// The chunks of a region file, still compressed.
+#[derive(Clone, Debug)]
+pub struct Region {
+    chunks: Vec<Option<StoredChunk>>,
+}
+
+#[derive(Clone, Debug)]
+struct StoredChunk {
+    /// Time of the last change to the chunk, in seconds since the Unix epoch
+    timestamp: u32,
+    compression: u8,
+    data: Vec<u8>,
+}
+
+impl Region {
+    /// Path of the region file that holds a chunk.
+    pub fn path(region_dir: &Path, chunk_x: i32, chunk_z: i32) -> PathBuf {
+        region_dir.join(format!(
+            "r.{}.{}.mca",
+            chunk_x.div_euclid(REGION_CHUNKS),
+            chunk_z.div_euclid(REGION_CHUNKS)
+        ))
+    }
+
+    /// Reads the contents of a region file. Minecraft sometimes leaves empty region files behind,
+    /// which are read as regions without chunks.
+    pub fn read(data: &[u8]) -> Result<Self> {
+        let mut chunks = vec![None; CHUNK_COUNT];
+        if data.is_empty() {
+            return Ok(Region { chunks });
+        }
+        if data.len() < HEADER_LEN {
+            return Err(anyhow!("region file is too short"));
+        }
+        let u32_at = |pos: usize| u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap());
+        for (index, chunk) in chunks.iter_mut().enumerate() {
+            let location = u32_at(index * 4);
+            let offset = (location >> 8) as usize * SECTOR_LEN;
+            if offset == 0 {
+                continue;
+            }
+            let damaged = || {
+                anyhow!(
+                    "chunk {} {} of the region file is damaged",
+                    index as i32 % REGION_CHUNKS,
+                    index as i32 / REGION_CHUNKS
+                )
+            };
+            if data.len() < offset + 5 {
+                return Err(damaged());
+            }
+            // The length includes the compression type.
+            let len = u32_at(offset) as usize;
+            let compressed = data
+                .get(offset + 5..offset + 4 + len)
+                .filter(|_| len > 0)
+                .ok_or_else(damaged)?;
+            *chunk = Some(StoredChunk {
+                timestamp: u32_at(SECTOR_LEN + index * 4),
+                compression: data[offset + 4],
+                data: compressed.to_vec(),
+            });
+        }
+        Ok(Region { chunks })
+    }
+
+    /// Decompresses a chunk, given in world chunk coordinates. Returns `None` for chunks that have
+    /// not been generated.
+    pub fn chunk(&self, chunk_x: i32, chunk_z: i32) -> Result<Option<Tag>> {
+        let stored = match &self.chunks[chunk_index(chunk_x, chunk_z)] {
+            Some(stored) => stored,
+            None => return Ok(None),
+        };
+        let mut data = vec![];
+        match stored.compression {
+            GZIP => GzDecoder::new(&stored.data[..]).read_to_end(&mut data)?,
+            ZLIB => ZlibDecoder::new(&stored.data[..]).read_to_end(&mut data)?,
+            UNCOMPRESSED => stored.data.as_slice().read_to_end(&mut data)?,
+            compression if compression & EXTERNAL != 0 => {
+                return Err(anyhow!(
+                    "chunk is stored in a separate .mcc file, which is not supported"
+                ))
+            }
+            compression => {
+                return Err(anyhow!(
+                    "chunk uses compression type {}, which is not supported",
+                    compression
+                ))
+            }
+        };
+        Ok(Some(nbt::read(&data)?))
+    }
+
+    /// Replaces a chunk, given in world chunk coordinates, and marks it as changed now.
+    pub fn set_chunk(&mut self, chunk_x: i32, chunk_z: i32, chunk: &Tag) {
+        let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+        // Writing to a vector cannot fail.
+        encoder.write_all(&nbt::write(chunk)).unwrap();
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs() as u32);
+        self.chunks[chunk_index(chunk_x, chunk_z)] = Some(StoredChunk {
+            timestamp,
+            compression: ZLIB,
+            data: encoder.finish().unwrap(),
+        });
+    }
+
+    /// Lays out the chunks one after another after the header, each starting at a new sector.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut out = vec![0; HEADER_LEN];
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            let stored = match chunk {
+                Some(stored) => stored,
+                None => continue,
+            };
+            let offset = out.len() / SECTOR_LEN;
+            out.extend((stored.data.len() as u32 + 1).to_be_bytes());
+            out.push(stored.compression);
+            out.extend(&stored.data);
+            out.resize((out.len() + SECTOR_LEN - 1) / SECTOR_LEN * SECTOR_LEN, 0);
+            let sectors = out.len() / SECTOR_LEN - offset;
+            if sectors > MAX_CHUNK_SECTORS {
+                return Err(anyhow!(
+                    "chunk {} {} is too large for a region file",
+                    index as i32 % REGION_CHUNKS,
+                    index as i32 / REGION_CHUNKS
+                ));
+            }
+            let location = (offset as u32) << 8 | sectors as u32;
+            out[index * 4..index * 4 + 4].copy_from_slice(&location.to_be_bytes());
+            out[SECTOR_LEN + index * 4..SECTOR_LEN + index * 4 + 4]
+                .copy_from_slice(&stored.timestamp.to_be_bytes());
+        }
+        Ok(out)
+    }
+}
+
+/// Index of a chunk in the tables at the start of its region file.
+fn chunk_index(chunk_x: i32, chunk_z: i32) -> usize {
+    (chunk_x.rem_euclid(REGION_CHUNKS) + chunk_z.rem_euclid(REGION_CHUNKS) * REGION_CHUNKS) as usize
+}
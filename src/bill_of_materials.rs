@@ -0,0 +1,255 @@
+use std::{collections::BTreeMap, fmt};
+
+use crate::timeline::WorldState;
+
+/// Number of stacks that fit in a shulker box
+pub const SHULKER_BOX_SLOTS: u32 = 27;
+
+/// Crafting recipes used to expand a bill of materials into raw materials. Each recipe gives the
+/// number of items that one craft produces, and the items that it uses. A recipe is listed before
+/// the recipes for its ingredients so that all of the demand for an ingredient is known before
+/// that ingredient is expanded.
+const RECIPES: [Recipe; 9] = [
+    (
+        "redstone repeater",
+        1,
+        &[("stone", 3), ("redstone torch", 2), ("redstone dust", 1)],
+    ),
+    (
+        "redstone lamp",
+        1,
+        &[("redstone dust", 4), ("glowstone", 1)],
+    ),
+    ("redstone torch", 1, &[("stick", 1), ("redstone dust", 1)]),
+    ("lever", 1, &[("stick", 1), ("cobblestone", 1)]),
+    ("iron block", 1, &[("iron ingot", 9)]),
+    ("sandstone", 1, &[("sand", 4)]),
+    ("glowstone", 1, &[("glowstone dust", 4)]),
+    ("stick", 4, &[("oak planks", 2)]),
+    ("oak planks", 4, &[("oak log", 1)]),
+];
+
+/// Output item, number of items produced by one craft, and ingredients with their counts
+type Recipe = (&'static str, u32, &'static [(&'static str, u32)]);
+
+/// Number of each item that is needed to build a design.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BillOfMaterials {
+    /// Item counts ordered by item name
+    pub items: BTreeMap<String, u32>,
+}
+
+impl BillOfMaterials {
+    /// Counts the blocks in the world. Blocks of the same type count as the same item regardless
+    /// of their state.
+    pub fn from_world(world: &WorldState) -> Self {
+        let mut items = BTreeMap::new();
+        for (_, state) in world.blocks() {
+            *items.entry(item_name(&state.block_type)).or_default() += 1;
+        }
+        BillOfMaterials { items }
+    }
+
+    /// Replaces craftable items with the raw materials used to craft them. Recipes that produce
+    /// more than one item are crafted enough times to cover the total demand, so some items may
+    /// be left over.
+    pub fn expand_crafting(&self) -> Self {
+        let mut items = self.items.clone();
+        for (output, yields, ingredients) in RECIPES {
+            let needed = match items.remove(output) {
+                Some(needed) => needed,
+                None => continue,
+            };
+            let crafts = (needed + yields - 1) / yields;
+            for (ingredient, count) in ingredients {
+                *items.entry(ingredient.to_string()).or_default() += crafts * count;
+            }
+        }
+        BillOfMaterials { items }
+    }
+
+    /// Formats the bill with one row per item. Columns are the item name, the total count, the
+    /// stack size for the item, the number of full stacks and the remainder, and the number of
+    /// shulker boxes needed to carry the item.
+    pub fn to_csv(&self) -> String {
+        let mut csv = "item,count,stack_size,stacks,remainder,shulker_boxes\n".to_owned();
+        for (item, count) in self.items.iter() {
+            let amount = Amount::new(item, *count);
+            csv.push_str(&format!(
+                "{},{},{},{},{},{}\n",
+                item,
+                count,
+                amount.stack_size,
+                amount.stacks,
+                amount.remainder,
+                amount.shulker_boxes()
+            ));
+        }
+        csv
+    }
+}
+
+/// A count of items expressed in stacks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Amount {
+    pub stack_size: u32,
+    pub stacks: u32,
+    pub remainder: u32,
+}
+
+impl Amount {
+    pub fn new(item: &str, count: u32) -> Self {
+        let stack_size = stack_size(item);
+        Amount {
+            stack_size,
+            stacks: count / stack_size,
+            remainder: count % stack_size,
+        }
+    }
+
+    /// Number of shulker boxes needed to hold the items, counting a partial box as a whole one.
+    pub fn shulker_boxes(&self) -> u32 {
+        let stacks = self.stacks + (self.remainder > 0) as u32;
+        (stacks + SHULKER_BOX_SLOTS - 1) / SHULKER_BOX_SLOTS
+    }
+}
+
+/// Formats the amount as it would be gathered, such as "2 stacks + 5". Amounts of more than one
+/// shulker box also give the number of boxes.
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count =
+            |n: u32, one: &str, many: &str| format!("{} {}", n, if n == 1 { one } else { many });
+        let mut parts = vec![];
+        if self.stacks > 0 {
+            parts.push(count(self.stacks, "stack", "stacks"));
+        }
+        if self.remainder > 0 || self.stacks == 0 {
+            parts.push(self.remainder.to_string());
+        }
+        write!(f, "{}", parts.join(" + "))?;
+        if self.stacks >= SHULKER_BOX_SLOTS {
+            write!(
+                f,
+                " ({})",
+                count(self.shulker_boxes(), "shulker box", "shulker boxes")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats the bill as a table with the count of each item, and the count in stacks.
+impl fmt::Display for BillOfMaterials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.items.keys().map(|item| item.len()).max().unwrap_or(0);
+        for (item, count) in self.items.iter() {
+            writeln!(
+                f,
+                "{:<width$}  {:>6}  {}",
+                item,
+                count,
+                Amount::new(item, *count),
+                width = width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The name of the item that places blocks of the given type.
+pub fn item_name(block_type: &str) -> String {
+    match block_type {
+        "redstone_wire" => "redstone dust".to_owned(),
+        "repeater" => "redstone repeater".to_owned(),
+        "comparator" => "redstone comparator".to_owned(),
+        "wall_torch" | "redstone_wall_torch" => item_name(&block_type.replace("wall_", "")),
+        _ => block_type.replace('_', " "),
+    }
+}
+
+fn stack_size(item: &str) -> u32 {
+    if item.ends_with(" sign") || item.ends_with(" banner") {
+        16
+    } else {
+        64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{block_state::BlockState, int_vec3::IntVec3, timeline::WorldState};
+
+    use super::{Amount, BillOfMaterials};
+
+    const WIRE: &str = "east=none,north=none,south=none,west=none,power=0";
+
+    fn world() -> WorldState {
+        let mut world = WorldState::new((IntVec3::ZERO, IntVec3::ONE * 15));
+        let mut place = |pos: IntVec3, block_type: &str, state_values: &str| {
+            world
+                .insert_block(pos, BlockState::new(block_type, state_values))
+                .unwrap();
+        };
+        place(
+            IntVec3::new(0, 0, 0),
+            "lever",
+            "face=floor,facing=north,powered=false",
+        );
+        place(
+            IntVec3::new(1, 0, 0),
+            "repeater",
+            "delay=1,facing=west,locked=false,powered=false",
+        );
+        for x in 2..5 {
+            place(IntVec3::new(x, 0, 0), "redstone_wire", WIRE);
+        }
+        world
+    }
+
+    #[test]
+    fn counts_blocks_by_item() {
+        let bill = BillOfMaterials::from_world(&world());
+        assert_eq!(
+            bill.to_string(),
+            "lever                   1  1\n\
+             redstone dust           3  3\n\
+             redstone repeater       1  1\n"
+        );
+    }
+
+    #[test]
+    fn expands_crafting_recipes_to_raw_materials() {
+        let bill = BillOfMaterials::from_world(&world()).expand_crafting();
+        let items: Vec<_> = bill
+            .items
+            .iter()
+            .map(|(item, count)| (item.as_str(), *count))
+            .collect();
+        // The lever and the two torches in the repeater need three sticks, which take one craft.
+        assert_eq!(
+            items,
+            vec![
+                ("cobblestone", 1),
+                ("oak log", 1),
+                ("redstone dust", 6),
+                ("stone", 3),
+            ]
+        );
+        assert_eq!(
+            bill.to_csv().lines().nth(3),
+            Some("redstone dust,6,64,0,6,1")
+        );
+    }
+
+    #[test]
+    fn formats_amounts_in_stacks_and_shulker_boxes() {
+        assert_eq!(Amount::new("stone", 130).to_string(), "2 stacks + 2");
+        assert_eq!(Amount::new("oak sign", 16).to_string(), "1 stack");
+        assert_eq!(
+            Amount::new("stone", 64 * 30).to_string(),
+            "30 stacks (2 shulker boxes)"
+        );
+        assert_eq!(Amount::new("stone", 64 * 27 + 1).shulker_boxes(), 2);
+    }
+}
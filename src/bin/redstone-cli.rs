@@ -0,0 +1,1008 @@
+use std::{
+    env, fs,
+    io::{self, BufRead, Read},
+    path::Path,
+    process::ExitCode,
+    slice,
+    str::FromStr,
+};
+
+use anyhow::Context;
+use redstone_designer::{
+    anvil::{export_world, import_world},
+    bill_of_materials::BillOfMaterials,
+    block_state::{face_from_name, BlockState, BlockTransform},
+    circuit_library::{Port, PortKind, Template, LIBRARY_DIR, TEMPLATE_EXTENSION},
+    design_diff::DesignDiff,
+    int_vec3::IntVec3,
+    logic_analyzer::{Probe, Waveform},
+    schematic::{merge, Format, Merge, RejectedBlock, Schematic, Side},
+    selection::{fill, hollow, replace, stack_selection, Selection, Stack},
+    survival::survival_issues,
+    synthesis::{synthesize, Netlist},
+    timeline::{InputSchedule, InvalidInput, TimeIndex, Timeline},
+    truth_table::{TruthTable, MAX_INPUTS},
+};
+
+const USAGE: &str = "\
+usage: redstone-cli <command> [arguments]
+
+commands:
+    convert <input> <output>
+        Converts a design between file formats. Formats are picked by file extension: .blocks
+        lists one block per line, and .layers draws each layer as a grid of characters.
+
+    simulate <design> [--ticks <n>] [--probe <x,y,z>]... [--schedule <file>] [--vcd <file>]
+        Runs a design for n ticks (default 20), and prints the power level at each probe for
+        every tick, followed by the period of the cycle that the design settles into if there
+        is one. Probes default to every lever and lamp in the design. --schedule replaces the
+        input schedule stored in the design, and --vcd writes the recording to a file.
+
+    validate <design>... [--survival]
+        Checks that every block in each design can be placed. --survival also checks that a
+        player in survival mode could build each design as it is, without support blocks that
+        are not in the design.
+
+    truth-table <design> [--input <x,y,z>]... [--output <x,y,z>]... [--max-ticks <n>]
+        Simulates every combination of inputs until the design stops changing, and prints the
+        outputs with the number of ticks that each combination took to settle. Inputs must be
+        levers, and default to every lever in the design. Outputs default to every lamp.
+        Combinations that oscillate are marked with their period, and combinations that are
+        still changing after n ticks (default 100) are marked unstable.
+
+    materials <design> [--raw] [--csv <file>]
+        Prints the number of each item needed to build a design, in stacks. --raw expands
+        crafted items into the materials used to craft them, and --csv writes the list to a
+        file.
+
+    synthesize (<netlist> | --expr <expression>) <output>
+        Builds a design from a netlist file or a single boolean expression, with a lever for
+        each input and a lamp for each output, and prints their positions. See the netlist
+        format in the synthesis module.
+
+    template <design> <output> [--name <name>] [--input <name>=<x,y,z>]...
+             [--output <name>=<x,y,z>]...
+        Saves a design as a circuit template with named ports. Ports default to every lever
+        and lamp in the design. Templates in the library directory are listed in the block
+        picker.
+
+    fill <design> <x,y,z> <x,y,z> <block> [--output <file>]
+    replace <design> <x,y,z> <x,y,z> <from> <to> [--output <file>]
+    hollow <design> <x,y,z> <x,y,z> <block> [--output <file>]
+        Edits the box between two corners. fill sets every position to a block, replace swaps
+        blocks that match <from> for <to>, and hollow builds walls, floor and ceiling around
+        the box and clears its inside. Blocks are written like repeater[facing=north], and a
+        <from> block without properties matches every state of its type. Prints how many
+        blocks were placed, and how many placements were rejected for each reason. The design
+        is edited in place unless --output is given.
+
+    stack <design> <x,y,z> <x,y,z> <count> <direction> [--spacing <n>] [--offset <x,y,z>]
+          [--alternate (mirror-x | mirror-z)] [--output <file>]
+        Repeats the box between two corners count more times toward north, south, east, west,
+        up or down. --spacing leaves empty positions between copies, --offset moves each copy
+        further, and --alternate mirrors every other copy so that neighbors can share wiring.
+        Nothing is placed if any copy does not fit.
+
+    resize <design> <x,y,z> <x,y,z> [--output <file>]
+        Changes the bounds of a design to the box between two corners, and prints each block
+        that would no longer fit. The design is only written if every block fits.
+
+    diff <before> <after>
+    diff <design> --ticks <from> <to>
+        Prints each block that differs between two designs, or between the states of one
+        design at two ticks, bottom layer first. Lines start with + for added blocks, - for
+        removed blocks and ~ for blocks with a different type or properties, and are followed
+        by a count of each.
+
+    merge <base> <ours> <theirs> [--output <file>] [--interactive]
+        Merges the changes that ours and theirs made to base. Positions that both sides changed
+        in different ways are printed as conflicts and keep our block, unless --interactive
+        asks which block to keep for each one. The result is written to ours unless --output is
+        given.
+
+    merge-driver <base> <ours> <theirs> <path>
+        Merges like merge, for git to call with temporary files. The file format is picked by
+        the extension of path. To use it, add this to .git/config:
+            [merge \"redstone\"]
+                driver = redstone-cli merge-driver %O %A %B %P
+        and this to .gitattributes:
+            *.blocks merge=redstone
+
+    share <design>
+        Prints a design as a short string that can be pasted into chat.
+
+    import (<string> | -) <output>
+        Writes a design from a string printed by share. - reads the string from standard input,
+        for pasting from the clipboard like: pbpaste | redstone-cli import - circuit.blocks
+
+    import-world <world> <x,y,z> <x,y,z> <output> [--substitute <block>]
+        Writes a design from the box between two corners of the overworld of a Minecraft Java
+        Edition world save, from version 1.18 on. Prints the number of each block that is not
+        supported. Those blocks are left out, unless --substitute gives a block to put in their
+        place, such as sandstone so that wire resting on them can still be placed.
+
+    export-world <design> <world> <x,y,z>
+        Builds a design into the overworld of a Minecraft Java Edition world save, from version
+        1.18 on, with the lowest corner of its bounds at x,y,z. Every position in the bounds is
+        replaced, and positions that are empty in the design are cleared. Close the world in
+        Minecraft and make a backup of it first.
+
+exit codes:
+    0  success
+    1  could not read or write a file
+    2  invalid arguments
+    3  a design has blocks that cannot be placed
+    4  a design could not be simulated
+    5  a merge has unresolved conflicts";
+
+const DEFAULT_TICKS: TimeIndex = 20;
+const DEFAULT_MAX_SETTLING_TICKS: TimeIndex = 100;
+
+enum Failure {
+    Usage(String),
+    Error(anyhow::Error),
+    InvalidDesign,
+    InvalidInput(InvalidInput),
+    Conflicted,
+}
+
+impl From<anyhow::Error> for Failure {
+    fn from(err: anyhow::Error) -> Self {
+        Failure::Error(err)
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Failure::Error(err)) => {
+            eprintln!("error: {:#}", err);
+            ExitCode::from(1)
+        }
+        Err(Failure::Usage(message)) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            ExitCode::from(2)
+        }
+        Err(Failure::InvalidDesign) => ExitCode::from(3),
+        Err(Failure::InvalidInput(err)) => {
+            eprintln!("simulation error: {:?}", err);
+            ExitCode::from(4)
+        }
+        Err(Failure::Conflicted) => ExitCode::from(5),
+    }
+}
+
+fn run(args: &[String]) -> Result<(), Failure> {
+    let (command, args) = args
+        .split_first()
+        .ok_or(Failure::Usage("missing command".to_owned()))?;
+    match command.as_str() {
+        "convert" => convert(args),
+        "simulate" => simulate(args),
+        "validate" => validate(args),
+        "truth-table" => truth_table(args),
+        "materials" => materials(args),
+        "synthesize" => synthesize_design(args),
+        "template" => save_template(args),
+        "fill" | "replace" | "hollow" => edit_region(command, args),
+        "stack" => stack(args),
+        "resize" => resize(args),
+        "diff" => diff(args),
+        "merge" => merge_designs(args),
+        "merge-driver" => merge_driver(args),
+        "share" => share(args),
+        "import" => import(args),
+        "import-world" => import_world_box(args),
+        "export-world" => export_world_at(args),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(Failure::Usage(format!("unknown command, {}", command))),
+    }
+}
+
+fn convert(args: &[String]) -> Result<(), Failure> {
+    let (input, output) = match args {
+        [input, output] => (Path::new(input), Path::new(output)),
+        _ => return Err(Failure::Usage("convert takes two files".to_owned())),
+    };
+    let schematic = read_schematic(input)?;
+    load_timeline(input, &schematic)?;
+    write_schematic(output, &schematic)?;
+    Ok(())
+}
+
+fn simulate(args: &[String]) -> Result<(), Failure> {
+    let mut ticks = DEFAULT_TICKS;
+    let mut probes = vec![];
+    let mut schedule_file = None;
+    let mut vcd_file = None;
+    let values = parse_options(args, |option, values| {
+        match option {
+            "--ticks" => ticks = values.parse()?,
+            "--probe" => probes.push(Probe::at(parse_position(values.next()?)?)),
+            "--schedule" => schedule_file = Some(values.next()?),
+            "--vcd" => vcd_file = Some(values.next()?),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+    let design = design_file(&values)?;
+
+    let mut schematic = read_schematic(design)?;
+    if let Some(file) = schedule_file {
+        let text = fs::read_to_string(file).with_context(|| format!("could not read {}", file))?;
+        schematic.schedule = InputSchedule::parse(&text)?;
+    }
+    let mut timeline = load_timeline(design, &schematic)?;
+    check_schedule(&timeline).map_err(Failure::InvalidInput)?;
+    if probes.is_empty() {
+        probes = probes_for(&timeline, &["lever", "redstone_lamp"]);
+    }
+
+    let waveform = Waveform::capture(&mut timeline, &probes, 0..=ticks);
+    let names: Vec<&str> = probes.iter().map(|probe| probe.name.as_str()).collect();
+    println!("tick\t{}", names.join("\t"));
+    for time in 0..=ticks {
+        let levels: Vec<String> = (0..probes.len())
+            .map(|index| waveform.level_at(index, time).unwrap_or(0).to_string())
+            .collect();
+        println!("{}\t{}", time, levels.join("\t"));
+    }
+    match timeline.cycle() {
+        Some(cycle) if cycle.period == 1 => {
+            println!("# steady state from tick {}", cycle.start)
+        }
+        Some(cycle) => println!(
+            "# repeats every {} ticks from tick {}",
+            cycle.period, cycle.start
+        ),
+        None => (),
+    }
+    if let Some(file) = vcd_file {
+        fs::write(file, waveform.to_vcd()).with_context(|| format!("could not write {}", file))?;
+    }
+    Ok(())
+}
+
+fn validate(args: &[String]) -> Result<(), Failure> {
+    let mut survival = false;
+    let files = parse_options(args, |option, _| {
+        survival |= option == "--survival";
+        Ok(option == "--survival")
+    })?;
+    if files.is_empty() {
+        return Err(Failure::Usage("missing design file".to_owned()));
+    }
+    let mut is_valid = true;
+    for file in files {
+        let path = Path::new(file);
+        let timeline = match load_timeline(path, &read_schematic(path)?) {
+            Ok(timeline) => timeline,
+            Err(Failure::InvalidDesign) => {
+                is_valid = false;
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        let issues = if survival {
+            survival_issues(timeline.design())
+        } else {
+            vec![]
+        };
+        for issue in issues.iter() {
+            eprintln!("{}: not survival legal: {}", path.display(), issue);
+        }
+        if issues.is_empty() {
+            println!("{}: ok", path.display());
+        } else {
+            is_valid = false;
+        }
+    }
+    if is_valid {
+        Ok(())
+    } else {
+        Err(Failure::InvalidDesign)
+    }
+}
+
+fn truth_table(args: &[String]) -> Result<(), Failure> {
+    let mut inputs = vec![];
+    let mut outputs = vec![];
+    let mut max_ticks = DEFAULT_MAX_SETTLING_TICKS;
+    let values = parse_options(args, |option, values| {
+        match option {
+            "--input" => inputs.push(Probe::at(parse_position(values.next()?)?)),
+            "--output" => outputs.push(Probe::at(parse_position(values.next()?)?)),
+            "--max-ticks" => max_ticks = values.parse()?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+    let design = design_file(&values)?;
+
+    let timeline = load_timeline(design, &read_schematic(design)?)?;
+    if inputs.is_empty() {
+        inputs = probes_for(&timeline, &["lever"]);
+    }
+    if outputs.is_empty() {
+        outputs = probes_for(&timeline, &["redstone_lamp"]);
+    }
+    if inputs.len() > MAX_INPUTS {
+        return Err(Failure::Usage(format!(
+            "truth tables are limited to {} inputs",
+            MAX_INPUTS
+        )));
+    }
+    let table = TruthTable::generate(&timeline, &inputs, &outputs, max_ticks)
+        .map_err(Failure::InvalidInput)?;
+    print!("{}", table);
+    Ok(())
+}
+
+fn materials(args: &[String]) -> Result<(), Failure> {
+    let mut raw = false;
+    let mut csv_file = None;
+    let values = parse_options(args, |option, values| {
+        match option {
+            "--raw" => raw = true,
+            "--csv" => csv_file = Some(values.next()?),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+    let design = design_file(&values)?;
+
+    let timeline = load_timeline(design, &read_schematic(design)?)?;
+    let mut bill = BillOfMaterials::from_world(timeline.design());
+    if raw {
+        bill = bill.expand_crafting();
+    }
+    print!("{}", bill);
+    if let Some(file) = csv_file {
+        fs::write(file, bill.to_csv()).with_context(|| format!("could not write {}", file))?;
+    }
+    Ok(())
+}
+
+fn synthesize_design(args: &[String]) -> Result<(), Failure> {
+    let mut expression = None;
+    let values = parse_options(args, |option, values| {
+        match option {
+            "--expr" => expression = Some(values.next()?),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+    let (netlist, output) = match (expression, &values[..]) {
+        (Some(expression), [output]) => (Netlist::from_expression(expression)?, output),
+        (None, [input, output]) => {
+            let text =
+                fs::read_to_string(input).with_context(|| format!("could not read {}", input))?;
+            let netlist =
+                Netlist::parse(&text).with_context(|| format!("could not parse {}", input))?;
+            (netlist, output)
+        }
+        _ => {
+            return Err(Failure::Usage(
+                "synthesize takes a netlist file or an expression, and an output file".to_owned(),
+            ))
+        }
+    };
+    let layout = synthesize(&netlist);
+    let schematic = Schematic::from_timeline(&Timeline::from_world(layout.world));
+    write_schematic(Path::new(output), &schematic)?;
+    for (kind, signals) in [("input", &layout.inputs), ("output", &layout.outputs)] {
+        for (name, pos) in signals.iter() {
+            println!("{} {} at {} {} {}", kind, name, pos.x, pos.y, pos.z);
+        }
+    }
+    Ok(())
+}
+
+fn save_template(args: &[String]) -> Result<(), Failure> {
+    let mut name = None;
+    let mut ports = vec![];
+    let files = parse_options(args, |option, values| {
+        match option {
+            "--name" => name = Some(values.next()?.clone()),
+            "--input" => ports.push(parse_port(PortKind::Input, values.next()?)?),
+            "--output" => ports.push(parse_port(PortKind::Output, values.next()?)?),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+    let (design, output) = match files[..] {
+        [design, output] => (Path::new(design), Path::new(output)),
+        _ => {
+            return Err(Failure::Usage(
+                "template takes a design file and an output file".to_owned(),
+            ))
+        }
+    };
+
+    let timeline = load_timeline(design, &read_schematic(design)?)?;
+    if ports.is_empty() {
+        for (kind, block_type, prefix) in [
+            (PortKind::Input, "lever", "in"),
+            (PortKind::Output, "redstone_lamp", "out"),
+        ] {
+            for (index, probe) in probes_for(&timeline, &[block_type]).iter().enumerate() {
+                ports.push(Port {
+                    name: format!("{}{}", prefix, index),
+                    kind,
+                    pos: probe.pos,
+                });
+            }
+        }
+    }
+    let name = name.unwrap_or_else(|| {
+        output
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    });
+    let blocks = timeline
+        .design()
+        .blocks()
+        .map(|(pos, state)| (pos, state.clone()))
+        .collect();
+    let template = Template::new(&name, blocks, ports)?;
+    fs::write(output, template.format())
+        .with_context(|| format!("could not write {}", output.display()))?;
+    if output.extension().and_then(|ext| ext.to_str()) != Some(TEMPLATE_EXTENSION) {
+        eprintln!(
+            "note: only .{} files in the {} directory are loaded into the library",
+            TEMPLATE_EXTENSION, LIBRARY_DIR
+        );
+    }
+    Ok(())
+}
+
+fn edit_region(command: &str, args: &[String]) -> Result<(), Failure> {
+    let mut output = None;
+    let values = parse_options(args, |option, values| {
+        match option {
+            "--output" => output = Some(values.next()?),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+    let block_count = if command == "replace" { 2 } else { 1 };
+    if values.len() != 3 + block_count {
+        return Err(Failure::Usage(format!(
+            "{} takes a design file, two corners and {} block{}",
+            command,
+            block_count,
+            if block_count == 1 { "" } else { "s" }
+        )));
+    }
+    let design = Path::new(values[0]);
+    let selection = Selection::new(parse_position(values[1])?, parse_position(values[2])?);
+    let blocks = values[3..]
+        .iter()
+        .map(|block| {
+            block
+                .parse::<BlockState>()
+                .map_err(|err| Failure::Usage(format!("invalid block, {}: {}", block, err)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let timeline = load_timeline(design, &read_schematic(design)?)?;
+    let mut world = timeline.design().clone();
+    let report = match command {
+        "fill" => fill(&mut world, &selection, &blocks[0]),
+        "replace" => replace(&mut world, &selection, &blocks[0], &blocks[1]),
+        _ => hollow(&mut world, &selection, &blocks[0]),
+    };
+    println!("{}", report);
+
+    let mut edited = Timeline::from_world(world);
+    edited.set_input_schedule(timeline.input_schedule().clone());
+    let output = output.map(Path::new).unwrap_or(design);
+    write_schematic(output, &Schematic::from_timeline(&edited))?;
+    Ok(())
+}
+
+fn stack(args: &[String]) -> Result<(), Failure> {
+    let mut spacing = 0;
+    let mut offset = IntVec3::ZERO;
+    let mut alternate = None;
+    let mut output = None;
+    let values = parse_options(args, |option, values| {
+        match option {
+            "--spacing" => spacing = values.parse()?,
+            "--offset" => offset = parse_position(values.next()?)?,
+            "--alternate" => {
+                alternate = Some(match values.next()?.as_str() {
+                    "mirror-x" => BlockTransform::MirrorX,
+                    "mirror-z" => BlockTransform::MirrorZ,
+                    other => {
+                        return Err(Failure::Usage(format!(
+                            "expected mirror-x or mirror-z; found {}",
+                            other
+                        )))
+                    }
+                })
+            }
+            "--output" => output = Some(values.next()?),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+    let (design, start, end, count, direction) = match values[..] {
+        [design, start, end, count, direction] => (design, start, end, count, direction),
+        _ => {
+            return Err(Failure::Usage(
+                "stack takes a design file, two corners, a count and a direction".to_owned(),
+            ))
+        }
+    };
+    let design = Path::new(design);
+    let selection = Selection::new(parse_position(start)?, parse_position(end)?);
+    let count = count
+        .parse()
+        .map_err(|_| Failure::Usage(format!("invalid count, {}", count)))?;
+    let direction = face_from_name(direction)
+        .map(IntVec3::from_block_face)
+        .ok_or(Failure::Usage(format!("invalid direction, {}", direction)))?;
+    let stack = Stack {
+        count,
+        direction,
+        spacing,
+        offset,
+        alternate,
+    };
+
+    let timeline = load_timeline(design, &read_schematic(design)?)?;
+    let mut world = timeline.design().clone();
+    let stacked = stack_selection(&mut world, &selection, &stack)
+        .map_err(|rejected| report_rejected(design, vec![rejected]))?;
+    let (min, max) = (stacked.min(), stacked.max());
+    println!(
+        "stacked {} copies from {} {} {} to {} {} {}",
+        count, min.x, min.y, min.z, max.x, max.y, max.z
+    );
+
+    let mut edited = Timeline::from_world(world);
+    edited.set_input_schedule(timeline.input_schedule().clone());
+    let output = output.map(Path::new).unwrap_or(design);
+    write_schematic(output, &Schematic::from_timeline(&edited))?;
+    Ok(())
+}
+
+fn resize(args: &[String]) -> Result<(), Failure> {
+    let mut output = None;
+    let values = parse_options(args, |option, values| {
+        match option {
+            "--output" => output = Some(values.next()?),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+    let (design, start, end) = match values[..] {
+        [design, start, end] => (Path::new(design), start, end),
+        _ => {
+            return Err(Failure::Usage(
+                "resize takes a design file and two corners".to_owned(),
+            ))
+        }
+    };
+    let bounds = Selection::new(parse_position(start)?, parse_position(end)?);
+
+    let mut timeline = load_timeline(design, &read_schematic(design)?)?;
+    timeline
+        .resize((bounds.min(), bounds.max()))
+        .map_err(|rejected| report_rejected(design, rejected))?;
+    let output = output.map(Path::new).unwrap_or(design);
+    write_schematic(output, &Schematic::from_timeline(&timeline))?;
+    Ok(())
+}
+
+fn diff(args: &[String]) -> Result<(), Failure> {
+    let mut ticks = None;
+    let values = parse_options(args, |option, values| {
+        match option {
+            "--ticks" => ticks = Some((values.parse::<TimeIndex>()?, values.parse()?)),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+    let files: Vec<&Path> = values.iter().map(Path::new).collect();
+    let (before, after) = match (&files[..], ticks) {
+        ([before, after], None) => (
+            load_timeline(before, &read_schematic(before)?)?
+                .design()
+                .clone(),
+            load_timeline(after, &read_schematic(after)?)?
+                .design()
+                .clone(),
+        ),
+        ([design], Some((from, to))) => {
+            let mut timeline = load_timeline(design, &read_schematic(design)?)?;
+            check_schedule(&timeline).map_err(Failure::InvalidInput)?;
+            timeline.simulate_to(from.max(to));
+            match (timeline.state_at(from), timeline.state_at(to)) {
+                (Some(before), Some(after)) => (before, after),
+                _ => return Err(Failure::Usage("ticks cannot be negative".to_owned())),
+            }
+        }
+        _ => {
+            return Err(Failure::Usage(
+                "diff takes two design files, or one design file and --ticks".to_owned(),
+            ))
+        }
+    };
+    let diff = DesignDiff::between(&before, &after);
+    print!("{}", diff);
+    println!("# {}", diff.summary());
+    Ok(())
+}
+
+fn merge_designs(args: &[String]) -> Result<(), Failure> {
+    let mut output = None;
+    let mut interactive = false;
+    let values = parse_options(args, |option, values| {
+        match option {
+            "--output" => output = Some(values.next()?),
+            "--interactive" => interactive = true,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+    let files: Vec<&Path> = values.iter().map(Path::new).collect();
+    let (base, ours, theirs) = match files[..] {
+        [base, ours, theirs] => (base, ours, theirs),
+        _ => {
+            return Err(Failure::Usage(
+                "merge takes base, ours and theirs design files".to_owned(),
+            ))
+        }
+    };
+    let output = output.map(Path::new).unwrap_or(ours);
+    let format = Format::from_path(output)?;
+    // Each side is read in the format of its own file, so designs can be merged across formats.
+    let sides = [
+        read_schematic(base)?,
+        read_schematic(ours)?,
+        read_schematic(theirs)?,
+    ];
+    merge_files(sides, output, output, format, interactive)
+}
+
+/// Git passes the three versions as temporary files without extensions, and expects the result
+/// to replace ours. A nonzero exit code tells git that the merge has conflicts.
+fn merge_driver(args: &[String]) -> Result<(), Failure> {
+    let (base, ours, theirs, path) = match args {
+        [base, ours, theirs, path] => (
+            Path::new(base),
+            Path::new(ours),
+            Path::new(theirs),
+            Path::new(path),
+        ),
+        _ => {
+            return Err(Failure::Usage(
+                "merge-driver takes base, ours, theirs and the path of the merged file".to_owned(),
+            ))
+        }
+    };
+    let format = Format::from_path(path)?;
+    let sides = [
+        read_schematic_as(base, format)?,
+        read_schematic_as(ours, format)?,
+        read_schematic_as(theirs, format)?,
+    ];
+    merge_files(sides, ours, path, format, false)
+}
+
+/// Writes the merge of base, ours and theirs to `output` in the given format, even if it has
+/// conflicts or blocks that cannot be placed, so that the result can be fixed up by hand.
+/// Problems are reported for the file at `path`.
+fn merge_files(
+    [base, ours, theirs]: [Schematic; 3],
+    output: &Path,
+    path: &Path,
+    format: Format,
+    interactive: bool,
+) -> Result<(), Failure> {
+    let mut merged = merge(&base, &ours, &theirs);
+    if interactive {
+        resolve_interactively(&mut merged)?;
+    }
+    write_schematic_as(output, format, &merged.schematic)?;
+    for conflict in merged.conflicts.iter() {
+        eprintln!("{}: conflict at {}", path.display(), conflict);
+    }
+    if merged.schedule_conflict {
+        eprintln!(
+            "{}: conflict in the input schedule, kept ours",
+            path.display()
+        );
+    }
+    let placed = merged
+        .schematic
+        .to_timeline()
+        .map(|_| ())
+        .map_err(|rejected| report_rejected(path, rejected));
+    if !merged.is_clean() {
+        return Err(Failure::Conflicted);
+    }
+    placed
+}
+
+/// Asks which block to keep for each conflict. Conflicts that are left when the input ends stay
+/// unresolved.
+fn resolve_interactively(merged: &mut Merge) -> Result<(), Failure> {
+    let mut lines = io::stdin().lock().lines();
+    for conflict in merged.conflicts.clone() {
+        let side = loop {
+            eprint!(
+                "conflict at {}\nkeep (o)urs, (t)heirs or (b)ase? ",
+                conflict
+            );
+            let line = match lines.next() {
+                Some(line) => line.context("could not read the answer")?,
+                None => return Ok(()),
+            };
+            match line.trim() {
+                "o" | "ours" => break Side::Ours,
+                "t" | "theirs" => break Side::Theirs,
+                "b" | "base" => break Side::Base,
+                _ => continue,
+            }
+        };
+        merged.resolve(conflict.pos, side);
+    }
+    Ok(())
+}
+
+fn share(args: &[String]) -> Result<(), Failure> {
+    let design = match args {
+        [design] => Path::new(design),
+        _ => return Err(Failure::Usage("share takes a design file".to_owned())),
+    };
+    let timeline = load_timeline(design, &read_schematic(design)?)?;
+    println!("{}", Schematic::from_timeline(&timeline).to_share_string());
+    Ok(())
+}
+
+fn import(args: &[String]) -> Result<(), Failure> {
+    let (input, output) = match args {
+        [input, output] => (input, Path::new(output)),
+        _ => {
+            return Err(Failure::Usage(
+                "import takes a design string and an output file".to_owned(),
+            ))
+        }
+    };
+    let input = if input == "-" {
+        let mut input = String::new();
+        io::stdin()
+            .read_to_string(&mut input)
+            .context("could not read the design string")?;
+        input
+    } else {
+        input.clone()
+    };
+    let schematic = Schematic::from_share_string(&input)?;
+    let timeline = load_timeline(output, &schematic)?;
+    write_schematic(output, &Schematic::from_timeline(&timeline))?;
+    Ok(())
+}
+
+fn import_world_box(args: &[String]) -> Result<(), Failure> {
+    let mut substitute = None;
+    let values = parse_options(args, |option, values| {
+        match option {
+            "--substitute" => {
+                let block = values.next()?;
+                let block = block
+                    .parse::<BlockState>()
+                    .map_err(|err| Failure::Usage(format!("invalid block, {}: {}", block, err)))?;
+                substitute = Some(block);
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+    let (world, selection, output) = match values[..] {
+        [world, start, end, output] => (
+            Path::new(world),
+            Selection::new(parse_position(start)?, parse_position(end)?),
+            Path::new(output),
+        ),
+        _ => {
+            return Err(Failure::Usage(
+                "import-world takes a world folder, two corners and an output file".to_owned(),
+            ))
+        }
+    };
+    let imported = import_world(world, &selection, substitute.as_ref())
+        .with_context(|| format!("could not import from {}", world.display()))?;
+
+    let mut counts: Vec<(&str, usize)> = vec![];
+    for block in imported.unsupported.iter() {
+        match counts.iter_mut().find(|(name, _)| *name == block.name) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((&block.name, 1)),
+        }
+    }
+    counts.sort();
+    for (name, count) in counts {
+        println!("unsupported: {} {}", count, name);
+    }
+
+    let timeline = load_timeline(output, &imported.schematic)?;
+    write_schematic(output, &Schematic::from_timeline(&timeline))?;
+    Ok(())
+}
+
+fn export_world_at(args: &[String]) -> Result<(), Failure> {
+    let (design, world, origin) = match args {
+        [design, world, origin] => (Path::new(design), Path::new(world), parse_position(origin)?),
+        _ => {
+            return Err(Failure::Usage(
+                "export-world takes a design file, a world folder and a position".to_owned(),
+            ))
+        }
+    };
+    let timeline = load_timeline(design, &read_schematic(design)?)?;
+    export_world(world, timeline.design(), origin)
+        .with_context(|| format!("could not export to {}", world.display()))?;
+    Ok(())
+}
+
+fn read_schematic(path: &Path) -> Result<Schematic, Failure> {
+    read_schematic_as(path, Format::from_path(path)?)
+}
+
+fn read_schematic_as(path: &Path, format: Format) -> Result<Schematic, Failure> {
+    let data = fs::read(path).with_context(|| format!("could not read {}", path.display()))?;
+    let schematic = format
+        .read(&data)
+        .with_context(|| format!("could not parse {}", path.display()))?;
+    Ok(schematic)
+}
+
+fn write_schematic(path: &Path, schematic: &Schematic) -> Result<(), Failure> {
+    write_schematic_as(path, Format::from_path(path)?, schematic)
+}
+
+fn write_schematic_as(path: &Path, format: Format, schematic: &Schematic) -> Result<(), Failure> {
+    let data = format.write(schematic)?;
+    fs::write(path, data).with_context(|| format!("could not write {}", path.display()))?;
+    Ok(())
+}
+
+/// Places the blocks from a schematic, and prints each block that could not be placed.
+fn load_timeline(path: &Path, schematic: &Schematic) -> Result<Timeline, Failure> {
+    schematic
+        .to_timeline()
+        .map_err(|rejected| report_rejected(path, rejected))
+}
+
+fn report_rejected(path: &Path, rejected: Vec<RejectedBlock>) -> Failure {
+    for RejectedBlock { pos, state, reason } in rejected {
+        eprintln!(
+            "{}: cannot place {} at {} {} {}: {:?}",
+            path.display(),
+            state,
+            pos.x,
+            pos.y,
+            pos.z,
+            reason
+        );
+    }
+    Failure::InvalidDesign
+}
+
+/// The simulation ignores scheduled inputs for blocks that are not levers so that removing a
+/// lever in the editor does not break the schedule. On the command line that is more likely to
+/// be a mistake.
+fn check_schedule(timeline: &Timeline) -> Result<(), InvalidInput> {
+    for entry in timeline.input_schedule().entries() {
+        timeline.design().clone().apply_input(&entry.event)?;
+    }
+    Ok(())
+}
+
+/// Probes every block of the given types in the design, ordered bottom layer first.
+fn probes_for(timeline: &Timeline, block_types: &[&str]) -> Vec<Probe> {
+    let mut positions: Vec<IntVec3> = timeline
+        .design()
+        .blocks()
+        .filter(|(_, state)| block_types.contains(&state.block_type.as_str()))
+        .map(|(pos, _)| pos)
+        .collect();
+    positions.sort_by_key(|pos| pos.layer_order_key());
+    positions.into_iter().map(Probe::at).collect()
+}
+
+/// The arguments that follow an option, which the option takes its values from.
+struct OptionValues<'a> {
+    option: &'a str,
+    args: slice::Iter<'a, String>,
+}
+
+impl<'a> OptionValues<'a> {
+    /// Takes the next argument as a value of the option.
+    fn next(&mut self) -> Result<&'a String, Failure> {
+        self.args
+            .next()
+            .ok_or_else(|| Failure::Usage(format!("missing value for {}", self.option)))
+    }
+
+    /// Takes the next argument as a value of the option, and parses it.
+    fn parse<T: FromStr>(&mut self) -> Result<T, Failure> {
+        let value = self.next()?;
+        value
+            .parse()
+            .map_err(|_| Failure::Usage(format!("invalid value for {}, {}", self.option, value)))
+    }
+}
+
+/// Reads the arguments of a command. Each argument that starts with `--` is passed to `option`,
+/// which takes the values that the option needs and returns false if the command has no such
+/// option. Returns the other arguments in order.
+fn parse_options<'a>(
+    args: &'a [String],
+    mut option: impl FnMut(&str, &mut OptionValues<'a>) -> Result<bool, Failure>,
+) -> Result<Vec<&'a String>, Failure> {
+    let mut values = vec![];
+    let mut args = OptionValues {
+        option: "",
+        args: args.iter(),
+    };
+    while let Some(arg) = args.args.next() {
+        if !arg.starts_with("--") {
+            values.push(arg);
+            continue;
+        }
+        args.option = arg;
+        if !option(arg, &mut args)? {
+            return Err(unexpected_argument(arg));
+        }
+    }
+    Ok(values)
+}
+
+/// The design file of a command that takes a single design.
+fn design_file<'a>(values: &[&'a String]) -> Result<&'a Path, Failure> {
+    match values {
+        [design] => Ok(Path::new(*design)),
+        [] => Err(Failure::Usage("missing design file".to_owned())),
+        [_, arg, ..] => Err(unexpected_argument(arg)),
+    }
+}
+
+fn unexpected_argument(arg: &str) -> Failure {
+    Failure::Usage(format!("unexpected argument, {}", arg))
+}
+
+fn parse_port(kind: PortKind, input: &str) -> Result<Port, Failure> {
+    let (name, pos) = input.split_once('=').ok_or(Failure::Usage(format!(
+        "expected a port in the form name=x,y,z; found {}",
+        input
+    )))?;
+    Ok(Port {
+        name: name.to_owned(),
+        kind,
+        pos: parse_position(pos)?,
+    })
+}
+
+fn parse_position(input: &str) -> Result<IntVec3, Failure> {
+    let coords: Vec<_> = input
+        .split(',')
+        .map(|coord| coord.trim().parse::<i32>().ok())
+        .collect();
+    match coords[..] {
+        [Some(x), Some(y), Some(z)] => Ok(IntVec3::new(x, y, z)),
+        _ => Err(Failure::Usage(format!(
+            "expected a position in the form x,y,z; found {}",
+            input
+        ))),
+    }
+}
@@ -1,7 +1,10 @@
-use crate::block_state::BlockState;
+use minecraft_assets::schemas::models::BlockFace;
+
+use crate::{block_state::BlockState, util::opposite_face};
 
 pub fn requires_flat_surface(state: &BlockState) -> bool {
     match state.block_type.as_ref() {
+        "lever" => state.get_str("face") == Some("floor"),
         "redstone_torch" => true,
         "redstone_wire" => true,
         "repeater" => true,
@@ -15,7 +18,40 @@ pub fn requires_flat_surface(state: &BlockState) -> bool {
 pub fn is_flat_surface(state: &BlockState) -> bool {
     match state.block_type.as_ref() {
         "iron_block" => true,
+        "redstone_lamp" => true,
         "sandstone" => true,
         _ => false,
     }
 }
+
+/// Returns true for full blocks that light passes through, such as glass. In survival torches and
+/// redstone wire cannot be placed on these.
+pub fn is_transparent(state: &BlockState) -> bool {
+    let block_type = state.block_type.as_str();
+    block_type == "glass" || block_type.ends_with("_glass") || block_type == "ice"
+}
+
+/// Returns true for blocks that fall if there is nothing under them, such as sand.
+pub fn has_gravity(state: &BlockState) -> bool {
+    match state.block_type.as_ref() {
+        "sand" | "red_sand" | "gravel" | "anvil" => true,
+        block_type => block_type.ends_with("_concrete_powder"),
+    }
+}
+
+/// Returns the face of a block that touches the block it is attached to, for blocks that must be
+/// attached to another block. For example a lever placed on a wall is attached to the block
+/// behind it.
+pub fn attachment_face(state: &BlockState) -> Option<BlockFace> {
+    match state.block_type.as_ref() {
+        "lever" => match state.get_str("face")? {
+            "floor" => Some(BlockFace::Down),
+            "ceiling" => Some(BlockFace::Up),
+            _ => state.facing().map(opposite_face),
+        },
+        "redstone_torch" => Some(BlockFace::Down),
+        "redstone_wire" => Some(BlockFace::Down),
+        "repeater" => Some(BlockFace::Down),
+        _ => None,
+    }
+}
@@ -8,8 +8,11 @@ use bevy::prelude::*;
 use crate::{cursor::Cursor, lines::LineMaterial};
 
 use self::placing_block::PlacingBlockPlugin;
-pub use self::spawn_block::{spawn_block, spawn_block_preview_for_block_picker};
-pub use behavior::{is_flat_surface, requires_flat_surface};
+pub use self::bounding_box::bounding_box_to_line_list;
+pub use self::spawn_block::{respawn_blocks, spawn_block, spawn_block_preview_for_block_picker};
+pub use behavior::{
+    attachment_face, has_gravity, is_flat_surface, is_transparent, requires_flat_surface,
+};
 
 #[derive(Component, Clone, Default)]
 pub struct BlockOutline;
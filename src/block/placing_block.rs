@@ -1,7 +1,10 @@
 use crate::block_picker::SelectedBlockType;
+use crate::block_state::BlockState;
 use crate::camera::MainCamera;
 use crate::cursor::Cursor;
+use crate::int_vec3::IntVec3;
 use crate::lines::LineMaterial;
+use crate::timeline::Timeline;
 use crate::user_input::{sent_command, UiCommand};
 use crate::util::{vec_to_block_face, HasRelativeDirection};
 use bevy::prelude::*;
@@ -30,18 +33,31 @@ fn place_block(
     block_rotation: Res<BlockRotation>,
     user_input: EventReader<UiCommand>,
     cursor: Res<Cursor>,
+    mut timeline: ResMut<Timeline>,
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut line_materials: ResMut<Assets<LineMaterial>>,
 ) {
-    if sent_command(user_input, UiCommand::PlaceBlock) {
+    // Events are read before checking for a template, so that clicks while a template is
+    // selected are not still waiting to place a block once a block is selected again.
+    let place = sent_command(user_input, UiCommand::PlaceBlock);
+    // Templates are placed by the circuit library.
+    if selected.template.is_some() {
+        return;
+    }
+    if place {
         let (block_type, mut block_state) = selected.block.clone();
         if let Some(direction) = block_rotation.direction {
             let _ = block_state.set_facing(direction);
         }
         if let Some(transform) = cursor.place_block_transform {
+            let pos = IntVec3::from_translation(transform.translation);
+            if let Err(err) = timeline.insert_block(pos, block_state.clone()) {
+                info!("cannot place {} at {:?}: {:?}", block_type, pos, err);
+                return;
+            }
             spawn_block(
                 &mut commands,
                 &asset_server,
@@ -75,10 +91,20 @@ fn rotate_block(
     }
 }
 
-fn destroy_block(mut commands: Commands, user_input: EventReader<UiCommand>, cursor: Res<Cursor>) {
+fn destroy_block(
+    mut commands: Commands,
+    user_input: EventReader<UiCommand>,
+    cursor: Res<Cursor>,
+    mut timeline: ResMut<Timeline>,
+    query_blocks: Query<&Transform, With<BlockState>>,
+) {
     if sent_command(user_input, UiCommand::DestroyBlock) {
         if let Some(block) = cursor.current_block {
-            commands.entity(block).despawn_recursive();
+            // The floor is under the cursor too, but it is not a block that can be destroyed.
+            if let Ok(transform) = query_blocks.get(block) {
+                timeline.remove_block(IntVec3::from_translation(transform.translation));
+                commands.entity(block).despawn_recursive();
+            }
         }
     }
 }
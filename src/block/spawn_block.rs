@@ -1,5 +1,9 @@
+use std::collections::HashSet;
+
 use crate::block::BlockBundle;
-use crate::{block_state::BlockState, util::degrees_to_radians};
+use crate::{
+    block_state::BlockState, int_vec3::IntVec3, timeline::WorldState, util::degrees_to_radians,
+};
 use anyhow::{anyhow, Context, Result};
 use bevy::{
     prelude::*,
@@ -57,6 +61,39 @@ pub fn spawn_block(
         });
 }
 
+/// Replaces the entities of the blocks at the given positions with the blocks that the world has
+/// there now. Positions where the world has no block are left empty.
+pub fn respawn_blocks(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    line_materials: &mut ResMut<Assets<LineMaterial>>,
+    world: &WorldState,
+    positions: &[IntVec3],
+    query_blocks: &Query<(Entity, &Transform), With<BlockState>>,
+) {
+    let positions: HashSet<_> = positions.iter().copied().collect();
+    for (block, transform) in query_blocks.iter() {
+        if positions.contains(&IntVec3::from_translation(transform.translation)) {
+            commands.entity(block).despawn_recursive();
+        }
+    }
+    for pos in positions {
+        if let Some(state) = world.block_at(pos) {
+            spawn_block(
+                commands,
+                asset_server,
+                meshes,
+                materials,
+                line_materials,
+                (&state.block_type, state.clone()),
+                Transform::from_translation(pos.to_translation()),
+            );
+        }
+    }
+}
+
 /// Spawn a block to display in the block picker, not in the simulation world.
 /// TODO: rename this to be less similar to `spawn_block_preview`
 /// TODO: reuse this logic in `spawn_block`
@@ -65,14 +102,13 @@ pub fn spawn_block_preview_for_block_picker(
     asset_server: &Res<AssetServer>,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
-    block_type: &str,
+    (block_type, state): (&str, &BlockState),
     transform: Transform,
 
     // Component to insert in the entity, and into children.
     recursive_component: Option<impl Component + Clone>,
 ) -> Result<Entity> {
-    let initial_state = BlockState::initial_state_for(block_type);
-    let (model, model_properties) = get_block_model_metadata(block_type, &initial_state)?;
+    let (model, model_properties) = get_block_model_metadata(block_type, state)?;
     spawn_block_common(
         commands,
         asset_server,
@@ -12,8 +12,11 @@ use bevy::{
 
 use crate::{
     block::spawn_block_preview_for_block_picker,
+    block_state::BlockState,
+    circuit_library::CircuitLibrary,
     constants::{BLOCKS, BLOCK_PALETTE, BLOCK_PREVIEW_LAYER},
-    user_input::UiCommand, block_state::BlockState,
+    int_vec3::IntVec3,
+    user_input::UiCommand,
 };
 
 const BLOCK_PREVIEW_SIZE: u32 = 100; // px
@@ -34,16 +37,21 @@ impl Plugin for BlockPickerPlugin {
                 initial_block_type,
                 BlockState::initial_state_for(initial_block_type),
             ),
+            template: None,
         })
         .add_startup_system(spawn_block_picker)
         .add_system(toggle_block_picker)
-        .add_system(button_system);
+        .add_system(button_system)
+        .add_system(template_button_system);
     }
 }
 
 #[derive(Debug, Resource)]
 pub struct SelectedBlockType {
     pub block: (&'static str, BlockState),
+    /// Index of a template in the circuit library. When a template is selected it is placed
+    /// instead of the block.
+    pub template: Option<usize>,
 }
 
 #[derive(Component, Debug, Default)]
@@ -59,12 +67,18 @@ struct BlockPickerButton {
     block: (&'static str, BlockState),
 }
 
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+struct TemplateButton {
+    index: usize,
+}
+
 fn spawn_block_picker(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut images: ResMut<Assets<Image>>,
+    library: Res<CircuitLibrary>,
 ) {
     let block_preview_image_handles = BLOCK_PALETTE
         .iter()
@@ -76,8 +90,29 @@ fn spawn_block_picker(
                 &mut meshes,
                 &mut materials,
                 &mut images,
-                block_type,
-                index,
+                &[(IntVec3::ZERO, &BlockState::initial_state_for(block_type))],
+                Vec3::new(5.0 * BLOCKS * index as f32, 0.0, 0.0),
+            )
+            .unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    // Templates are rendered in a separate row, far enough apart that they fit next to each
+    // other.
+    let template_preview_image_handles = library
+        .templates
+        .iter()
+        .enumerate()
+        .map(|(index, template)| {
+            let blocks: Vec<_> = template.world.blocks().collect();
+            spawn_block_preview(
+                &mut commands,
+                &asset_server,
+                &mut meshes,
+                &mut materials,
+                &mut images,
+                &blocks,
+                Vec3::new(32.0 * BLOCKS * index as f32, 0.0, 32.0 * BLOCKS),
             )
             .unwrap()
         })
@@ -150,22 +185,49 @@ fn spawn_block_picker(
                                         parent.spawn(TextBundle::from_section("", default()));
                                     });
                             }
+                            for (index, image) in
+                                template_preview_image_handles.into_iter().enumerate()
+                            {
+                                parent
+                                    .spawn(ButtonBundle {
+                                        image: image.into(),
+                                        style: Style {
+                                            size: Size::new(
+                                                Val::Px(BLOCK_PREVIEW_SIZE as f32),
+                                                Val::Px(BLOCK_PREVIEW_SIZE as f32),
+                                            ),
+                                            margin: UiRect {
+                                                top: Val::Px(6.0),
+                                                left: Val::Px(6.0),
+                                                ..default()
+                                            },
+                                            ..default()
+                                        },
+                                        background_color: NORMAL_BUTTON_COLOR.into(),
+                                        ..default()
+                                    })
+                                    .insert(TemplateButton { index })
+                                    .with_children(|parent| {
+                                        parent.spawn(TextBundle::from_section("", default()));
+                                    });
+                            }
                         });
                 });
         })
         .insert(BlockPicker::default());
 }
 
-/// Renders a block to a texture, and returns an image handle so that the texture can be displayed
-/// in the block picker UI.
+/// Renders a group of blocks to a texture, and returns an image handle so that the texture can be
+/// displayed in the block picker UI. Block positions are relative to `offset`, and the camera is
+/// zoomed out to fit every block.
 fn spawn_block_preview(
     commands: &mut Commands,
     asset_server: &Res<AssetServer>,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
     images: &mut ResMut<Assets<Image>>,
-    block_model: &str,
-    index: usize,
+    blocks: &[(IntVec3, &BlockState)],
+    offset: Vec3,
 ) -> Result<Handle<Image>> {
     // This code for rendering to a texture is taken from one of the Bevy examples,
     // https://github.com/bevyengine/bevy/blob/main/examples/3d/render_to_texture.rs
@@ -197,20 +259,29 @@ fn spawn_block_preview(
 
     let image_handle = images.add(image);
 
-    // Translate each block by a different amount so they don't cover each other.
-    let center_of_block = Vec3::new(5.0 * BLOCKS * index as f32, 0.0, 0.0);
+    for (pos, state) in blocks.iter().copied() {
+        let block = spawn_block_preview_for_block_picker(
+            commands,
+            asset_server,
+            meshes,
+            materials,
+            (&state.block_type, state),
+            Transform::from_translation(offset + pos.to_translation()),
+            // Make the block visible to the camera below, and not to the main camera
+            Some(BLOCK_PREVIEW_LAYER),
+        )?;
+        commands.entity(block).insert(BlockPreview);
+    }
 
-    let block = spawn_block_preview_for_block_picker(
-        commands,
-        asset_server,
-        meshes,
-        materials,
-        block_model,
-        Transform::from_translation(center_of_block),
-        // Make the block visible to the camera below, and not to the main camera
-        Some(BLOCK_PREVIEW_LAYER),
-    )?;
-    commands.entity(block).insert(BlockPreview);
+    let extent = blocks.iter().fold(IntVec3::ZERO, |extent, (pos, _)| {
+        IntVec3::new(
+            extent.x.max(pos.x),
+            extent.y.max(pos.y),
+            extent.z.max(pos.z),
+        )
+    });
+    let center_of_block = offset + extent.to_translation() / 2.0;
+    let zoom = (extent.x.max(extent.y).max(extent.z) + 1) as f32;
 
     commands
         .spawn(Camera3dBundle {
@@ -225,11 +296,12 @@ fn spawn_block_preview(
                 ..default()
             },
             projection: Projection::Orthographic(OrthographicProjection {
-                scale: 24.0 / BLOCK_PREVIEW_SIZE as f32, // smaller numbers here make the block look bigger
+                // smaller numbers here make the block look bigger
+                scale: zoom * 24.0 / BLOCK_PREVIEW_SIZE as f32,
                 ..default()
             }),
             transform: Transform::from_translation(
-                center_of_block + Vec3::new(1.0 * BLOCKS, 0.66 * BLOCKS, 1.0 * BLOCKS),
+                center_of_block + Vec3::new(1.0 * BLOCKS, 0.66 * BLOCKS, 1.0 * BLOCKS) * zoom,
             )
             .looking_at(center_of_block, Vec3::Y),
             ..default()
@@ -282,6 +354,32 @@ fn button_system(
             Interaction::Clicked => {
                 *color = PRESSED_BUTTON_COLOR.into();
                 selected.block = button.block.clone();
+                selected.template = None;
+                toggle_block_picker_helper(false, &mut picker_query)
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON_COLOR.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON_COLOR.into();
+            }
+        }
+    }
+}
+
+fn template_button_system(
+    mut button_query: Query<
+        (&Interaction, &TemplateButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+    mut picker_query: Query<(&mut BlockPicker, &mut Style)>,
+    mut selected: ResMut<SelectedBlockType>,
+) {
+    for (interaction, button, mut color) in &mut button_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON_COLOR.into();
+                selected.template = Some(button.index);
                 toggle_block_picker_helper(false, &mut picker_query)
             }
             Interaction::Hovered => {
@@ -1,8 +1,14 @@
 mod states_by_block_type;
+mod transform;
 
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Error, Result};
 use bevy::prelude::*;
 use minecraft_assets::schemas::{
     blockstates::{multipart::StateValue, Variant},
@@ -12,6 +18,8 @@ use minecraft_assets::schemas::{
 
 use states_by_block_type::state_values_for;
 
+pub use transform::BlockTransform;
+
 /// The current state of a specific block. Matches against block states defined in
 /// minecraft/assets/minecraft/blockstates/ to determine which block model to render, and how to
 /// render it.
@@ -102,9 +110,7 @@ impl BlockState {
             .iter()
             .any(|allowed_value| *allowed_value == value)
         {
-            self.values
-                .entry(prop.to_owned())
-                .and_modify(|v| *v = value);
+            self.values.insert(prop.to_owned(), value);
             Ok(())
         } else {
             Err(anyhow!(
@@ -116,17 +122,158 @@ impl BlockState {
         }
     }
 
+    /// Sets a property value without checking it against the allowed values for the block type.
+    /// This is for transforms that move existing values between properties, such as turning the
+    /// connections of redstone wire. Allowed values for wire connections are match patterns like
+    /// `side|up` which would reject the values that wire actually has.
+    pub fn set_unchecked(&mut self, prop: &str, value: StateValue) {
+        self.values.insert(prop.to_owned(), value);
+    }
+
     /// Set facing for a block that supports it.
     pub fn set_facing(&mut self, face: BlockFace) -> Result<()> {
-        let facing = match face {
-            BlockFace::North => "north",
-            BlockFace::South => "south",
-            BlockFace::East => "east",
-            BlockFace::West => "west",
-            BlockFace::Up => "up",
-            BlockFace::Down => "down",
+        self.update("facing", StateValue::String(face_name(face).to_owned()))
+    }
+
+    /// Reads a property value. Returns `None` if the property is not set for this block.
+    pub fn get(&self, prop: &str) -> Option<&StateValue> {
+        self.values.get(prop)
+    }
+
+    /// Iterates over the properties that are set for this block, in alphabetical order.
+    pub fn values(&self) -> impl Iterator<Item = (&str, &StateValue)> {
+        let mut values: Vec<_> = self
+            .values
+            .iter()
+            .map(|(prop, value)| (prop.as_str(), value))
+            .collect();
+        values.sort_by_key(|(prop, _)| *prop);
+        values.into_iter()
+    }
+
+    pub fn get_bool(&self, prop: &str) -> Option<bool> {
+        match self.get(prop)? {
+            StateValue::Bool(value) => Some(*value),
+            StateValue::String(_) => None,
+        }
+    }
+
+    /// Reads a property that holds a number, such as the delay of a repeater, or the power level of
+    /// redstone wire. Block state files represent these values as strings.
+    pub fn get_int(&self, prop: &str) -> Option<i32> {
+        match self.get(prop)? {
+            StateValue::String(value) => value.parse().ok(),
+            StateValue::Bool(_) => None,
+        }
+    }
+
+    pub fn get_str(&self, prop: &str) -> Option<&str> {
+        match self.get(prop)? {
+            StateValue::String(value) => Some(value.as_str()),
+            StateValue::Bool(_) => None,
+        }
+    }
+
+    /// Gets facing for a block that supports it.
+    pub fn facing(&self) -> Option<BlockFace> {
+        face_from_name(self.get_str("facing")?)
+    }
+
+    /// True if this block has the pattern's block type and every property value that the pattern
+    /// sets. A pattern without properties, like `redstone_lamp`, matches every state of its type.
+    pub fn matches(&self, pattern: &BlockState) -> bool {
+        self.block_type == pattern.block_type
+            && pattern
+                .values
+                .iter()
+                .all(|(prop, value)| self.values.get(prop) == Some(value))
+    }
+}
+
+/// Formats a block as `block_type[prop=value,prop=value]` with properties in alphabetical order,
+/// or as just the block type if it has no properties. The result can be parsed with `str::parse`.
+impl fmt::Display for BlockState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.block_type)?;
+        if self.values.is_empty() {
+            return Ok(());
+        }
+        let values: Vec<String> = self
+            .values()
+            .map(|(prop, value)| match value {
+                StateValue::Bool(value) => format!("{}={}", prop, value),
+                StateValue::String(value) => format!("{}={}", prop, value),
+            })
+            .collect();
+        write!(f, "[{}]", values.join(","))
+    }
+}
+
+/// Properties are hashed in alphabetical order so that equal states have equal hashes regardless
+/// of the order of entries in the underlying map.
+impl Hash for BlockState {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.block_type.hash(state);
+        let mut values: Vec<_> = self.values.iter().collect();
+        values.sort_by_key(|(prop, _)| prop.as_str());
+        for (prop, value) in values {
+            prop.hash(state);
+            match value {
+                StateValue::Bool(value) => value.hash(state),
+                StateValue::String(value) => value.hash(state),
+            }
+        }
+    }
+}
+
+impl FromStr for BlockState {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        let input = input.trim();
+        let (block_type, state_values) = match input.split_once('[') {
+            Some((block_type, rest)) => {
+                let state_values = rest
+                    .strip_suffix(']')
+                    .ok_or(anyhow!("expected ']' at the end of, {}", input))?;
+                (block_type, state_values)
+            }
+            None => (input, ""),
         };
-        self.update("facing", StateValue::String(facing.to_owned()))
+        if block_type.is_empty() {
+            return Err(anyhow!("missing block type in, {}", input));
+        }
+        if let Some(invalid) = state_values
+            .split(',')
+            .find(|value| !value.is_empty() && value.split('=').count() != 2)
+        {
+            return Err(anyhow!("expected prop=value, found {}", invalid));
+        }
+        Ok(BlockState::new(block_type, state_values))
+    }
+}
+
+/// Name of a face as it appears in block state values.
+pub fn face_name(face: BlockFace) -> &'static str {
+    match face {
+        BlockFace::North => "north",
+        BlockFace::South => "south",
+        BlockFace::East => "east",
+        BlockFace::West => "west",
+        BlockFace::Up => "up",
+        BlockFace::Down => "down",
+    }
+}
+
+pub fn face_from_name(name: &str) -> Option<BlockFace> {
+    match name {
+        "north" => Some(BlockFace::North),
+        "south" => Some(BlockFace::South),
+        "east" => Some(BlockFace::East),
+        "west" => Some(BlockFace::West),
+        "up" => Some(BlockFace::Up),
+        "down" => Some(BlockFace::Down),
+        _ => None,
     }
 }
 
@@ -147,6 +294,20 @@ mod tests {
 
     use super::BlockState;
 
+    #[test]
+    fn formats_a_state_that_can_be_parsed_again() -> Result<()> {
+        let state = BlockState::new("repeater", "facing=west,delay=2,powered=false");
+        let text = state.to_string();
+        assert_eq!(text, "repeater[delay=2,facing=west,powered=false]");
+        assert_eq!(text.parse::<BlockState>()?, state);
+        assert_eq!(
+            "sandstone".parse::<BlockState>()?,
+            BlockState::new("sandstone", "")
+        );
+        assert!("lever[powered]".parse::<BlockState>().is_err());
+        Ok(())
+    }
+
     #[test]
     fn constructs_empty_state() {
         let state = BlockState::new("sandstone", "");
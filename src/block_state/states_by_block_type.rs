@@ -34,11 +34,23 @@ impl PartialEq<StateValue> for StaticStateValue {
 /// will be used for the default state.
 pub fn state_values_for(block_type: &str) -> HashMap<&'static str, Vec<StaticStateValue>> {
     match block_type {
+        "lever" => hashmap! {
+            "face" => vec![S("floor"), S("wall"), S("ceiling")],
+            "facing" => vec![S("north"), S("south"), S("east"), S("west")],
+            "powered" => vec![B(false), B(true)],
+        },
+        "redstone_lamp" => hashmap! {
+            "lit" => vec![B(false), B(true)],
+        },
         "redstone_wire" => hashmap! {
             "east"  => vec![S("none"), S("side|up")],
             "north" => vec![S("none"), S("side|up")],
             "south" => vec![S("none"), S("side|up")],
             "west"  => vec![S("none"), S("side|up")],
+            "power" => vec![
+                S("0"), S("1"), S("2"), S("3"), S("4"), S("5"), S("6"), S("7"),
+                S("8"), S("9"), S("10"), S("11"), S("12"), S("13"), S("14"), S("15"),
+            ],
         },
         "repeater" => hashmap! {
             "delay" => vec![S("1"), S("2"), S("3"), S("4")],
@@ -0,0 +1,188 @@
+use minecraft_assets::schemas::{blockstates::multipart::StateValue, models::BlockFace};
+
+use crate::int_vec3::IntVec3;
+
+use super::{face_from_name, face_name, BlockState};
+
+const HORIZONTAL_FACES: [BlockFace; 4] = [
+    BlockFace::North,
+    BlockFace::East,
+    BlockFace::South,
+    BlockFace::West,
+];
+
+/// How a property changes when a block is turned or mirrored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DirectionalProperty {
+    /// The property value is the name of a face, like the `facing` of a repeater.
+    FaceValue,
+
+    /// The property is named after a face, like the connections of redstone wire. The value moves
+    /// to the property for the transformed face.
+    FaceName(BlockFace),
+}
+
+use DirectionalProperty::*;
+
+/// Properties of each block type that refer to a direction. Properties that are not listed are
+/// copied unchanged.
+const DIRECTIONAL_PROPERTIES: &[(&str, &str, DirectionalProperty)] = &[
+    ("lever", "facing", FaceValue),
+    ("repeater", "facing", FaceValue),
+    ("redstone_wire", "north", FaceName(BlockFace::North)),
+    ("redstone_wire", "east", FaceName(BlockFace::East)),
+    ("redstone_wire", "south", FaceName(BlockFace::South)),
+    ("redstone_wire", "west", FaceName(BlockFace::West)),
+];
+
+/// A rotation or reflection about a vertical axis through the origin.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlockTransform {
+    /// Clockwise quarter turns as seen from above
+    Rotate(i32),
+
+    /// Negates x coordinates, swapping east and west
+    MirrorX,
+
+    /// Negates z coordinates, swapping north and south
+    MirrorZ,
+}
+
+impl BlockTransform {
+    pub fn pos(self, pos: IntVec3) -> IntVec3 {
+        match self {
+            BlockTransform::Rotate(turns) => {
+                (0..turns.rem_euclid(4)).fold(pos, |pos, _| IntVec3::new(-pos.z, pos.y, pos.x))
+            }
+            BlockTransform::MirrorX => IntVec3::new(-pos.x, pos.y, pos.z),
+            BlockTransform::MirrorZ => IntVec3::new(pos.x, pos.y, -pos.z),
+        }
+    }
+
+    /// Vertical faces are unchanged.
+    pub fn face(self, face: BlockFace) -> BlockFace {
+        let index = match HORIZONTAL_FACES.iter().position(|f| *f == face) {
+            Some(index) => index,
+            None => return face,
+        };
+        let index = match self {
+            BlockTransform::Rotate(turns) => index + turns.rem_euclid(4) as usize,
+            // North and south stay in place, east and west swap
+            BlockTransform::MirrorX => 4 - index,
+            // East and west stay in place, north and south swap
+            BlockTransform::MirrorZ => 6 - index,
+        };
+        HORIZONTAL_FACES[index % 4]
+    }
+
+    /// Turns or mirrors every directional property of a block according to the table of
+    /// directional properties.
+    pub fn state(self, state: &BlockState) -> BlockState {
+        let mut transformed = state.clone();
+        let properties = DIRECTIONAL_PROPERTIES
+            .iter()
+            .filter(|(block_type, _, _)| *block_type == state.block_type);
+        for (_, prop, kind) in properties {
+            let value = match state.get(prop) {
+                Some(value) => value,
+                None => continue,
+            };
+            match kind {
+                FaceValue => {
+                    if let Some(face) = state.get_str(prop).and_then(face_from_name) {
+                        let face = face_name(self.face(face)).to_owned();
+                        transformed.set_unchecked(prop, StateValue::String(face));
+                    }
+                }
+                FaceName(face) => {
+                    transformed.set_unchecked(face_name(self.face(*face)), value.clone())
+                }
+            }
+        }
+        transformed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use minecraft_assets::schemas::models::BlockFace;
+
+    use crate::{block_state::BlockState, int_vec3::IntVec3};
+
+    use super::BlockTransform;
+
+    #[test]
+    fn transforms_faces() {
+        assert_eq!(
+            BlockTransform::Rotate(1).face(BlockFace::North),
+            BlockFace::East
+        );
+        assert_eq!(
+            BlockTransform::Rotate(-1).face(BlockFace::North),
+            BlockFace::West
+        );
+        assert_eq!(BlockTransform::Rotate(2).face(BlockFace::Up), BlockFace::Up);
+        assert_eq!(
+            BlockTransform::MirrorX.face(BlockFace::East),
+            BlockFace::West
+        );
+        assert_eq!(
+            BlockTransform::MirrorX.face(BlockFace::North),
+            BlockFace::North
+        );
+        assert_eq!(
+            BlockTransform::MirrorZ.face(BlockFace::North),
+            BlockFace::South
+        );
+        assert_eq!(
+            BlockTransform::MirrorZ.face(BlockFace::West),
+            BlockFace::West
+        );
+    }
+
+    #[test]
+    fn moves_positions_with_faces() {
+        for transform in [
+            BlockTransform::Rotate(1),
+            BlockTransform::Rotate(3),
+            BlockTransform::MirrorX,
+            BlockTransform::MirrorZ,
+        ] {
+            for face in [BlockFace::North, BlockFace::East] {
+                assert_eq!(
+                    transform.pos(IntVec3::from_block_face(face)),
+                    IntVec3::from_block_face(transform.face(face))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn transforms_directional_properties() {
+        let repeater = BlockState::new("repeater", "delay=3,facing=north");
+        assert_eq!(
+            BlockTransform::Rotate(1).state(&repeater),
+            BlockState::new("repeater", "delay=3,facing=east")
+        );
+        let wire = BlockState::new(
+            "redstone_wire",
+            "east=side,north=up,power=4,south=none,west=none",
+        );
+        assert_eq!(
+            BlockTransform::MirrorX.state(&wire),
+            BlockState::new(
+                "redstone_wire",
+                "east=none,north=up,power=4,south=none,west=side"
+            )
+        );
+        assert_eq!(
+            BlockTransform::Rotate(2).state(&wire),
+            BlockState::new(
+                "redstone_wire",
+                "east=none,north=none,power=4,south=up,west=side"
+            )
+        );
+        let lamp = BlockState::new("redstone_lamp", "lit=true");
+        assert_eq!(BlockTransform::MirrorZ.state(&lamp), lamp);
+    }
+}
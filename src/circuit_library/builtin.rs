@@ -0,0 +1,284 @@
+//! Templates that ship with the app.
+
+use super::template::Template;
+
+/// Oscillates with a period of six ticks. A torch feeds a repeater that powers the block above the
+/// `clock` wire, and that wire loops back to switch off the torch.
+const CLOCK: &str = "\
+name clock
+port out clock 3 0 0
+block 0 0 0 iron_block
+block 1 0 0 iron_block
+block 2 0 0 redstone_wire[east=side,north=none,power=0,south=side,west=none]
+block 3 0 0 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 0 0 1 redstone_wire[east=side,north=side,power=0,south=none,west=none]
+block 1 0 1 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 2 0 1 redstone_wire[east=none,north=side,power=0,south=none,west=side]
+block 0 1 0 redstone_torch[lit=true]
+block 1 1 0 repeater[delay=2,facing=west,locked=false,powered=false]
+block 2 1 0 iron_block
+";
+
+/// Turns the rising edge of `in` into a pulse of about five ticks. The output torch is lit while
+/// the inverted input has switched off, and the same input, delayed by a chain of repeaters that
+/// climbs up to the output block, has not arrived yet.
+const PULSE_LIMITER: &str = "\
+name pulse limiter
+port in in 0 0 0
+port out out 3 3 0
+block 0 0 0 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 1 0 0 redstone_wire[east=side,north=none,power=0,south=side,west=side]
+block 2 0 0 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 3 0 0 iron_block
+block 1 0 1 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 1 0 2 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 1 0 3 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 1 0 4 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 1 0 5 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 1 0 6 redstone_wire[east=side,north=side,power=0,south=none,west=none]
+block 2 0 6 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 3 0 6 redstone_wire[east=none,north=side,power=0,south=none,west=side]
+block 3 0 5 repeater[delay=4,facing=south,locked=false,powered=false]
+block 3 0 4 iron_block
+block 3 0 3 iron_block
+block 3 1 0 redstone_torch[lit=true]
+block 3 1 1 iron_block
+block 3 1 2 iron_block
+block 3 1 3 repeater[delay=1,facing=south,locked=false,powered=false]
+block 3 1 4 redstone_wire[east=none,north=side,power=0,south=none,west=none]
+block 3 2 0 iron_block
+block 3 2 1 repeater[delay=1,facing=south,locked=false,powered=false]
+block 3 2 2 redstone_wire[east=none,north=side,power=0,south=none,west=none]
+block 3 3 0 redstone_torch[lit=false]
+";
+
+/// Toggles `q` on each rising edge of `t`. Like the pulse limiter, the set and reset gates of a
+/// latch of two torches are only on while the inverted `t` has switched off and the delayed `t`
+/// has not arrived yet. The gate that is not blocked by the latch flips it, and the slow repeaters
+/// that feed the latch back to the gates keep the other gate blocked until the pulse is over.
+const T_FLIP_FLOP: &str = "\
+name t flip-flop
+port in t 15 0 13
+port out q 7 2 4
+block 2 0 0 redstone_wire[east=side,north=none,power=0,south=side,west=none]
+block 3 0 0 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 4 0 0 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 5 0 0 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 6 0 0 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 7 0 0 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 8 0 0 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 9 0 0 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 10 0 0 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 11 0 0 redstone_wire[east=side,north=none,power=0,south=side,west=side]
+block 12 0 0 repeater[delay=4,facing=east,locked=false,powered=false]
+block 13 0 0 repeater[delay=4,facing=east,locked=false,powered=false]
+block 14 0 0 redstone_wire[east=side,north=none,power=0,south=none,west=none]
+block 15 0 0 redstone_wire[east=none,north=none,power=0,south=side,west=side]
+block 2 0 1 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 11 0 1 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 15 0 1 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 0 0 2 redstone_wire[east=side,north=none,power=0,south=side,west=none]
+block 1 0 2 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 2 0 2 iron_block
+block 3 0 2 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 4 0 2 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 5 0 2 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 6 0 2 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 7 0 2 redstone_wire[east=none,north=none,power=0,south=none,west=side]
+block 9 0 2 redstone_wire[east=none,north=none,power=0,south=side,west=none]
+block 10 0 2 iron_block
+block 11 0 2 iron_block
+block 12 0 2 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 13 0 2 redstone_wire[east=none,north=none,power=0,south=side,west=side]
+block 15 0 2 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 0 0 3 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 2 0 3 iron_block
+block 7 0 3 iron_block
+block 9 0 3 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 11 0 3 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 13 0 3 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 15 0 3 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 0 0 4 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 2 0 4 redstone_wire[east=none,north=none,power=0,south=side,west=none]
+block 4 0 4 redstone_wire[east=side,north=none,power=0,south=side,west=none]
+block 5 0 4 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 6 0 4 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 7 0 4 iron_block
+block 8 0 4 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 9 0 4 redstone_wire[east=none,north=side,power=0,south=none,west=side]
+block 11 0 4 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 13 0 4 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 15 0 4 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 0 0 5 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 2 0 5 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 4 0 5 redstone_wire[east=none,north=side,power=0,south=none,west=none]
+block 7 0 5 iron_block
+block 11 0 5 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 13 0 5 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 15 0 5 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 0 0 6 repeater[delay=1,facing=south,locked=false,powered=true]
+block 2 0 6 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 4 0 6 iron_block
+block 7 0 6 redstone_wire[east=none,north=none,power=0,south=side,west=none]
+block 11 0 6 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 13 0 6 repeater[delay=2,facing=south,locked=false,powered=true]
+block 15 0 6 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 0 0 7 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 2 0 7 redstone_wire[east=side,north=side,power=0,south=none,west=none]
+block 3 0 7 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 4 0 7 iron_block
+block 5 0 7 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 6 0 7 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 7 0 7 redstone_wire[east=none,north=side,power=0,south=none,west=side]
+block 11 0 7 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 13 0 7 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 15 0 7 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 0 0 8 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 4 0 8 iron_block
+block 11 0 8 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 13 0 8 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 15 0 8 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 0 0 9 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 4 0 9 redstone_wire[east=side,north=none,power=0,south=none,west=none]
+block 5 0 9 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 6 0 9 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 7 0 9 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 8 0 9 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 9 0 9 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 10 0 9 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 11 0 9 redstone_wire[east=none,north=side,power=0,south=none,west=side]
+block 13 0 9 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 15 0 9 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 0 0 10 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 13 0 10 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 15 0 10 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 0 0 11 redstone_wire[east=side,north=side,power=0,south=none,west=none]
+block 1 0 11 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 2 0 11 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 3 0 11 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 4 0 11 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 5 0 11 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 6 0 11 repeater[delay=1,facing=east,locked=false,powered=true]
+block 7 0 11 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 8 0 11 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 9 0 11 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 10 0 11 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 11 0 11 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 12 0 11 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 13 0 11 redstone_wire[east=none,north=side,power=0,south=none,west=side]
+block 15 0 11 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 13 0 12 iron_block
+block 15 0 12 redstone_wire[east=none,north=side,power=0,south=side,west=none]
+block 13 0 13 iron_block
+block 14 0 13 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 15 0 13 redstone_wire[east=none,north=side,power=0,south=none,west=side]
+block 2 1 2 redstone_torch[lit=false]
+block 7 1 2 iron_block
+block 9 1 2 iron_block
+block 10 1 2 repeater[delay=1,facing=east,locked=false,powered=false]
+block 11 1 2 redstone_torch[lit=false]
+block 2 1 3 repeater[delay=1,facing=north,locked=false,powered=false]
+block 7 1 3 repeater[delay=4,facing=south,locked=false,powered=false]
+block 2 1 4 iron_block
+block 7 1 4 redstone_torch[lit=false]
+block 4 1 5 iron_block
+block 7 1 5 repeater[delay=1,facing=north,locked=false,powered=false]
+block 4 1 6 repeater[delay=1,facing=south,locked=false,powered=true]
+block 7 1 6 iron_block
+block 4 1 7 redstone_torch[lit=true]
+block 4 1 8 repeater[delay=4,facing=north,locked=false,powered=true]
+block 4 1 9 iron_block
+block 13 1 11 iron_block
+block 13 1 12 repeater[delay=1,facing=south,locked=false,powered=true]
+block 13 1 13 redstone_torch[lit=true]
+block 7 2 4 redstone_lamp[lit=false]
+";
+
+pub fn builtin_templates() -> Vec<Template> {
+    vec![
+        Template::parse(CLOCK).unwrap(),
+        Template::parse(PULSE_LIMITER).unwrap(),
+        Template::parse(T_FLIP_FLOP).unwrap(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{harness::CircuitTest, int_vec3::IntVec3, timeline::WorldState};
+
+    use super::{builtin_templates, Template, CLOCK, PULSE_LIMITER, T_FLIP_FLOP};
+
+    fn stamped(template: &Template, origin: IntVec3) -> CircuitTest {
+        let mut world = WorldState::new((IntVec3::ZERO, IntVec3::ONE * 15));
+        template.stamp(&mut world, origin).unwrap();
+        CircuitTest::from_world(world)
+    }
+
+    fn output_over(test: &mut CircuitTest, pos: IntVec3, ticks: usize) -> Vec<bool> {
+        (0..ticks)
+            .map(|_| test.run(1).power_level(pos) > 0)
+            .collect()
+    }
+
+    #[test]
+    fn clock_oscillates() {
+        let template = Template::parse(CLOCK).unwrap().rotated(1);
+        let out = template.ports[0].pos + IntVec3::new(2, 0, 2);
+        let mut test = stamped(&template, IntVec3::new(2, 0, 2));
+        test.run(10);
+        let levels = output_over(&mut test, out, 12);
+        assert_eq!(levels[..6], levels[6..]);
+        assert_eq!(levels.iter().filter(|on| **on).count(), 6);
+    }
+
+    #[test]
+    fn pulse_limiter_shortens_a_long_input() {
+        let template = Template::parse(PULSE_LIMITER).unwrap();
+        let origin = IntVec3::new(2, 0, 2);
+        let lever = origin + IntVec3::NEG_X;
+        let out = template.ports[1].pos + origin;
+        let mut test = stamped(&template, origin);
+        test.place(lever, "lever", "face=floor,facing=east,powered=false")
+            .run(10)
+            .assert_unpowered(out);
+
+        test.set_lever(lever, true);
+        let pulse = output_over(&mut test, out, 20);
+        assert_eq!(pulse.iter().filter(|on| **on).count(), 5);
+        test.assert_unpowered(out);
+
+        test.set_lever(lever, false);
+        assert!(!output_over(&mut test, out, 20).contains(&true));
+    }
+
+    #[test]
+    fn t_flip_flop_toggles_on_rising_edges() {
+        let template = Template::parse(T_FLIP_FLOP).unwrap();
+        let (t, q) = (template.ports[0].pos, template.ports[1].pos);
+        let lever = t + IntVec3::Z;
+        let mut test = stamped(&template, IntVec3::ZERO);
+        test.place(lever, "lever", "face=floor,facing=south,powered=false")
+            .run(20)
+            .assert_unpowered(q);
+        for _ in 0..3 {
+            test.set_lever(lever, true).run(20).assert_powered(q);
+            test.set_lever(lever, false).run(20).assert_powered(q);
+            test.set_lever(lever, true).run(20).assert_unpowered(q);
+            test.set_lever(lever, false).run(20).assert_unpowered(q);
+        }
+    }
+
+    #[test]
+    fn builtin_templates_have_ports() {
+        for template in builtin_templates() {
+            assert!(
+                template
+                    .ports
+                    .iter()
+                    .all(|port| template.world.block_at(port.pos).is_some()),
+                "{} has a port outside of the circuit",
+                template.name
+            );
+        }
+    }
+}
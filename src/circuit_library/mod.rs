@@ -0,0 +1,148 @@
+mod builtin;
+mod template;
+
+use std::{fs, path::Path};
+
+use bevy::prelude::*;
+
+use crate::{
+    block::respawn_blocks,
+    block_picker::SelectedBlockType,
+    block_state::BlockState,
+    cursor::Cursor,
+    int_vec3::IntVec3,
+    lines::LineMaterial,
+    timeline::Timeline,
+    user_input::{sent_command, UiCommand},
+};
+
+pub use self::builtin::builtin_templates;
+pub use self::template::{Port, PortKind, Template};
+
+/// Templates in this directory are added to the library when the app starts.
+pub const LIBRARY_DIR: &str = "library";
+
+/// File extension for templates in the library directory
+pub const TEMPLATE_EXTENSION: &str = "template";
+
+pub struct CircuitLibraryPlugin;
+
+impl Plugin for CircuitLibraryPlugin {
+    fn build(&self, app: &mut App) {
+        // The library is inserted while building the app, not in a startup system, so that the
+        // block picker can list templates when it is spawned.
+        app.insert_resource(CircuitLibrary::load(Path::new(LIBRARY_DIR)))
+            .insert_resource(TemplateRotation::default())
+            .add_system(rotate_template)
+            .add_system(stamp_template);
+    }
+}
+
+/// Templates that can be stamped into the world, built-in templates first.
+#[derive(Debug, Default, Resource)]
+pub struct CircuitLibrary {
+    pub templates: Vec<Template>,
+}
+
+impl CircuitLibrary {
+    /// Loads the built-in templates, and every template file in the given directory. Files that
+    /// cannot be read are skipped with a warning.
+    pub fn load(dir: &Path) -> Self {
+        let mut templates = builtin_templates();
+        let mut paths: Vec<_> = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .filter(|path| {
+                    path.extension().and_then(|ext| ext.to_str()) == Some(TEMPLATE_EXTENSION)
+                })
+                .collect(),
+            Err(_) => vec![],
+        };
+        paths.sort();
+        for path in paths {
+            let template = fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|text| Template::parse(&text));
+            match template {
+                Ok(template) => templates.push(template),
+                Err(err) => warn!("could not load template {}: {:#}", path.display(), err),
+            }
+        }
+        CircuitLibrary { templates }
+    }
+}
+
+/// Number of clockwise quarter turns to apply to a template before stamping it.
+#[derive(Debug, Default, Resource)]
+struct TemplateRotation {
+    quarter_turns: i32,
+}
+
+fn rotate_template(user_input: EventReader<UiCommand>, mut rotation: ResMut<TemplateRotation>) {
+    if sent_command(user_input, UiCommand::RotateTemplate) {
+        rotation.quarter_turns = (rotation.quarter_turns + 1) % 4;
+        info!("template rotated {} degrees", rotation.quarter_turns * 90);
+    }
+}
+
+/// Places the selected template with its lowest corner at the cursor. Nothing is placed if any
+/// block of the template does not fit.
+fn stamp_template(
+    selected: Res<SelectedBlockType>,
+    rotation: Res<TemplateRotation>,
+    library: Res<CircuitLibrary>,
+    user_input: EventReader<UiCommand>,
+    cursor: Res<Cursor>,
+    mut timeline: ResMut<Timeline>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut line_materials: ResMut<Assets<LineMaterial>>,
+    query_blocks: Query<(Entity, &Transform), With<BlockState>>,
+) {
+    if !sent_command(user_input, UiCommand::PlaceBlock) {
+        return;
+    }
+    let (template, transform) = match (selected.template, cursor.place_block_transform) {
+        (Some(index), Some(transform)) => (&library.templates[index], transform),
+        _ => return,
+    };
+    let template = template.rotated(rotation.quarter_turns);
+    let origin = IntVec3::from_translation(transform.translation);
+    let mut world = timeline.design().clone();
+    if let Err(rejected) = template.stamp(&mut world, origin) {
+        info!(
+            "cannot place {} at {:?}: {} at {:?}: {:?}",
+            template.name, origin, rejected.state, rejected.pos, rejected.reason
+        );
+        return;
+    }
+    let changed = match timeline.set_design(&world) {
+        Ok(changed) => changed,
+        Err(rejected) => {
+            info!(
+                "cannot place {} at {:?}: {} at {:?}: {:?}",
+                template.name, origin, rejected[0].state, rejected[0].pos, rejected[0].reason
+            );
+            return;
+        }
+    };
+    respawn_blocks(
+        &mut commands,
+        &asset_server,
+        &mut meshes,
+        &mut materials,
+        &mut line_materials,
+        timeline.design(),
+        &changed,
+        &query_blocks,
+    );
+    for port in template.ports.iter() {
+        let port = Port {
+            pos: origin + port.pos,
+            ..port.clone()
+        };
+        info!("{}: port {}", template.name, port);
+    }
+}
@@ -0,0 +1,318 @@
+//! Templates are stored in a plain text format that extends the block list format with a name,
+//! and with named ports:
+//!
+//! ```text
+//! # an inverter
+//! name inverter
+//! port in a 0 0 0
+//! port out q 1 1 0
+//! block 0 0 0 redstone_wire[east=side,north=none,power=0,south=none,west=none]
+//! block 1 0 0 iron_block
+//! block 1 1 0 redstone_torch[lit=true]
+//! ```
+//!
+//! Ports mark the blocks where signals go in and come out of the circuit. Blank lines, and lines
+//! that start with `#` are ignored.
+
+use std::fmt::{self, Write};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::{
+    block_state::{BlockState, BlockTransform},
+    int_vec3::IntVec3,
+    schematic::RejectedBlock,
+    synthesis::Layout,
+    timeline::WorldState,
+};
+
+/// A small design that can be stamped into the world. Blocks are positioned relative to the
+/// lowest corner of the template, which is at the origin.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    pub name: String,
+    pub world: WorldState,
+    pub ports: Vec<Port>,
+}
+
+/// A named position where a signal enters or leaves a template.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Port {
+    pub name: String,
+    pub kind: PortKind,
+    pub pos: IntVec3,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PortKind {
+    Input,
+    Output,
+}
+
+impl Template {
+    /// Builds a template from a list of blocks. Blocks are placed bottom layer first, and the
+    /// template is moved so that its lowest corner is at the origin.
+    pub fn new(name: &str, blocks: Vec<(IntVec3, BlockState)>, ports: Vec<Port>) -> Result<Self> {
+        let corner = |pick: fn(i32, i32) -> i32| {
+            blocks
+                .iter()
+                .map(|(pos, _)| *pos)
+                .reduce(|a, b| IntVec3::new(pick(a.x, b.x), pick(a.y, b.y), pick(a.z, b.z)))
+                .unwrap_or(IntVec3::ZERO)
+        };
+        let low = corner(i32::min);
+        let high = corner(i32::max) - low;
+        let mut world = WorldState::new((IntVec3::ZERO, high));
+        let mut blocks = blocks;
+        blocks.sort_by_key(|(pos, _)| pos.layer_order_key());
+        for (pos, state) in blocks {
+            world
+                .insert_block(pos - low, state.clone())
+                .map_err(|err| anyhow!("cannot place {} at {:?}: {:?}", state, pos, err))?;
+        }
+        let ports = ports
+            .into_iter()
+            .map(|port| Port {
+                pos: port.pos - low,
+                ..port
+            })
+            .collect();
+        Ok(Template {
+            name: name.to_owned(),
+            world,
+            ports,
+        })
+    }
+
+    /// Makes a template from a synthesized layout. The levers and lamps of the layout become the
+    /// input and output ports.
+    pub fn from_layout(name: &str, layout: &Layout) -> Result<Self> {
+        let port = |kind| {
+            move |(name, pos): &(String, IntVec3)| Port {
+                name: name.clone(),
+                kind,
+                pos: *pos,
+            }
+        };
+        let ports = layout
+            .inputs
+            .iter()
+            .map(port(PortKind::Input))
+            .chain(layout.outputs.iter().map(port(PortKind::Output)))
+            .collect();
+        Self::new(name, blocks_of(&layout.world), ports)
+    }
+
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut name = None;
+        let mut blocks = vec![];
+        let mut ports = vec![];
+        for (index, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+            let result = match keyword {
+                "name" => {
+                    name = Some(rest.trim().to_owned());
+                    Ok(())
+                }
+                "port" => parse_port(rest).map(|port| ports.push(port)),
+                "block" => parse_block(rest).map(|block| blocks.push(block)),
+                _ => Err(anyhow!("unknown keyword, {}", keyword)),
+            };
+            result.with_context(|| format!("invalid line {}", index + 1))?;
+        }
+        let name = name.ok_or(anyhow!("template has no name"))?;
+        Self::new(&name, blocks, ports)
+    }
+
+    pub fn format(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "name {}", self.name);
+        for port in self.ports.iter() {
+            let _ = writeln!(out, "port {}", port);
+        }
+        for (pos, state) in blocks_of(&self.world) {
+            let _ = writeln!(out, "block {} {} {} {}", pos.x, pos.y, pos.z, state);
+        }
+        out
+    }
+
+    /// The number of blocks that the template spans along each axis.
+    pub fn size(&self) -> IntVec3 {
+        self.world.bounds().1 + IntVec3::ONE
+    }
+
+    /// Turns the template clockwise, as seen from above, by the given number of quarter turns.
+    /// Blocks that face a horizontal direction, and wire connections, are turned with it.
+    pub fn rotated(&self, quarter_turns: i32) -> Self {
+        let rotation = BlockTransform::Rotate(quarter_turns);
+        let blocks = blocks_of(&self.world)
+            .into_iter()
+            .map(|(pos, state)| (rotation.pos(pos), rotation.state(&state)))
+            .collect();
+        let ports = self
+            .ports
+            .iter()
+            .map(|port| Port {
+                pos: rotation.pos(port.pos),
+                ..port.clone()
+            })
+            .collect();
+        // Rotating a valid template only changes which way blocks face, so every block still has
+        // the support that it had before.
+        Self::new(&self.name, blocks, ports).unwrap()
+    }
+
+    /// Places a copy of the template into the world with its lowest corner at `origin`. Nothing is
+    /// placed unless every block fits. Returns the placed blocks bottom layer first.
+    pub fn stamp(
+        &self,
+        world: &mut WorldState,
+        origin: IntVec3,
+    ) -> Result<Vec<(IntVec3, BlockState)>, RejectedBlock> {
+        let mut stamped = world.clone();
+        let blocks: Vec<_> = blocks_of(&self.world)
+            .into_iter()
+            .map(|(pos, state)| (origin + pos, state))
+            .collect();
+        for (pos, state) in blocks.iter() {
+            stamped
+                .insert_block(*pos, state.clone())
+                .map_err(|reason| RejectedBlock {
+                    pos: *pos,
+                    state: state.clone(),
+                    reason,
+                })?;
+        }
+        *world = stamped;
+        Ok(blocks)
+    }
+}
+
+impl fmt::Display for Port {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            PortKind::Input => "in",
+            PortKind::Output => "out",
+        };
+        write!(
+            f,
+            "{} {} {} {} {}",
+            kind, self.name, self.pos.x, self.pos.y, self.pos.z
+        )
+    }
+}
+
+fn parse_port(input: &str) -> Result<Port> {
+    let words: Vec<_> = input.split_whitespace().collect();
+    let (kind, name, coords) = match words.as_slice() {
+        [kind, name, coords @ ..] => (*kind, *name, coords),
+        _ => return Err(anyhow!("expected port kind, name, and position")),
+    };
+    let kind = match kind {
+        "in" => PortKind::Input,
+        "out" => PortKind::Output,
+        _ => return Err(anyhow!("unknown port kind, {}", kind)),
+    };
+    Ok(Port {
+        name: name.to_owned(),
+        kind,
+        pos: parse_pos(coords.iter().copied())?,
+    })
+}
+
+fn parse_block(input: &str) -> Result<(IntVec3, BlockState)> {
+    let mut words = input.splitn(4, ' ');
+    let pos = parse_pos(words.by_ref().take(3))?;
+    let state = words.next().ok_or(anyhow!("missing block"))?.parse()?;
+    Ok((pos, state))
+}
+
+fn parse_pos<'a>(words: impl Iterator<Item = &'a str>) -> Result<IntVec3> {
+    let coords = words
+        .map(|word| word.parse::<i32>())
+        .collect::<Result<Vec<_>, _>>()?;
+    match coords.as_slice() {
+        [x, y, z] => Ok(IntVec3::new(*x, *y, *z)),
+        _ => Err(anyhow!("expected 3 integers")),
+    }
+}
+
+/// Blocks ordered bottom layer first so that the list can be placed in order.
+fn blocks_of(world: &WorldState) -> Vec<(IntVec3, BlockState)> {
+    let mut blocks: Vec<_> = world
+        .blocks()
+        .map(|(pos, state)| (pos, state.clone()))
+        .collect();
+    blocks.sort_by_key(|(pos, _)| pos.layer_order_key());
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{block_state::BlockState, int_vec3::IntVec3, timeline::WorldState};
+
+    use super::{PortKind, Template};
+
+    const INVERTER: &str = "\
+name inverter
+port in a 0 0 0
+port out q 2 1 0
+block 0 0 0 redstone_wire[east=side,north=none,power=0,south=none,west=side]
+block 1 0 0 iron_block
+block 2 1 0 repeater[delay=1,facing=west,locked=false,powered=false]
+block 1 1 0 redstone_torch[lit=true]
+block 2 0 0 iron_block
+";
+
+    #[test]
+    fn round_trips_a_template() {
+        let template = Template::parse(INVERTER).unwrap();
+        assert_eq!(template.name, "inverter");
+        assert_eq!(template.size(), IntVec3::new(3, 2, 1));
+        assert_eq!(template.ports[1].kind, PortKind::Output);
+        assert_eq!(Template::parse(&template.format()).unwrap(), template);
+    }
+
+    #[test]
+    fn rotates_blocks_and_ports() {
+        let template = Template::parse(INVERTER).unwrap().rotated(1);
+        assert_eq!(template.size(), IntVec3::new(1, 2, 3));
+        assert_eq!(template.ports[0].pos, IntVec3::new(0, 0, 0));
+        assert_eq!(template.ports[1].pos, IntVec3::new(0, 1, 2));
+        assert_eq!(
+            template.world.block_at(IntVec3::new(0, 0, 0)),
+            Some(&BlockState::new(
+                "redstone_wire",
+                "east=none,north=side,power=0,south=side,west=none"
+            ))
+        );
+        assert_eq!(
+            template
+                .world
+                .block_at(IntVec3::new(0, 1, 2))
+                .and_then(|state| state.facing()),
+            Some(minecraft_assets::schemas::models::BlockFace::North)
+        );
+        assert_eq!(template.rotated(3), Template::parse(INVERTER).unwrap());
+    }
+
+    #[test]
+    fn stamps_only_when_every_block_fits() {
+        let template = Template::parse(INVERTER).unwrap();
+        let mut world = WorldState::new((IntVec3::ZERO, IntVec3::ONE * 15));
+        let placed = template.stamp(&mut world, IntVec3::new(4, 0, 4)).unwrap();
+        assert_eq!(placed.len(), 5);
+        assert!(world.block_at(IntVec3::new(5, 1, 4)).is_some());
+
+        let before = world.clone();
+        let rejected = template
+            .stamp(&mut world, IntVec3::new(14, 0, 4))
+            .unwrap_err();
+        assert_eq!(rejected.pos, IntVec3::new(16, 0, 4));
+        assert_eq!(world, before);
+    }
+}
@@ -9,7 +9,8 @@ pub const PIXELS: f32 = 1.0;
 /// one Minecraft "pixel". A Minecraft block is 16 pixels.
 pub const BLOCKS: f32 = 16.0 * PIXELS;
 
-/// Width, height, and depth of the world. Blocks may not be placed out of these bounds.
+/// Width, height, and depth of a new world. Blocks may not be placed out of the bounds of the
+/// world, which can be changed with `Timeline::resize`.
 pub const WORLD_SIZE: i32 = 16;
 
 /// List of all six block face directions: Down, Up, North, South, West, East
@@ -33,14 +34,20 @@ pub const BLOCK_FACE_NORMALS: [(BlockFace, Vec3); 6] = [
 ];
 
 /// Available block types paired with initial state.
-pub const BLOCK_PALETTE: [&'static str; 5] = [
+pub const BLOCK_PALETTE: [&'static str; 7] = [
     "iron_block",
+    "lever",
+    "redstone_lamp",
     "redstone_torch",
     "redstone_wire",
     "repeater",
     "sandstone",
 ];
 
+/// Font for text in overlays, loaded from the assets directory. Bevy does not come with a font,
+/// so text is not drawn until a font is copied there.
+pub const UI_FONT: &str = "fonts/ui.ttf";
+
 /// Blocks previews for the block picker are rendered to textures via a camera in this layer.
 /// Cameras and other entities may be associated with one or more layers; a camera will only render
 /// entities in a matching layer. Using UI_LAYER lets us render block previews that don't show up
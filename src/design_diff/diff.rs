@@ -0,0 +1,167 @@
+use std::fmt;
+
+use crate::{block_state::BlockState, int_vec3::IntVec3, timeline::WorldState};
+
+/// How the block at one position differs between two worlds.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    /// A block was placed at a position that was empty
+    Added(BlockState),
+
+    /// The block at a position was removed
+    Removed(BlockState),
+
+    /// The position holds a block in both worlds, but a different type or different properties
+    Changed {
+        before: BlockState,
+        after: BlockState,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockChange {
+    pub pos: IntVec3,
+    pub change: Change,
+}
+
+/// Differences between two worlds, such as two saved versions of a design, or the same design at
+/// two ticks. Changes are ordered bottom layer first.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DesignDiff {
+    pub changes: Vec<BlockChange>,
+}
+
+impl DesignDiff {
+    pub fn between(before: &WorldState, after: &WorldState) -> Self {
+        Self::at_positions(before, after, before.changed_positions(after))
+    }
+
+    /// Compares two worlds only at the given positions, such as the positions that a timeline
+    /// stored as changed between two ticks. Positions that hold the same block in both worlds are
+    /// left out.
+    pub fn at_positions(
+        before: &WorldState,
+        after: &WorldState,
+        positions: impl IntoIterator<Item = IntVec3>,
+    ) -> Self {
+        let mut changes: Vec<BlockChange> = positions
+            .into_iter()
+            .filter_map(|pos| {
+                let change = match (before.block_at(pos), after.block_at(pos)) {
+                    (None, Some(after)) => Change::Added(after.clone()),
+                    (Some(before), None) => Change::Removed(before.clone()),
+                    (Some(before), Some(after)) if before == after => return None,
+                    (Some(before), Some(after)) => Change::Changed {
+                        before: before.clone(),
+                        after: after.clone(),
+                    },
+                    (None, None) => return None,
+                };
+                Some(BlockChange { pos, change })
+            })
+            .collect();
+        changes.sort_by_key(|change| change.pos.layer_order_key());
+        DesignDiff { changes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// The change at a position, if there is one.
+    pub fn change_at(&self, pos: IntVec3) -> Option<&Change> {
+        self.changes
+            .iter()
+            .find(|change| change.pos == pos)
+            .map(|change| &change.change)
+    }
+
+    /// Counts added, removed and changed blocks, like `2 added, 0 removed, 1 changed`.
+    pub fn summary(&self) -> String {
+        let count = |matches: fn(&Change) -> bool| {
+            self.changes
+                .iter()
+                .filter(|change| matches(&change.change))
+                .count()
+        };
+        format!(
+            "{} added, {} removed, {} changed",
+            count(|change| matches!(change, Change::Added(_))),
+            count(|change| matches!(change, Change::Removed(_))),
+            count(|change| matches!(change, Change::Changed { .. })),
+        )
+    }
+}
+
+/// Formats one line per change, starting with `+` for added blocks, `-` for removed blocks, and
+/// `~` for changed blocks, followed by the position and the blocks.
+impl fmt::Display for BlockChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let IntVec3 { x, y, z } = self.pos;
+        match &self.change {
+            Change::Added(state) => write!(f, "+ {} {} {} {}", x, y, z, state),
+            Change::Removed(state) => write!(f, "- {} {} {} {}", x, y, z, state),
+            Change::Changed { before, after } => {
+                write!(f, "~ {} {} {} {} -> {}", x, y, z, before, after)
+            }
+        }
+    }
+}
+
+impl fmt::Display for DesignDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in self.changes.iter() {
+            writeln!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{block_state::BlockState, int_vec3::IntVec3, timeline::WorldState};
+
+    use super::{Change, DesignDiff};
+
+    fn world(blocks: &[(IntVec3, &str)]) -> WorldState {
+        let mut world = WorldState::new((IntVec3::ZERO, IntVec3::ONE * 7));
+        for (pos, state) in blocks {
+            world.insert_block(*pos, state.parse().unwrap()).unwrap();
+        }
+        world
+    }
+
+    #[test]
+    fn lists_added_removed_and_changed_blocks() {
+        let before = world(&[
+            (IntVec3::new(0, 0, 0), "sandstone"),
+            (IntVec3::new(1, 0, 0), "redstone_lamp[lit=false]"),
+            (IntVec3::new(2, 0, 0), "iron_block"),
+        ]);
+        let after = world(&[
+            (IntVec3::new(0, 0, 0), "sandstone"),
+            (IntVec3::new(1, 0, 0), "redstone_lamp[lit=true]"),
+            (IntVec3::new(0, 1, 0), "redstone_wire[power=0]"),
+        ]);
+        let diff = DesignDiff::between(&before, &after);
+        assert_eq!(diff.summary(), "1 added, 1 removed, 1 changed");
+        assert_eq!(
+            diff.to_string(),
+            "~ 1 0 0 redstone_lamp[lit=false] -> redstone_lamp[lit=true]\n\
+             - 2 0 0 iron_block\n\
+             + 0 1 0 redstone_wire[power=0]\n"
+        );
+        assert_eq!(
+            diff.change_at(IntVec3::new(2, 0, 0)),
+            Some(&Change::Removed(BlockState::new("iron_block", "")))
+        );
+        assert_eq!(diff.change_at(IntVec3::ZERO), None);
+        assert!(DesignDiff::between(&after, &after).is_empty());
+
+        let positions = [IntVec3::ZERO, IntVec3::new(2, 0, 0)];
+        assert_eq!(
+            DesignDiff::at_positions(&before, &after, positions).to_string(),
+            "- 2 0 0 iron_block\n"
+        );
+    }
+}
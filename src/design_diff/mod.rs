@@ -0,0 +1,254 @@
+mod diff;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use bevy::prelude::*;
+
+use crate::{
+    block::bounding_box_to_line_list,
+    block_state::BlockState,
+    constants::PIXELS,
+    int_vec3::IntVec3,
+    lines::LineMaterial,
+    logic_analyzer::CurrentTick,
+    schematic::Format,
+    timeline::{TimeIndex, Timeline, WorldState},
+    user_input::UiCommand,
+};
+
+pub use self::diff::{BlockChange, Change, DesignDiff};
+
+const ADDED_TINT: Color = Color::rgb(0.4, 1.0, 0.4);
+const CHANGED_TINT: Color = Color::rgb(1.0, 1.0, 0.3);
+const REMOVED_MARKER_COLOR: Color = Color::RED;
+
+pub struct DesignDiffPlugin;
+
+impl Plugin for DesignDiffPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DiffOverlay::default())
+            .init_resource::<DiffBaseFile>()
+            .add_system(diff_overlay_commands)
+            .add_system(update_diff)
+            .add_system(tint_changed_blocks)
+            .add_system(update_removal_markers);
+    }
+}
+
+/// Changes to the design compared with a base. Added blocks are tinted green, changed blocks
+/// yellow, and removed blocks are outlined in red where they used to be.
+#[derive(Debug, Default, Resource)]
+pub struct DiffOverlay {
+    /// What the design is compared with, or `None` while the overlay is off.
+    base: Option<DiffBase>,
+    pub diff: DesignDiff,
+}
+
+/// Design file that the overlay compares with when a base is loaded, given on the command line
+/// with `--diff-base <file>`. The format is picked by the file extension, like in the command
+/// line tool.
+#[derive(Debug, Default, Resource)]
+pub struct DiffBaseFile(pub Option<PathBuf>);
+
+#[derive(Debug)]
+enum DiffBase {
+    /// The design as it was when the overlay was turned on
+    Snapshot(WorldState),
+
+    /// A design loaded from `DiffBaseFile`
+    File(WorldState),
+
+    /// The world at the tick before the current tick, so that the overlay shows what changed on
+    /// the current tick
+    PreviousTick,
+}
+
+impl DiffBase {
+    fn describe(&self) -> &'static str {
+        match self {
+            DiffBase::Snapshot(_) => "since the diff overlay was turned on",
+            DiffBase::File(_) => "compared with the design in the base file",
+            DiffBase::PreviousTick => "on the current tick",
+        }
+    }
+}
+
+#[derive(Component)]
+struct RemovalMarker;
+
+/// Turns the overlay on with the base for the command, or turns it off if it is already on.
+fn diff_overlay_commands(
+    mut user_input: EventReader<UiCommand>,
+    timeline: Res<Timeline>,
+    base_file: Res<DiffBaseFile>,
+    mut overlay: ResMut<DiffOverlay>,
+) {
+    for command in user_input.iter() {
+        if !matches!(
+            command,
+            UiCommand::ToggleDiffOverlay | UiCommand::LoadDiffBase | UiCommand::ToggleTickDiff
+        ) {
+            continue;
+        }
+        if overlay.base.is_some() {
+            overlay.base = None;
+            info!("diff overlay off");
+            continue;
+        }
+        let base = match command {
+            UiCommand::LoadDiffBase => {
+                let path = match &base_file.0 {
+                    Some(path) => path,
+                    None => {
+                        warn!("no design to compare with, start the app with --diff-base <file>");
+                        continue;
+                    }
+                };
+                match load_base(path) {
+                    Ok(base) => DiffBase::File(base),
+                    Err(err) => {
+                        warn!("could not load {}: {:#}", path.display(), err);
+                        continue;
+                    }
+                }
+            }
+            UiCommand::ToggleTickDiff => DiffBase::PreviousTick,
+            _ => DiffBase::Snapshot(timeline.design().clone()),
+        };
+        info!("diff overlay on, showing changes {}", base.describe());
+        overlay.base = Some(base);
+    }
+}
+
+/// Reads a design file and places its blocks, to compare the design with.
+fn load_base(path: &Path) -> Result<WorldState> {
+    let data = fs::read(path)?;
+    let schematic = Format::from_path(path)?.read(&data)?;
+    let timeline = schematic.to_timeline().map_err(|rejected| {
+        anyhow!(
+            "{} blocks cannot be placed, including {} at {:?}",
+            rejected.len(),
+            rejected[0].state,
+            rejected[0].pos
+        )
+    })?;
+    Ok(timeline.design().clone())
+}
+
+/// Compares the design with the base whenever either changes, or with the tick before the
+/// current tick whenever the current tick changes, and logs a summary of the changes.
+fn update_diff(
+    mut timeline: ResMut<Timeline>,
+    current_tick: Res<CurrentTick>,
+    mut overlay: ResMut<DiffOverlay>,
+) {
+    if !(timeline.is_changed() || current_tick.is_changed() || overlay.is_changed()) {
+        return;
+    }
+    let diff = match &overlay.base {
+        Some(DiffBase::Snapshot(base) | DiffBase::File(base)) => {
+            DesignDiff::between(base, timeline.design())
+        }
+        Some(DiffBase::PreviousTick) => {
+            // Only borrow the timeline mutably when more ticks need to be simulated. Otherwise the
+            // change would trigger another comparison on every frame.
+            if timeline.last_simulated_time() < current_tick.0 {
+                timeline.simulate_to(current_tick.0);
+            }
+            tick_diff(&timeline, current_tick.0)
+        }
+        None => DesignDiff::default(),
+    };
+    if diff == overlay.diff {
+        return;
+    }
+    if let Some(base) = &overlay.base {
+        info!("changes {}: {}", base.describe(), diff.summary());
+    }
+    overlay.diff = diff;
+}
+
+/// Changes from the tick before the given tick. Only the positions that the timeline stored as
+/// changed on that tick are compared.
+fn tick_diff(timeline: &Timeline, time: TimeIndex) -> DesignDiff {
+    match (timeline.state_at(time - 1), timeline.state_at(time)) {
+        (Some(before), Some(after)) => {
+            DesignDiff::at_positions(&before, &after, timeline.changed_between(time - 1, time))
+        }
+        _ => DesignDiff::default(),
+    }
+}
+
+/// Sets the color of every textured face of each block to the tint for its change. Blocks that
+/// are spawned after an edit start out untinted, so they are checked as they appear.
+fn tint_changed_blocks(
+    overlay: Res<DiffOverlay>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    query_blocks: Query<(&Transform, &Children), With<BlockState>>,
+    query_new_blocks: Query<(), Added<BlockState>>,
+    query_faces: Query<&Handle<StandardMaterial>>,
+) {
+    if !overlay.is_changed() && query_new_blocks.is_empty() {
+        return;
+    }
+    for (transform, children) in query_blocks.iter() {
+        let pos = IntVec3::from_translation(transform.translation);
+        let tint = match overlay.diff.change_at(pos) {
+            Some(Change::Added(_)) => ADDED_TINT,
+            Some(Change::Changed { .. }) => CHANGED_TINT,
+            _ => Color::WHITE,
+        };
+        for handle in children
+            .iter()
+            .filter_map(|child| query_faces.get(*child).ok())
+        {
+            // Faces without a texture keep the color that marks them as missing.
+            let is_textured = materials
+                .get(handle)
+                .map_or(false, |material| material.base_color_texture.is_some());
+            if !is_textured {
+                continue;
+            }
+            if let Some(material) = materials.get_mut(handle) {
+                if material.base_color != tint {
+                    material.base_color = tint;
+                }
+            }
+        }
+    }
+}
+
+/// Draws a red outline where each removed block used to be.
+fn update_removal_markers(
+    mut commands: Commands,
+    overlay: Res<DiffOverlay>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut line_materials: ResMut<Assets<LineMaterial>>,
+    query_markers: Query<Entity, With<RemovalMarker>>,
+) {
+    if !overlay.is_changed() {
+        return;
+    }
+    for marker in query_markers.iter() {
+        commands.entity(marker).despawn();
+    }
+    let half_size = Vec3::ONE * 8.0 * PIXELS;
+    for change in overlay.diff.changes.iter() {
+        if let Change::Removed(_) = change.change {
+            commands
+                .spawn(MaterialMeshBundle {
+                    mesh: meshes.add(Mesh::from(bounding_box_to_line_list((
+                        -half_size, half_size,
+                    )))),
+                    material: line_materials.add(LineMaterial::new(REMOVED_MARKER_COLOR)),
+                    transform: Transform::from_translation(change.pos.to_translation()),
+                    ..default()
+                })
+                .insert(RemovalMarker);
+        }
+    }
+}
@@ -0,0 +1,260 @@
+//! Runs designs without a window so that circuits can be checked with `cargo test`.
+//!
+//! ```ignore
+//! let mut test = CircuitTest::new();
+//! test.place(IntVec3::new(0, 0, 0), "lever", "face=floor,facing=north,powered=false")
+//!     .place(IntVec3::new(1, 0, 0), "redstone_lamp", "lit=false");
+//! test.toggle(IntVec3::new(0, 0, 0))
+//!     .run(1)
+//!     .assert_state(IntVec3::new(1, 0, 0), "lit", "true");
+//! ```
+
+use minecraft_assets::schemas::blockstates::multipart::StateValue;
+
+use crate::{
+    block_state::BlockState,
+    int_vec3::IntVec3,
+    timeline::{power_level, InputEvent, InputSchedule, TimeIndex, Timeline, WorldState},
+};
+
+/// A design loaded into a timeline together with a current tick. Inputs are applied at the
+/// current tick, and `run` advances the current tick. Methods panic with a description of the
+/// problem instead of returning errors since they are meant to be used in tests.
+#[derive(Clone, Debug, Default)]
+pub struct CircuitTest {
+    timeline: Timeline,
+    time: TimeIndex,
+}
+
+impl CircuitTest {
+    /// Starts with an empty world with the default bounds.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_world(world: WorldState) -> Self {
+        Self::from_timeline(Timeline::from_world(world))
+    }
+
+    /// Starts from an existing timeline, including its input schedule and any inputs that have
+    /// already been inserted.
+    pub fn from_timeline(timeline: Timeline) -> Self {
+        CircuitTest { timeline, time: 0 }
+    }
+
+    /// Adds a block to the design. State values use the same `prop=value,prop=value` format as
+    /// `BlockState::new`. The design can only be changed before the first call to `run`.
+    #[track_caller]
+    pub fn place(&mut self, pos: IntVec3, block_type: &str, state_values: &str) -> &mut Self {
+        assert_eq!(self.time, 0, "blocks can only be placed at tick 0");
+        if let Err(err) = self
+            .timeline
+            .insert_block(pos, BlockState::new(block_type, state_values))
+        {
+            panic!("could not place {} at {:?}: {:?}", block_type, pos, err);
+        }
+        self
+    }
+
+    /// Replaces the input schedule that is replayed from the start of the timeline.
+    pub fn schedule(&mut self, schedule: InputSchedule) -> &mut Self {
+        self.timeline.set_input_schedule(schedule);
+        self
+    }
+
+    /// Flips a lever at the current tick. The change is visible after the next call to `run`.
+    #[track_caller]
+    pub fn toggle(&mut self, pos: IntVec3) -> &mut Self {
+        self.input(InputEvent::ToggleLever(pos))
+    }
+
+    /// Switches a lever on or off at the current tick. The change is visible after the next call
+    /// to `run`.
+    #[track_caller]
+    pub fn set_lever(&mut self, pos: IntVec3, powered: bool) -> &mut Self {
+        self.input(InputEvent::SetLever(pos, powered))
+    }
+
+    #[track_caller]
+    pub fn input(&mut self, event: InputEvent) -> &mut Self {
+        if let Err(err) = self.timeline.insert_input(self.time, event, false) {
+            panic!(
+                "could not apply {:?} at tick {}: {:?}",
+                event, self.time, err
+            );
+        }
+        self
+    }
+
+    /// Advances the current tick by the given number of ticks.
+    pub fn run(&mut self, ticks: TimeIndex) -> &mut Self {
+        self.run_to(self.time + ticks)
+    }
+
+    /// Advances to the given tick. Does nothing if the current tick is already past it.
+    pub fn run_to(&mut self, time: TimeIndex) -> &mut Self {
+        self.time = self.time.max(time);
+        self.timeline.simulate_to(self.time);
+        self
+    }
+
+    pub fn time(&self) -> TimeIndex {
+        self.time
+    }
+
+    pub fn timeline(&self) -> &Timeline {
+        &self.timeline
+    }
+
+    /// The world at the current tick.
+    pub fn world(&self) -> WorldState {
+        self.timeline
+            .state_at(self.time)
+            .expect("current tick has been simulated")
+    }
+
+    pub fn block_at(&self, pos: IntVec3) -> Option<BlockState> {
+        self.world().block_at(pos).cloned()
+    }
+
+    pub fn power_level(&self, pos: IntVec3) -> i32 {
+        power_level(&self.world(), pos)
+    }
+
+    /// Checks that the block at the given position has a property with the given value at the
+    /// current tick. The expected value is parsed the same way as values in `BlockState::new`.
+    #[track_caller]
+    pub fn assert_state(&self, pos: IntVec3, prop: &str, expected: &str) -> &Self {
+        let state = self.expect_block(pos);
+        let expected = StateValue::from(expected);
+        assert!(
+            state.get(prop) == Some(&expected),
+            "at tick {} expected {}={:?} at {:?}, but the block is {:?}",
+            self.time,
+            prop,
+            expected,
+            pos,
+            state
+        );
+        self
+    }
+
+    #[track_caller]
+    pub fn assert_block_type(&self, pos: IntVec3, block_type: &str) -> &Self {
+        let state = self.expect_block(pos);
+        assert_eq!(
+            state.block_type, block_type,
+            "at tick {} expected {} at {:?}",
+            self.time, block_type, pos
+        );
+        self
+    }
+
+    #[track_caller]
+    pub fn assert_power(&self, pos: IntVec3, expected: i32) -> &Self {
+        assert_eq!(
+            self.power_level(pos),
+            expected,
+            "at tick {} expected power level {} at {:?}",
+            self.time,
+            expected,
+            pos
+        );
+        self
+    }
+
+    #[track_caller]
+    pub fn assert_powered(&self, pos: IntVec3) -> &Self {
+        assert!(
+            self.power_level(pos) > 0,
+            "at tick {} expected {:?} to be powered",
+            self.time,
+            pos
+        );
+        self
+    }
+
+    #[track_caller]
+    pub fn assert_unpowered(&self, pos: IntVec3) -> &Self {
+        assert_eq!(
+            self.power_level(pos),
+            0,
+            "at tick {} expected {:?} to be unpowered",
+            self.time,
+            pos
+        );
+        self
+    }
+
+    #[track_caller]
+    fn expect_block(&self, pos: IntVec3) -> BlockState {
+        match self.block_at(pos) {
+            Some(state) => state,
+            None => panic!("at tick {} expected a block at {:?}", self.time, pos),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{int_vec3::IntVec3, timeline::InputSchedule};
+
+    use super::CircuitTest;
+
+    const LEVER: IntVec3 = IntVec3::new(0, 0, 0);
+    const WIRE: IntVec3 = IntVec3::new(1, 0, 0);
+    const BLOCK: IntVec3 = IntVec3::new(2, 0, 0);
+    const TORCH: IntVec3 = IntVec3::new(2, 1, 0);
+
+    fn inverter() -> CircuitTest {
+        let mut test = CircuitTest::new();
+        test.place(LEVER, "lever", "face=floor,facing=north,powered=false")
+            .place(
+                WIRE,
+                "redstone_wire",
+                "east=none,north=none,south=none,west=none,power=0",
+            )
+            .place(BLOCK, "iron_block", "")
+            .place(TORCH, "redstone_torch", "lit=true");
+        test
+    }
+
+    #[test]
+    fn applies_inputs_and_checks_states() {
+        let mut test = inverter();
+        test.run(1)
+            .assert_unpowered(WIRE)
+            .assert_state(TORCH, "lit", "true");
+        test.toggle(LEVER)
+            .run(1)
+            .assert_power(WIRE, 15)
+            .assert_state(TORCH, "lit", "true");
+        test.run(1).assert_state(TORCH, "lit", "false");
+        test.set_lever(LEVER, false)
+            .run(2)
+            .assert_unpowered(WIRE)
+            .assert_powered(TORCH);
+    }
+
+    #[test]
+    fn replays_a_schedule() {
+        let mut test = inverter();
+        test.schedule(InputSchedule::parse("on 0 0 0 @ 3").unwrap());
+        test.run_to(4)
+            .assert_state(LEVER, "powered", "true")
+            .assert_powered(TORCH);
+        test.run(1).assert_unpowered(TORCH);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected lit=Bool(false)")]
+    fn reports_unexpected_states() {
+        inverter().run(1).assert_state(TORCH, "lit", "false");
+    }
+
+    #[test]
+    #[should_panic(expected = "could not apply")]
+    fn rejects_input_for_a_block_that_is_not_a_lever() {
+        inverter().toggle(WIRE);
+    }
+}
@@ -1,6 +1,11 @@
-use std::ops::{Add, Mul};
+use std::ops::{Add, Mul, Neg, Sub};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+use bevy::prelude::*;
+use minecraft_assets::schemas::models::BlockFace;
+
+use crate::constants::{BLOCKS, BLOCK_FACES};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct IntVec3 {
     pub x: i32,
     pub y: i32,
@@ -10,7 +15,55 @@ pub struct IntVec3 {
 impl IntVec3 {
     pub const ZERO: Self = IntVec3 { x: 0, y: 0, z: 0 };
     pub const ONE: Self = IntVec3 { x: 1, y: 1, z: 1 };
-    pub const NEG_Y: Self = IntVec3 { x: 0, y: 0, z: 0 };
+    pub const X: Self = IntVec3 { x: 1, y: 0, z: 0 };
+    pub const Y: Self = IntVec3 { x: 0, y: 1, z: 0 };
+    pub const Z: Self = IntVec3 { x: 0, y: 0, z: 1 };
+    pub const NEG_X: Self = IntVec3 { x: -1, y: 0, z: 0 };
+    pub const NEG_Y: Self = IntVec3 { x: 0, y: -1, z: 0 };
+    pub const NEG_Z: Self = IntVec3 { x: 0, y: 0, z: -1 };
+
+    pub const fn new(x: i32, y: i32, z: i32) -> Self {
+        IntVec3 { x, y, z }
+    }
+
+    /// Key that sorts positions bottom layer first, then by x and by z. Blocks listed in this
+    /// order come after the blocks that they rest on.
+    pub fn layer_order_key(self) -> (i32, i32, i32) {
+        (self.y, self.x, self.z)
+    }
+
+    /// Unit vector pointing out of the given face of a block. Matches the conventions in
+    /// `BLOCK_FACE_NORMALS`: north is toward negative z, and west is toward negative x.
+    pub fn from_block_face(face: BlockFace) -> Self {
+        match face {
+            BlockFace::Down => Self::NEG_Y,
+            BlockFace::Up => Self::Y,
+            BlockFace::North => Self::NEG_Z,
+            BlockFace::South => Self::Z,
+            BlockFace::West => Self::NEG_X,
+            BlockFace::East => Self::X,
+        }
+    }
+
+    /// Converts a translation in render space to a position in the block grid.
+    pub fn from_translation(translation: Vec3) -> Self {
+        let pos = (translation / BLOCKS).round();
+        IntVec3::new(pos.x as i32, pos.y as i32, pos.z as i32)
+    }
+
+    /// Converts a position in the block grid to the translation for the center of that block in
+    /// render space.
+    pub fn to_translation(self) -> Vec3 {
+        Vec3::new(self.x as f32, self.y as f32, self.z as f32) * BLOCKS
+    }
+
+    /// The six positions that share a face with this one, paired with the face of this position
+    /// that each neighbor touches.
+    pub fn neighbors(self) -> impl Iterator<Item = (BlockFace, IntVec3)> {
+        BLOCK_FACES
+            .into_iter()
+            .map(move |face| (face, self + Self::from_block_face(face)))
+    }
 }
 
 impl Add<IntVec3> for IntVec3 {
@@ -24,6 +77,24 @@ impl Add<IntVec3> for IntVec3 {
     }
 }
 
+impl Sub<IntVec3> for IntVec3 {
+    type Output = Self;
+    fn sub(self, rhs: IntVec3) -> Self::Output {
+        IntVec3 {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+            z: self.z - rhs.z,
+        }
+    }
+}
+
+impl Neg for IntVec3 {
+    type Output = Self;
+    fn neg(self) -> Self::Output {
+        self * -1
+    }
+}
+
 impl Mul<i32> for IntVec3 {
     type Output = Self;
     fn mul(self, rhs: i32) -> Self::Output {
@@ -0,0 +1,26 @@
+#![feature(option_result_contains)]
+
+pub mod anvil;
+pub mod bill_of_materials;
+pub mod block;
+pub mod block_picker;
+pub mod block_state;
+pub mod camera;
+pub mod circuit_library;
+pub mod constants;
+pub mod cursor;
+pub mod design_diff;
+pub mod harness;
+pub mod int_vec3;
+pub mod lines;
+pub mod logic_analyzer;
+pub mod redstone;
+pub mod schematic;
+pub mod selection;
+pub mod signal_trace;
+pub mod synthesis;
+pub mod survival;
+pub mod timeline;
+pub mod truth_table;
+pub mod user_input;
+pub mod util;
@@ -0,0 +1,349 @@
+mod waveform;
+
+use std::fs;
+
+use bevy::prelude::*;
+
+use crate::{
+    block::bounding_box_to_line_list,
+    block_state::BlockState,
+    constants::{PIXELS, UI_FONT},
+    cursor::Cursor,
+    int_vec3::IntVec3,
+    lines::LineMaterial,
+    timeline::{TimeIndex, Timeline, MAX_POWER},
+    user_input::{sent_command, UiCommand},
+};
+
+pub use self::waveform::{Probe, Pulse, Waveform};
+
+/// Number of ticks shown in the waveform panel, and written to exported files.
+const WAVEFORM_TICKS: TimeIndex = 100;
+
+/// Exported waveforms are written to this file in the working directory.
+const WAVEFORM_FILE: &str = "waveform.vcd";
+
+const TICK_WIDTH: f32 = 8.0; // px
+const ROW_HEIGHT: f32 = 24.0; // px
+const READOUT_FONT_SIZE: f32 = 16.0;
+
+const PANEL_BACKGROUND_COLOR: Color = Color::rgba(0.1, 0.1, 0.1, 0.85);
+const CURSOR_COLORS: [Color; 2] = [Color::rgb(0.5, 0.5, 0.2), Color::rgb(0.2, 0.5, 0.5)];
+
+/// Each probe gets a color that is used for its marker in the world, and for its row in the
+/// waveform panel.
+const PROBE_COLORS: [Color; 6] = [
+    Color::RED,
+    Color::LIME_GREEN,
+    Color::ORANGE,
+    Color::FUCHSIA,
+    Color::AQUAMARINE,
+    Color::GOLD,
+];
+
+pub struct LogicAnalyzerPlugin;
+
+impl Plugin for LogicAnalyzerPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Probes::default())
+            .insert_resource(CurrentTick::default())
+            .insert_resource(LogicAnalyzer::default())
+            .add_startup_system(spawn_waveform_panel)
+            .add_system(toggle_probe)
+            .add_system(update_probe_markers)
+            .add_system(logic_analyzer_commands)
+            .add_system(draw_waveforms);
+    }
+}
+
+/// Blocks whose power levels are shown in the logic analyzer.
+#[derive(Debug, Default, Resource)]
+pub struct Probes(pub Vec<Probe>);
+
+/// Tick selected by the first logic analyzer cursor. Overlays that show the simulated state of
+/// the world show it at this tick.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Resource)]
+pub struct CurrentTick(pub TimeIndex);
+
+#[derive(Debug, Default, Resource)]
+struct LogicAnalyzer {
+    is_open: bool,
+
+    /// Two cursors mark ticks in the waveform panel. The panel shows the distance between them,
+    /// and the levels, pulse widths and delays at each probe.
+    cursors: [TimeIndex; 2],
+}
+
+#[derive(Component)]
+struct WaveformPanel;
+
+#[derive(Component)]
+struct ProbeMarker;
+
+fn probe_color(index: usize) -> Color {
+    PROBE_COLORS[index % PROBE_COLORS.len()]
+}
+
+fn spawn_waveform_panel(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                display: Display::None,
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(0.0),
+                    bottom: Val::Px(0.0),
+                    ..default()
+                },
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(6.0)),
+                ..default()
+            },
+            background_color: PANEL_BACKGROUND_COLOR.into(),
+            ..default()
+        })
+        .insert(WaveformPanel);
+}
+
+fn toggle_probe(
+    user_input: EventReader<UiCommand>,
+    cursor: Res<Cursor>,
+    mut probes: ResMut<Probes>,
+    query_blocks: Query<&Transform, With<BlockState>>,
+) {
+    if !sent_command(user_input, UiCommand::ToggleProbe) {
+        return;
+    }
+    let pos = match cursor
+        .current_block
+        .and_then(|block| query_blocks.get(block).ok())
+    {
+        Some(transform) => IntVec3::from_translation(transform.translation),
+        None => return,
+    };
+    match probes.0.iter().position(|probe| probe.pos == pos) {
+        Some(index) => {
+            probes.0.remove(index);
+        }
+        None => probes.0.push(Probe::at(pos)),
+    }
+}
+
+/// Draws a colored outline around each probed block.
+fn update_probe_markers(
+    mut commands: Commands,
+    probes: Res<Probes>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut line_materials: ResMut<Assets<LineMaterial>>,
+    query_markers: Query<Entity, With<ProbeMarker>>,
+) {
+    if !probes.is_changed() {
+        return;
+    }
+    for marker in query_markers.iter() {
+        commands.entity(marker).despawn();
+    }
+    // Slightly larger than a block so that the marker is not hidden by the block outline.
+    let half_size = Vec3::ONE * 8.5 * PIXELS;
+    for (index, probe) in probes.0.iter().enumerate() {
+        commands
+            .spawn(MaterialMeshBundle {
+                mesh: meshes.add(Mesh::from(bounding_box_to_line_list((
+                    -half_size, half_size,
+                )))),
+                material: line_materials.add(LineMaterial::new(probe_color(index))),
+                transform: Transform::from_translation(probe.pos.to_translation()),
+                ..default()
+            })
+            .insert(ProbeMarker);
+    }
+}
+
+fn logic_analyzer_commands(
+    mut user_input: EventReader<UiCommand>,
+    mut analyzer: ResMut<LogicAnalyzer>,
+    mut current_tick: ResMut<CurrentTick>,
+    mut timeline: ResMut<Timeline>,
+    probes: Res<Probes>,
+) {
+    for command in user_input.iter() {
+        match command {
+            UiCommand::ToggleLogicAnalyzer => analyzer.is_open = !analyzer.is_open,
+            UiCommand::MoveAnalyzerCursor { cursor, ticks } => {
+                let time = (analyzer.cursors[*cursor] + ticks).clamp(0, WAVEFORM_TICKS - 1);
+                analyzer.cursors[*cursor] = time;
+                if *cursor == 0 {
+                    current_tick.0 = time;
+                }
+            }
+            UiCommand::ExportWaveform => {
+                let waveform = Waveform::capture(&mut timeline, &probes.0, 0..=WAVEFORM_TICKS - 1);
+                match fs::write(WAVEFORM_FILE, waveform.to_vcd()) {
+                    Ok(()) => info!("wrote waveform to {}", WAVEFORM_FILE),
+                    Err(err) => error!("could not write {}: {}", WAVEFORM_FILE, err),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Describes the ticks at the cursors, shown above the rows of the waveform panel.
+fn cursor_readout(cursors: [TimeIndex; 2]) -> String {
+    let [a, b] = cursors;
+    format!(
+        "cursors at ticks {} and {}, {} ticks apart",
+        a,
+        b,
+        (b - a).abs()
+    )
+}
+
+/// Describes a probe at the cursors, shown at the end of its row in the waveform panel: the power
+/// levels at both cursors, the width of the pulse under the first cursor, and the delay from the
+/// first probe to this one after the earlier cursor.
+fn probe_readout(waveform: &Waveform, index: usize, cursors: [TimeIndex; 2]) -> String {
+    let [a, b] = cursors;
+    let mut readout = format!(
+        "{}: power {} at tick {}, {} at tick {}",
+        waveform.probes[index].name,
+        waveform.level_at(index, a).unwrap_or(0),
+        a,
+        waveform.level_at(index, b).unwrap_or(0),
+        b
+    );
+    let width = waveform
+        .pulses(index)
+        .into_iter()
+        .find(|pulse| pulse.start <= a && pulse.end.map_or(true, |end| a < end))
+        .and_then(|pulse| pulse.width());
+    if let Some(width) = width {
+        readout.push_str(&format!(", pulse {} ticks wide", width));
+    }
+    if index > 0 {
+        if let Some(delay) = waveform.delay(0, index, a.min(b)) {
+            readout.push_str(&format!(
+                ", {} ticks after {}",
+                delay, waveform.probes[0].name
+            ));
+        }
+    }
+    readout
+}
+
+/// Redraws the waveform panel when the design, the probes, or the cursors change. Each probe gets
+/// a row with one bar per tick whose height is proportional to the power level at that tick,
+/// followed by a readout of the measurements at the cursors.
+fn draw_waveforms(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    analyzer: Res<LogicAnalyzer>,
+    probes: Res<Probes>,
+    mut timeline: ResMut<Timeline>,
+    mut query_panel: Query<(Entity, &mut Style), With<WaveformPanel>>,
+) {
+    if !(analyzer.is_changed() || probes.is_changed() || timeline.is_changed()) {
+        return;
+    }
+    let (panel, mut panel_style) = query_panel.single_mut();
+    panel_style.display = if analyzer.is_open {
+        Display::Flex
+    } else {
+        Display::None
+    };
+    commands.entity(panel).despawn_descendants();
+    if !analyzer.is_open {
+        return;
+    }
+
+    // Only borrow the timeline mutably when more ticks need to be simulated. Otherwise the change
+    // would trigger a redraw on every frame.
+    if timeline.last_simulated_time() < WAVEFORM_TICKS - 1 {
+        timeline.simulate_to(WAVEFORM_TICKS - 1);
+    }
+    let waveform = Waveform::record(&timeline, &probes.0, 0..=WAVEFORM_TICKS - 1);
+    let text_style = TextStyle {
+        font: asset_server.load(UI_FONT),
+        font_size: READOUT_FONT_SIZE,
+        color: Color::WHITE,
+    };
+
+    commands.entity(panel).with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            cursor_readout(analyzer.cursors),
+            text_style.clone(),
+        ));
+        for (index, _) in waveform.probes.iter().enumerate() {
+            let color = probe_color(index);
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Auto, Val::Px(ROW_HEIGHT)),
+                        margin: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    // Swatch that matches the color of the probe marker in the world
+                    row.spawn(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(ROW_HEIGHT), Val::Px(ROW_HEIGHT)),
+                            margin: UiRect {
+                                right: Val::Px(6.0),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        background_color: color.into(),
+                        ..default()
+                    });
+                    for time in 0..WAVEFORM_TICKS {
+                        let level = waveform.level_at(index, time).unwrap_or(0);
+                        let cell_color = match analyzer.cursors.iter().position(|c| *c == time) {
+                            Some(cursor) => CURSOR_COLORS[cursor],
+                            None => Color::NONE,
+                        };
+                        row.spawn(NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(TICK_WIDTH), Val::Percent(100.0)),
+                                // The UI y axis points up so `FlexStart` aligns bars to the
+                                // bottom of the row.
+                                align_items: AlignItems::FlexStart,
+                                ..default()
+                            },
+                            background_color: cell_color.into(),
+                            ..default()
+                        })
+                        .with_children(|cell| {
+                            cell.spawn(NodeBundle {
+                                style: Style {
+                                    size: Size::new(
+                                        Val::Percent(100.0),
+                                        Val::Percent(100.0 * level as f32 / MAX_POWER as f32),
+                                    ),
+                                    ..default()
+                                },
+                                background_color: color.into(),
+                                ..default()
+                            });
+                        });
+                    }
+                    row.spawn(
+                        TextBundle::from_section(
+                            probe_readout(&waveform, index, analyzer.cursors),
+                            text_style.clone(),
+                        )
+                        .with_style(Style {
+                            margin: UiRect {
+                                left: Val::Px(6.0),
+                                ..default()
+                            },
+                            align_self: AlignSelf::Center,
+                            ..default()
+                        }),
+                    );
+                });
+        }
+    });
+}
@@ -0,0 +1,299 @@
+use std::{fmt::Write, ops::RangeInclusive};
+
+use crate::{
+    int_vec3::IntVec3,
+    timeline::{power_level, TimeIndex, Timeline},
+};
+
+/// A block whose power level is recorded by the logic analyzer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Probe {
+    pub name: String,
+    pub pos: IntVec3,
+}
+
+impl Probe {
+    /// Creates a probe named after its position.
+    pub fn at(pos: IntVec3) -> Self {
+        Probe {
+            name: format!("x{}_y{}_z{}", pos.x, pos.y, pos.z),
+            pos,
+        }
+    }
+}
+
+/// Power levels for a set of probes recorded over a range of ticks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Waveform {
+    pub probes: Vec<Probe>,
+    pub start: TimeIndex,
+
+    /// Power levels indexed by probe, and then by tick relative to `start`
+    samples: Vec<Vec<i32>>,
+}
+
+/// A span of ticks where a signal is on. `end` is the first tick after the pulse where the signal
+/// is off again, or `None` if the signal is still on at the end of the recording.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pulse {
+    pub start: TimeIndex,
+    pub end: Option<TimeIndex>,
+}
+
+impl Pulse {
+    /// Length of the pulse in ticks, if the pulse ended during the recording.
+    pub fn width(&self) -> Option<TimeIndex> {
+        self.end.map(|end| end - self.start)
+    }
+}
+
+impl Waveform {
+    /// Records the power level at each probe for every tick in the given range, simulating the
+    /// timeline as far as necessary.
+    pub fn capture(
+        timeline: &mut Timeline,
+        probes: &[Probe],
+        range: RangeInclusive<TimeIndex>,
+    ) -> Self {
+        timeline.simulate_to(*range.end());
+        Self::record(timeline, probes, range)
+    }
+
+    /// Records power levels from ticks that have already been simulated. Ticks that have not been
+    /// simulated are recorded as unpowered.
+    pub fn record(timeline: &Timeline, probes: &[Probe], range: RangeInclusive<TimeIndex>) -> Self {
+        let samples = probes
+            .iter()
+            .map(|probe| {
+                range
+                    .clone()
+                    .map(|time| match timeline.state_at(time) {
+                        Some(world) => power_level(&world, probe.pos),
+                        None => 0,
+                    })
+                    .collect()
+            })
+            .collect();
+        Waveform {
+            probes: probes.to_vec(),
+            start: *range.start(),
+            samples,
+        }
+    }
+
+    /// Number of ticks in the recording.
+    pub fn len(&self) -> usize {
+        self.samples.first().map_or(0, |samples| samples.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn end(&self) -> TimeIndex {
+        self.start + self.len() as TimeIndex - 1
+    }
+
+    /// Power level at a probe for the given tick.
+    pub fn level_at(&self, probe: usize, time: TimeIndex) -> Option<i32> {
+        let index = usize::try_from(time - self.start).ok()?;
+        self.samples.get(probe)?.get(index).copied()
+    }
+
+    /// Ticks where the signal at a probe switches between off and on. Each item is a tick paired
+    /// with `true` for a rising edge, or `false` for a falling edge.
+    pub fn edges(&self, probe: usize) -> Vec<(TimeIndex, bool)> {
+        let samples = match self.samples.get(probe) {
+            Some(samples) => samples,
+            None => return vec![],
+        };
+        samples
+            .windows(2)
+            .enumerate()
+            .filter_map(|(index, pair)| {
+                let (before, after) = (pair[0] > 0, pair[1] > 0);
+                if before != after {
+                    Some((self.start + index as TimeIndex + 1, after))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Spans of ticks where the signal at a probe is on. A signal that is on at the start of the
+    /// recording is not counted as a pulse since its start time is not known.
+    pub fn pulses(&self, probe: usize) -> Vec<Pulse> {
+        let mut pulses = vec![];
+        let mut current: Option<TimeIndex> = None;
+        for (time, rising) in self.edges(probe) {
+            match (rising, current) {
+                (true, _) => current = Some(time),
+                (false, Some(start)) => {
+                    pulses.push(Pulse {
+                        start,
+                        end: Some(time),
+                    });
+                    current = None;
+                }
+                (false, None) => (),
+            }
+        }
+        if let Some(start) = current {
+            pulses.push(Pulse { start, end: None });
+        }
+        pulses
+    }
+
+    /// Number of ticks from the first rising edge at or after `after` on the `from` probe to the
+    /// next rising edge on the `to` probe. This measures propagation delay through a circuit.
+    pub fn delay(&self, from: usize, to: usize, after: TimeIndex) -> Option<TimeIndex> {
+        let (start, _) = self
+            .edges(from)
+            .into_iter()
+            .find(|(time, rising)| *rising && *time >= after)?;
+        let (end, _) = self
+            .edges(to)
+            .into_iter()
+            .find(|(time, rising)| *rising && *time >= start)?;
+        Some(end - start)
+    }
+
+    /// Serializes the recording in Value Change Dump format which can be viewed in a waveform
+    /// viewer such as GTKWave. Each probe is a 4-bit signal holding the power level. One redstone
+    /// tick is 100ms.
+    pub fn to_vcd(&self) -> String {
+        let mut out = String::new();
+        let ids: Vec<String> = (0..self.probes.len()).map(vcd_identifier).collect();
+        let _ = writeln!(out, "$version redstone_designer $end");
+        let _ = writeln!(out, "$timescale 100 ms $end");
+        let _ = writeln!(out, "$scope module circuit $end");
+        for (probe, id) in self.probes.iter().zip(ids.iter()) {
+            let _ = writeln!(out, "$var wire 4 {} {} $end", id, vcd_name(&probe.name));
+        }
+        let _ = writeln!(out, "$upscope $end");
+        let _ = writeln!(out, "$enddefinitions $end");
+        for offset in 0..self.len() {
+            let time = self.start + offset as TimeIndex;
+            let changes: Vec<String> = self
+                .samples
+                .iter()
+                .zip(ids.iter())
+                .filter(|(samples, _)| offset == 0 || samples[offset] != samples[offset - 1])
+                .map(|(samples, id)| format!("b{:b} {}", samples[offset], id))
+                .collect();
+            if !changes.is_empty() {
+                let _ = writeln!(out, "#{}", time);
+                for change in changes {
+                    let _ = writeln!(out, "{}", change);
+                }
+            }
+        }
+        let _ = writeln!(out, "#{}", self.end() + 1);
+        out
+    }
+}
+
+/// VCD identifiers are short strings of printable ASCII characters.
+fn vcd_identifier(index: usize) -> String {
+    const FIRST: u8 = b'!';
+    const COUNT: usize = (b'~' - b'!' + 1) as usize;
+    let mut id = String::new();
+    let mut n = index;
+    loop {
+        id.push((FIRST + (n % COUNT) as u8) as char);
+        n /= COUNT;
+        if n == 0 {
+            return id;
+        }
+        n -= 1;
+    }
+}
+
+/// Signal names in VCD may not contain whitespace.
+fn vcd_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join("_")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        block_state::BlockState,
+        int_vec3::IntVec3,
+        timeline::{InputSchedule, Timeline},
+    };
+
+    use super::{Probe, Pulse, Waveform};
+
+    const LEVER: IntVec3 = IntVec3::new(1, 1, 1);
+    const WIRE: IntVec3 = IntVec3::new(2, 1, 1);
+    const REPEATER: IntVec3 = IntVec3::new(3, 1, 1);
+
+    fn test_waveform() -> Waveform {
+        let mut timeline = Timeline::default();
+        let blocks = [
+            (LEVER, "lever", "face=floor,facing=north,powered=false"),
+            (
+                WIRE,
+                "redstone_wire",
+                "east=none,north=none,south=none,west=none,power=0",
+            ),
+            (
+                REPEATER,
+                "repeater",
+                "delay=2,facing=west,locked=false,powered=false",
+            ),
+        ];
+        for (pos, block_type, state) in blocks {
+            timeline
+                .insert_block(pos + IntVec3::NEG_Y, BlockState::new("sandstone", ""))
+                .unwrap();
+            timeline
+                .insert_block(pos, BlockState::new(block_type, state))
+                .unwrap();
+        }
+        timeline.set_input_schedule(InputSchedule::parse("toggle 1 1 1 @ 1, 5").unwrap());
+        Waveform::capture(
+            &mut timeline,
+            &[Probe::at(WIRE), Probe::at(REPEATER)],
+            0..=10,
+        )
+    }
+
+    #[test]
+    fn measures_pulses_and_delays() {
+        let waveform = test_waveform();
+        assert_eq!(waveform.len(), 11);
+        assert_eq!(
+            waveform.pulses(0),
+            vec![Pulse {
+                start: 2,
+                end: Some(6)
+            }]
+        );
+        assert_eq!(waveform.pulses(0)[0].width(), Some(4));
+        assert_eq!(
+            waveform.delay(0, 1, 0),
+            Some(2),
+            "repeater delays by 2 ticks"
+        );
+        assert_eq!(
+            waveform.pulses(1)[0].width(),
+            Some(4),
+            "pulse width is kept"
+        );
+    }
+
+    #[test]
+    fn exports_vcd() {
+        let vcd = test_waveform().to_vcd();
+        assert!(vcd.contains("$var wire 4 ! x2_y1_z1 $end\n"));
+        assert!(vcd.contains("$var wire 4 \" x3_y1_z1 $end\n"));
+        assert!(vcd.contains("#2\nb1111 !\n"), "wire switches on at tick 2");
+        assert!(
+            vcd.contains("#4\nb1111 \"\n"),
+            "repeater switches on at tick 4"
+        );
+        assert!(vcd.ends_with("#11\n"));
+    }
+}
@@ -1,40 +1,50 @@
-#![feature(option_result_contains)]
-
-mod block;
-mod block_picker;
-mod block_state;
-mod camera;
-mod constants;
-mod cursor;
-mod lines;
-mod redstone;
-mod timeline;
-mod user_input;
-mod util;
-mod int_vec3;
+use std::{env, path::PathBuf};
 
 use bevy::{prelude::*, render::texture::ImagePlugin};
 use bevy_rapier3d::prelude::*;
-use block::BlockPlugin;
-use block_picker::BlockPickerPlugin;
-use camera::CameraPlugin;
-use cursor::CursorPlugin;
-use redstone::RedstonePlugin;
-use timeline::Timeline;
-use user_input::UserInputPlugin;
+use redstone_designer::{
+    block::BlockPlugin,
+    block_picker::BlockPickerPlugin,
+    camera::CameraPlugin,
+    circuit_library::CircuitLibraryPlugin,
+    cursor::CursorPlugin,
+    design_diff::{DesignDiffPlugin, DiffBaseFile},
+    logic_analyzer::LogicAnalyzerPlugin,
+    redstone::RedstonePlugin,
+    selection::SelectionPlugin,
+    signal_trace::SignalTracePlugin,
+    survival::SurvivalCheckPlugin,
+    timeline::Timeline,
+    user_input::UserInputPlugin,
+};
 
 fn main() {
     App::new()
         .insert_resource(ClearColor(Color::rgb(0.43, 0.69, 1.0))) // sky color
         .insert_resource(Timeline::default())
+        .insert_resource(DiffBaseFile(diff_base_from_args()))
         .add_plugins(DefaultPlugins.set(ImagePlugin::default_nearest()))
         .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
         // .add_plugin(RapierDebugRenderPlugin::default())
         .add_plugin(CursorPlugin)
         .add_plugin(CameraPlugin)
         .add_plugin(BlockPlugin)
+        .add_plugin(CircuitLibraryPlugin)
         .add_plugin(BlockPickerPlugin)
+        .add_plugin(LogicAnalyzerPlugin)
+        .add_plugin(SignalTracePlugin)
+        .add_plugin(SelectionPlugin)
+        .add_plugin(SurvivalCheckPlugin)
+        .add_plugin(DesignDiffPlugin)
         .add_plugin(RedstonePlugin)
         .add_plugin(UserInputPlugin)
         .run();
 }
+
+/// Reads the design file to compare with from `--diff-base <file>` on the command line.
+fn diff_base_from_args() -> Option<PathBuf> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    args.windows(2)
+        .find(|pair| pair[0] == "--diff-base")
+        .map(|pair| PathBuf::from(&pair[1]))
+}
@@ -1,43 +1,126 @@
-use crate::{
-    block::spawn_block,
-    constants::{BLOCKS, WORLD_SIZE},
-    lines::LineMaterial, block_state::BlockState,
+use crate::{constants::BLOCKS, int_vec3::IntVec3, timeline::Timeline, user_input::UiCommand};
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
 };
-use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+const FLOOR_TEXTURE: &str = "minecraft/assets/minecraft/textures/block/sandstone_top.png";
 
 pub struct RedstonePlugin;
 
+/// Marks the floor that is laid under the world. The floor is not part of the design.
+#[derive(Component)]
+struct Floor;
+
 impl Plugin for RedstonePlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(setup_floor)
+        app.add_system(resize_world)
+            .add_system(update_floor)
             .add_startup_system(setup_lights);
     }
 }
 
-fn setup_floor(
+/// Moves the east and south edges of the world. Shrinking the world fails if it would leave any
+/// block out of bounds.
+fn resize_world(mut user_input: EventReader<UiCommand>, mut timeline: ResMut<Timeline>) {
+    let change = match user_input.iter().find_map(|command| match command {
+        UiCommand::ResizeWorld(change) => Some(*change),
+        _ => None,
+    }) {
+        Some(change) => change,
+        None => return,
+    };
+    let (low, high) = timeline.bounds();
+    let high = high + IntVec3::new(change, 0, change);
+    if high.x < low.x || high.z < low.z {
+        return;
+    }
+    match timeline.resize((low, high)) {
+        Ok(()) => info!("resized world to {:?}", high - low + IntVec3::ONE),
+        Err(rejected) => info!(
+            "cannot resize world: {} blocks would not fit, including {} at {:?}",
+            rejected.len(),
+            rejected[0].state,
+            rejected[0].pos
+        ),
+    }
+}
+
+/// Lays a floor of sandstone under the world bounds, and lays it again whenever the bounds change.
+fn update_floor(
     mut commands: Commands,
+    timeline: Res<Timeline>,
+    mut floor_bounds: Local<Option<(IntVec3, IntVec3)>>,
     asset_server: Res<AssetServer>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    mut line_materials: ResMut<Assets<LineMaterial>>,
+    query_floor: Query<Entity, With<Floor>>,
 ) {
-    let block_type = "sandstone";
-    let block = (block_type, BlockState::initial_state_for(block_type));
-    for x in 0..WORLD_SIZE {
-        for z in 0..WORLD_SIZE {
-            let transform =
-                Transform::from_xyz(x as f32 * BLOCKS, -1.0 * BLOCKS, z as f32 * BLOCKS);
-            spawn_block(
-                &mut commands,
-                &asset_server,
-                &mut meshes,
-                &mut materials,
-                &mut line_materials,
-                block.clone(),
-                transform,
-            );
+    let bounds = timeline.bounds();
+    if *floor_bounds == Some(bounds) {
+        return;
+    }
+    *floor_bounds = Some(bounds);
+    for floor in query_floor.iter() {
+        commands.entity(floor).despawn();
+    }
+    let (low, high) = bounds;
+    let size = high - low + IntVec3::ONE;
+    let center = Vec3::new(
+        (low.x + high.x) as f32 / 2.0,
+        (low.y - 1) as f32,
+        (low.z + high.z) as f32 / 2.0,
+    ) * BLOCKS;
+    commands
+        .spawn(PbrBundle {
+            mesh: meshes.add(floor_mesh(size)),
+            material: materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load(FLOOR_TEXTURE)),
+                unlit: true,
+                ..default()
+            }),
+            transform: Transform::from_translation(center),
+            ..default()
+        })
+        .insert(Collider::cuboid(
+            size.x as f32 * BLOCKS / 2.0,
+            BLOCKS / 2.0,
+            size.z as f32 * BLOCKS / 2.0,
+        ))
+        .insert(Floor);
+}
+
+/// The top of a floor that is `size.x` by `size.z` blocks, centered on the origin like a block.
+/// Each block gets its own square with the whole texture so that the texture repeats across the
+/// floor.
+fn floor_mesh(size: IntVec3) -> Mesh {
+    let half_block = BLOCKS / 2.0;
+    let corner = Vec3::new(size.x as f32, 0.0, size.z as f32) * -half_block;
+    let mut positions = vec![];
+    let mut indices = vec![];
+    for x in 0..size.x {
+        for z in 0..size.z {
+            let min = corner + Vec3::new(x as f32, 0.0, z as f32) * BLOCKS;
+            let start = positions.len() as u32;
+            positions.extend([
+                [min.x + BLOCKS, half_block, min.z],
+                [min.x, half_block, min.z],
+                [min.x, half_block, min.z + BLOCKS],
+                [min.x + BLOCKS, half_block, min.z + BLOCKS],
+            ]);
+            indices.extend([start, start + 1, start + 2, start + 2, start + 3, start]);
         }
     }
+    let normals: Vec<[f32; 3]> = vec![[0.0, 1.0, 0.0]; positions.len()];
+    let uvs: Vec<[f32; 2]> =
+        [[1.0, 0.0], [0.0, 0.0], [0.0, 1.0], [1.0, 1.0]].repeat(positions.len() / 4);
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
 }
 
 fn setup_lights(mut commands: Commands) {
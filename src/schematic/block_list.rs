@@ -0,0 +1,158 @@
+//! A plain text format with one line per block. Each line starts with a keyword:
+//!
+//! ```text
+//! # a lever that powers a lamp
+//! bounds 0 0 0 16 16 16
+//! block 0 0 0 lever[face=floor,facing=north,powered=false]
+//! block 1 0 0 redstone_lamp[lit=false]
+//! input toggle 0 0 0 @ 0, 10
+//! ```
+//!
+//! `bounds` gives the lowest and highest corners of the world, and defaults to the bounds of a new
+//! timeline. `input` lines use the format of `InputSchedule`. Blank lines, and lines that start
+//! with `#` are ignored.
+
+use std::fmt::Write;
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::{block_state::BlockState, int_vec3::IntVec3};
+
+use super::Schematic;
+
+pub fn parse(input: &str) -> Result<Schematic> {
+    let mut schematic = Schematic::empty();
+    for (index, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        parse_line(&mut schematic, line).with_context(|| format!("invalid line {}", index + 1))?;
+    }
+    Ok(schematic)
+}
+
+fn parse_line(schematic: &mut Schematic, line: &str) -> Result<()> {
+    let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+    if schematic.parse_shared_line(keyword, rest)? {
+        return Ok(());
+    }
+    match keyword {
+        "block" => {
+            let coords = parse_ints(rest.split_whitespace().take(3), 3)?;
+            // The block is the rest of the line after the coordinates, however much whitespace
+            // separates them.
+            let block = (0..3).fold(rest.trim_start(), |rest, _| {
+                rest.split_once(char::is_whitespace)
+                    .map_or("", |(_, after)| after.trim_start())
+            });
+            if block.is_empty() {
+                return Err(anyhow!("missing block"));
+            }
+            let state: BlockState = block.parse()?;
+            schematic
+                .blocks
+                .push((IntVec3::new(coords[0], coords[1], coords[2]), state));
+        }
+        _ => return Err(anyhow!("unknown keyword, {}", keyword)),
+    }
+    Ok(())
+}
+
+fn parse_ints<'a>(words: impl Iterator<Item = &'a str>, count: usize) -> Result<Vec<i32>> {
+    let ints = words
+        .map(|word| word.parse::<i32>())
+        .collect::<Result<Vec<_>, _>>()?;
+    if ints.len() != count {
+        return Err(anyhow!("expected {} integers", count));
+    }
+    Ok(ints)
+}
+
+pub fn format(schematic: &Schematic) -> String {
+    let mut out = String::new();
+    let (low, high) = schematic.bounds;
+    let _ = writeln!(
+        out,
+        "bounds {} {} {} {} {} {}",
+        low.x, low.y, low.z, high.x, high.y, high.z
+    );
+    for (pos, state) in schematic.blocks.iter() {
+        let _ = writeln!(out, "block {} {} {} {}", pos.x, pos.y, pos.z, state);
+    }
+    for line in schematic.schedule.to_string().lines() {
+        let _ = writeln!(out, "input {}", line);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{block_state::BlockState, int_vec3::IntVec3, timeline::InvalidPlacement};
+
+    use super::{format, parse};
+
+    const DESIGN: &str = "\
+bounds 0 0 0 8 8 8
+block 1 1 0 redstone_lamp[lit=false]
+block 0 0 0 lever[face=floor,facing=north,powered=false]
+block 1 0 0 iron_block
+input toggle 0 0 0 @ 0, 10
+";
+
+    #[test]
+    fn round_trips_a_design() {
+        let schematic = parse(DESIGN).unwrap();
+        assert_eq!(schematic.blocks.len(), 3);
+        assert_eq!(
+            schematic.blocks[0],
+            (
+                IntVec3::new(1, 1, 0),
+                BlockState::new("redstone_lamp", "lit=false")
+            )
+        );
+        assert_eq!(format(&schematic), DESIGN);
+        assert_eq!(parse(&format(&schematic)).unwrap(), schematic);
+    }
+
+    #[test]
+    fn places_blocks_bottom_layer_first() {
+        let timeline = parse(DESIGN).unwrap().to_timeline().unwrap();
+        assert_eq!(timeline.design().blocks().count(), 3);
+        assert!(!timeline.input_schedule().is_empty());
+    }
+
+    #[test]
+    fn reports_every_rejected_block() {
+        let schematic = parse(
+            "bounds 0 0 0 4 4 4\nblock 9 0 0 iron_block\nblock 0 2 0 redstone_torch[lit=true]",
+        )
+        .unwrap();
+        let rejected = schematic.to_timeline().unwrap_err();
+        let reasons: Vec<_> = rejected.iter().map(|block| block.reason).collect();
+        assert_eq!(
+            reasons,
+            vec![
+                InvalidPlacement::OutOfBounds,
+                InvalidPlacement::NotAFlatSurface
+            ]
+        );
+    }
+
+    #[test]
+    fn allows_extra_whitespace_between_coordinates() {
+        let schematic = parse("block  1\t0   0  iron_block\t").unwrap();
+        assert_eq!(
+            schematic.blocks,
+            vec![(IntVec3::new(1, 0, 0), BlockState::new("iron_block", ""))]
+        );
+        let err = parse("block 1 0 0 iron_block\nblock 0 0  0 ").unwrap_err();
+        assert_eq!(format!("{:#}", err), "invalid line 2: missing block");
+    }
+
+    #[test]
+    fn reports_the_line_of_an_error() {
+        let err = parse("bounds 0 0 0 4 4 4\nblock 1 x 0 iron_block").unwrap_err();
+        assert_eq!(err.to_string(), "invalid line 2");
+    }
+}
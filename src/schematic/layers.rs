@@ -0,0 +1,301 @@
+//! A plain text format that draws each layer of a design as a grid of characters, for reading in
+//! code review and for writing small designs by hand:
+//!
+//! ```text
+//! # a lever that powers a lamp through a wire
+//! bounds 0 0 0 8 8 8
+//! palette l lever[face=floor,facing=north,powered=false]
+//! palette r redstone_wire[power=0]
+//! palette L redstone_lamp[lit=false]
+//! palette s sandstone
+//! layer 0
+//! |s
+//! |lrL
+//! input toggle 0 0 1 @ 0
+//! ```
+//!
+//! `palette` lines map a character to a block, written like `BlockState` values are parsed. Each
+//! `layer` line is followed by the rows of that layer, starting with the row at the lowest z of
+//! the bounds. Each row starts with `|`, and each character after it is the block at the next x,
+//! starting at the lowest x of the bounds. `.` is an empty position. Rows can stop after their
+//! last block, and layers can stop after their last row with a block. `bounds` and `input` lines
+//! are the same as in the block list format. Blank lines, and lines that start with `#` are
+//! ignored.
+
+use std::{collections::HashMap, fmt::Write};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::{block_state::BlockState, int_vec3::IntVec3};
+
+use super::Schematic;
+
+const EMPTY: char = '.';
+const ROW_START: char = '|';
+
+/// Characters that are tried in order for blocks whose first letter is already taken.
+const FALLBACK_CHARS: &str =
+    "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789#$%&*+-/:;<=>?@^~";
+
+pub fn parse(input: &str) -> Result<Schematic> {
+    let mut schematic = Schematic::empty();
+    let mut palette: HashMap<char, BlockState> = HashMap::new();
+    // Rows are placed after every line is read since they depend on the bounds.
+    let mut rows: Vec<(usize, i32, i32, &str)> = vec![];
+    let mut layer: Option<(i32, i32)> = None;
+    for (index, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parsed = match line.strip_prefix(ROW_START) {
+            Some(row) => match &mut layer {
+                Some((y, next_row)) => {
+                    rows.push((index, *y, *next_row, row));
+                    *next_row += 1;
+                    Ok(())
+                }
+                None => Err(anyhow!("row before the first layer")),
+            },
+            None => parse_line(&mut schematic, &mut palette, &mut layer, line),
+        };
+        parsed.with_context(|| format!("invalid line {}", index + 1))?;
+    }
+    let low = schematic.bounds.0;
+    for (index, y, z, row) in rows {
+        for (x, symbol) in row.chars().enumerate() {
+            if symbol == EMPTY {
+                continue;
+            }
+            let state = palette
+                .get(&symbol)
+                .ok_or(anyhow!("{} is not in the palette", symbol))
+                .with_context(|| format!("invalid line {}", index + 1))?;
+            let pos = IntVec3::new(low.x + x as i32, y, low.z + z);
+            schematic.blocks.push((pos, state.clone()));
+        }
+    }
+    schematic
+        .blocks
+        .sort_by_key(|(pos, _)| pos.layer_order_key());
+    Ok(schematic)
+}
+
+fn parse_line(
+    schematic: &mut Schematic,
+    palette: &mut HashMap<char, BlockState>,
+    layer: &mut Option<(i32, i32)>,
+    line: &str,
+) -> Result<()> {
+    let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+    if schematic.parse_shared_line(keyword, rest)? {
+        return Ok(());
+    }
+    match keyword {
+        "palette" => {
+            let (symbol, state) = rest
+                .trim()
+                .split_once(' ')
+                .ok_or(anyhow!("expected a character and a block"))?;
+            let mut chars = symbol.chars();
+            let symbol = match (chars.next(), chars.next()) {
+                (Some(symbol), None) if symbol != EMPTY && symbol != ROW_START => symbol,
+                _ => return Err(anyhow!("{} cannot stand for a block", symbol)),
+            };
+            if palette.insert(symbol, state.parse()?).is_some() {
+                return Err(anyhow!("{} is already in the palette", symbol));
+            }
+        }
+        "layer" => *layer = Some((rest.trim().parse()?, 0)),
+        _ => return Err(anyhow!("unknown keyword, {}", keyword)),
+    }
+    Ok(())
+}
+
+/// Fails if a block is outside of the bounds, since grids can only show positions inside them.
+pub fn format(schematic: &Schematic) -> Result<String> {
+    let (low, high) = schematic.bounds;
+    if let Some((pos, state)) = schematic.blocks.iter().find(|(pos, _)| {
+        pos.x < low.x
+            || pos.y < low.y
+            || pos.z < low.z
+            || pos.x > high.x
+            || pos.y > high.y
+            || pos.z > high.z
+    }) {
+        return Err(anyhow!(
+            "{} at {} {} {} is outside of the bounds",
+            state,
+            pos.x,
+            pos.y,
+            pos.z
+        ));
+    }
+    let mut blocks: Vec<&(IntVec3, BlockState)> = schematic.blocks.iter().collect();
+    blocks.sort_by_key(|(pos, _)| (pos.y, pos.z, pos.x));
+    let palette = assign_symbols(blocks.iter().map(|(_, state)| state));
+    let symbol_for = |state: &BlockState| {
+        palette
+            .iter()
+            .find(|(_, entry)| *entry == state)
+            .map(|(symbol, _)| *symbol)
+            .unwrap()
+    };
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "bounds {} {} {} {} {} {}",
+        low.x, low.y, low.z, high.x, high.y, high.z
+    );
+    for (symbol, state) in palette.iter() {
+        let _ = writeln!(out, "palette {} {}", symbol, state);
+    }
+    let mut layer = None;
+    let mut row: Option<(i32, String)> = None;
+    for (pos, state) in blocks {
+        if layer != Some(pos.y) {
+            if let Some((_, text)) = row.take() {
+                let _ = writeln!(out, "{}{}", ROW_START, text);
+            }
+            let _ = writeln!(out, "layer {}", pos.y);
+            layer = Some(pos.y);
+        }
+        let z = pos.z - low.z;
+        // Start a new row, with an empty row for each row without blocks in between.
+        let mut text = match row.take() {
+            Some((row_z, text)) if row_z == z => text,
+            previous => {
+                let first_z = match previous {
+                    Some((row_z, text)) => {
+                        let _ = writeln!(out, "{}{}", ROW_START, text);
+                        row_z + 1
+                    }
+                    None => 0,
+                };
+                for _ in first_z..z {
+                    let _ = writeln!(out, "{}", ROW_START);
+                }
+                String::new()
+            }
+        };
+        let x = (pos.x - low.x) as usize;
+        while text.chars().count() < x {
+            text.push(EMPTY);
+        }
+        text.push(symbol_for(state));
+        row = Some((z, text));
+    }
+    if let Some((_, text)) = row {
+        let _ = writeln!(out, "{}{}", ROW_START, text);
+    }
+    for line in schematic.schedule.to_string().lines() {
+        let _ = writeln!(out, "input {}", line);
+    }
+    Ok(out)
+}
+
+/// Picks a character for each distinct block, in the order that the blocks come up. Blocks get
+/// the first letter of their type if it is free, then the same letter in upper case, so that
+/// palettes are easy to remember.
+fn assign_symbols<'a>(states: impl Iterator<Item = &'a BlockState>) -> Vec<(char, &'a BlockState)> {
+    let mut palette: Vec<(char, &BlockState)> = vec![];
+    for state in states {
+        if palette.iter().any(|(_, entry)| *entry == state) {
+            continue;
+        }
+        let initial = state.block_type.chars().next().unwrap_or(EMPTY);
+        let is_free = |symbol: &char| {
+            *symbol != EMPTY
+                && *symbol != ROW_START
+                && !palette.iter().any(|(entry, _)| entry == symbol)
+        };
+        let symbol = [initial.to_ascii_lowercase(), initial.to_ascii_uppercase()]
+            .into_iter()
+            .chain(FALLBACK_CHARS.chars())
+            // Large palettes continue with letters outside of ASCII.
+            .chain((0xc0..).filter_map(char::from_u32))
+            .find(is_free)
+            .unwrap();
+        palette.push((symbol, state));
+    }
+    palette
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{block_state::BlockState, int_vec3::IntVec3, schematic::block_list};
+
+    use super::{format, parse};
+
+    const DESIGN: &str = "\
+bounds 0 0 0 8 8 8
+palette s sandstone
+palette l lever[face=floor,facing=north,powered=false]
+palette r redstone_wire[power=0]
+palette R redstone_lamp[lit=false]
+palette i iron_block
+layer 0
+|s
+|
+|lrRi
+layer 1
+|
+|
+|...r
+input toggle 0 0 2 @ 0, 10
+";
+
+    #[test]
+    fn round_trips_a_design() {
+        let schematic = parse(DESIGN).unwrap();
+        assert_eq!(schematic.blocks.len(), 6);
+        assert_eq!(
+            schematic.blocks[1],
+            (
+                IntVec3::new(0, 0, 2),
+                BlockState::new("lever", "face=floor,facing=north,powered=false")
+            )
+        );
+        assert_eq!(format(&schematic).unwrap(), DESIGN);
+        let from_block_list = block_list::parse(&block_list::format(&schematic)).unwrap();
+        assert_eq!(format(&from_block_list).unwrap(), DESIGN);
+        assert_eq!(
+            schematic.to_timeline().unwrap().design().blocks().count(),
+            6
+        );
+    }
+
+    #[test]
+    fn reads_hand_written_grids() {
+        let schematic = parse(
+            "bounds 2 0 2 6 4 6\n\
+             layer 0\n\
+             |##.\n\
+             \n\
+             |.#\n\
+             palette # iron_block\n",
+        )
+        .unwrap();
+        let positions: Vec<IntVec3> = schematic.blocks.iter().map(|(pos, _)| *pos).collect();
+        assert_eq!(
+            positions,
+            vec![
+                IntVec3::new(2, 0, 2),
+                IntVec3::new(3, 0, 2),
+                IntVec3::new(3, 0, 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_unknown_blocks_and_blocks_outside_of_the_bounds() {
+        let err = parse("palette i iron_block\nlayer 0\n|ix").unwrap_err();
+        assert_eq!(err.to_string(), "invalid line 3");
+        assert!(parse("|i").is_err(), "row before a layer");
+
+        let mut schematic = parse(DESIGN).unwrap();
+        schematic.bounds.1 = IntVec3::new(2, 8, 8);
+        assert!(format(&schematic).is_err());
+    }
+}
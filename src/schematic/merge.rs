@@ -0,0 +1,267 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+};
+
+use crate::{block_state::BlockState, int_vec3::IntVec3};
+
+use super::Schematic;
+
+/// One of the three versions of a design in a merge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    /// The common ancestor of the other two versions
+    Base,
+    Ours,
+    Theirs,
+}
+
+/// A position where both sides changed the base in different ways. `None` means that the position
+/// is empty in that version.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conflict {
+    pub pos: IntVec3,
+    pub base: Option<BlockState>,
+    pub ours: Option<BlockState>,
+    pub theirs: Option<BlockState>,
+}
+
+impl Conflict {
+    pub fn block(&self, side: Side) -> Option<&BlockState> {
+        match side {
+            Side::Base => self.base.as_ref(),
+            Side::Ours => self.ours.as_ref(),
+            Side::Theirs => self.theirs.as_ref(),
+        }
+    }
+}
+
+/// Formats a conflict like `1 0 2: base sandstone, ours iron_block, theirs empty`.
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = |side| match self.block(side) {
+            Some(state) => state.to_string(),
+            None => "empty".to_owned(),
+        };
+        write!(
+            f,
+            "{} {} {}: base {}, ours {}, theirs {}",
+            self.pos.x,
+            self.pos.y,
+            self.pos.z,
+            name(Side::Base),
+            name(Side::Ours),
+            name(Side::Theirs)
+        )
+    }
+}
+
+/// The result of a three-way merge. Conflicting positions hold our block until they are resolved.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Merge {
+    pub schematic: Schematic,
+
+    /// Unresolved conflicts, bottom layer first
+    pub conflicts: Vec<Conflict>,
+
+    /// True if both sides changed the input schedule in different ways. The merged schematic keeps
+    /// our schedule.
+    pub schedule_conflict: bool,
+}
+
+impl Merge {
+    /// Keeps the block from one side at a conflicting position. Returns `false` if there is no
+    /// unresolved conflict at that position.
+    pub fn resolve(&mut self, pos: IntVec3, side: Side) -> bool {
+        let index = match self
+            .conflicts
+            .iter()
+            .position(|conflict| conflict.pos == pos)
+        {
+            Some(index) => index,
+            None => return false,
+        };
+        let conflict = self.conflicts.remove(index);
+        let blocks = &mut self.schematic.blocks;
+        blocks.retain(|(block_pos, _)| *block_pos != pos);
+        if let Some(state) = conflict.block(side) {
+            blocks.push((pos, state.clone()));
+            blocks.sort_by_key(|(pos, _)| pos.layer_order_key());
+        }
+        true
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty() && !self.schedule_conflict
+    }
+}
+
+/// Merges the changes that two versions made to a common base. A position that only one side
+/// changed takes that side's block, and a position that both sides changed the same way takes
+/// the shared block. Blocks are not checked for valid placement, since blocks that each side
+/// placed correctly can still end up without support after a merge. Converting the merged
+/// schematic to a `Timeline` reports those blocks.
+///
+/// Bounds that both sides changed are merged into the smallest box that contains both.
+pub fn merge(base: &Schematic, ours: &Schematic, theirs: &Schematic) -> Merge {
+    let (base_blocks, our_blocks, their_blocks) = (
+        blocks_by_pos(base),
+        blocks_by_pos(ours),
+        blocks_by_pos(theirs),
+    );
+    let positions: BTreeSet<IntVec3> = base_blocks
+        .keys()
+        .chain(our_blocks.keys())
+        .chain(their_blocks.keys())
+        .copied()
+        .collect();
+    let mut blocks = vec![];
+    let mut conflicts = vec![];
+    for pos in positions {
+        let (base, ours, theirs) = (
+            base_blocks.get(&pos).copied(),
+            our_blocks.get(&pos).copied(),
+            their_blocks.get(&pos).copied(),
+        );
+        let merged = if ours == theirs || theirs == base {
+            ours
+        } else if ours == base {
+            theirs
+        } else {
+            conflicts.push(Conflict {
+                pos,
+                base: base.cloned(),
+                ours: ours.cloned(),
+                theirs: theirs.cloned(),
+            });
+            ours
+        };
+        if let Some(state) = merged {
+            blocks.push((pos, state.clone()));
+        }
+    }
+    blocks.sort_by_key(|(pos, _)| pos.layer_order_key());
+    conflicts.sort_by_key(|conflict| conflict.pos.layer_order_key());
+
+    let bounds = if ours.bounds == theirs.bounds || theirs.bounds == base.bounds {
+        ours.bounds
+    } else if ours.bounds == base.bounds {
+        theirs.bounds
+    } else {
+        let ((our_low, our_high), (their_low, their_high)) = (ours.bounds, theirs.bounds);
+        (
+            IntVec3::new(
+                our_low.x.min(their_low.x),
+                our_low.y.min(their_low.y),
+                our_low.z.min(their_low.z),
+            ),
+            IntVec3::new(
+                our_high.x.max(their_high.x),
+                our_high.y.max(their_high.y),
+                our_high.z.max(their_high.z),
+            ),
+        )
+    };
+    let schedule_conflict = ours.schedule != theirs.schedule
+        && ours.schedule != base.schedule
+        && theirs.schedule != base.schedule;
+    let schedule = if ours.schedule == base.schedule {
+        theirs.schedule.clone()
+    } else {
+        ours.schedule.clone()
+    };
+    Merge {
+        schematic: Schematic {
+            bounds,
+            blocks,
+            schedule,
+        },
+        conflicts,
+        schedule_conflict,
+    }
+}
+
+fn blocks_by_pos(schematic: &Schematic) -> HashMap<IntVec3, &BlockState> {
+    schematic
+        .blocks
+        .iter()
+        .map(|(pos, state)| (*pos, state))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{block_state::BlockState, int_vec3::IntVec3, schematic::Format};
+
+    use super::{merge, Schematic, Side};
+
+    fn schematic(text: &str) -> Schematic {
+        Format::BlockList.read(text.as_bytes()).unwrap()
+    }
+
+    const BASE: &str = "\
+bounds 0 0 0 8 8 8
+block 0 0 0 sandstone
+block 1 0 0 sandstone
+block 2 0 0 redstone_lamp[lit=false]
+input toggle 0 0 0 @ 1
+";
+
+    #[test]
+    fn merges_edits_to_different_positions() {
+        let ours = schematic(
+            "bounds 0 0 0 8 8 8\nblock 0 0 0 iron_block\nblock 1 0 0 sandstone\n\
+             block 2 0 0 redstone_lamp[lit=false]\ninput toggle 0 0 0 @ 1",
+        );
+        let theirs = schematic(
+            "bounds 0 0 0 8 16 8\nblock 0 0 0 sandstone\nblock 2 0 0 redstone_lamp[lit=false]\n\
+             block 2 1 0 redstone_wire[power=0]\ninput toggle 0 0 0 @ 1, 5",
+        );
+        let merged = merge(&schematic(BASE), &ours, &theirs);
+        assert!(merged.is_clean());
+        assert_eq!(
+            merged.schematic,
+            schematic(
+                "bounds 0 0 0 8 16 8\nblock 0 0 0 iron_block\n\
+                 block 2 0 0 redstone_lamp[lit=false]\nblock 2 1 0 redstone_wire[power=0]\n\
+                 input toggle 0 0 0 @ 1, 5"
+            )
+        );
+    }
+
+    #[test]
+    fn lists_conflicts_and_resolves_them() {
+        let ours = schematic(
+            "bounds 0 0 0 8 8 8\nblock 0 0 0 sandstone\nblock 1 0 0 iron_block\n\
+             block 2 0 0 redstone_lamp[lit=true]\ninput toggle 0 0 0 @ 2",
+        );
+        let theirs = schematic(
+            "bounds 0 0 0 8 8 8\nblock 0 0 0 sandstone\nblock 2 0 0 redstone_lamp[lit=false]\n\
+             input toggle 0 0 0 @ 3",
+        );
+        let mut merged = merge(&schematic(BASE), &ours, &theirs);
+        assert!(merged.schedule_conflict);
+        assert_eq!(merged.conflicts.len(), 1, "lamp was only changed by us");
+        let conflict = &merged.conflicts[0];
+        assert_eq!(
+            conflict.to_string(),
+            "1 0 0: base sandstone, ours iron_block, theirs empty"
+        );
+        assert_eq!(
+            merged.schematic.blocks[1],
+            (IntVec3::new(1, 0, 0), BlockState::new("iron_block", ""))
+        );
+
+        assert!(merged.resolve(IntVec3::new(1, 0, 0), Side::Theirs));
+        assert!(!merged.resolve(IntVec3::new(1, 0, 0), Side::Ours));
+        assert!(merged.conflicts.is_empty());
+        assert_eq!(merged.schematic.blocks.len(), 2);
+        assert_eq!(
+            merged.schematic.blocks[1],
+            (
+                IntVec3::new(2, 0, 0),
+                BlockState::new("redstone_lamp", "lit=true")
+            )
+        );
+    }
+}
@@ -0,0 +1,151 @@
+mod block_list;
+mod layers;
+mod merge;
+mod share;
+
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+    block_state::BlockState,
+    constants::WORLD_SIZE,
+    int_vec3::IntVec3,
+    timeline::{InputSchedule, InvalidPlacement, Timeline, WorldState},
+};
+
+pub use self::merge::{merge, Conflict, Merge, Side};
+
+/// A design as it is stored in a file: the blocks at the start of the timeline, and the input
+/// schedule. Blocks are not checked for valid placement until the schematic is converted to a
+/// `Timeline`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Schematic {
+    pub bounds: (IntVec3, IntVec3),
+    pub blocks: Vec<(IntVec3, BlockState)>,
+    pub schedule: InputSchedule,
+}
+
+/// A block from a schematic that could not be placed in the world.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RejectedBlock {
+    pub pos: IntVec3,
+    pub state: BlockState,
+    pub reason: InvalidPlacement,
+}
+
+impl Schematic {
+    /// Captures the design of a timeline. Blocks are listed bottom layer first.
+    pub fn from_timeline(timeline: &Timeline) -> Self {
+        let design = timeline.design();
+        let mut blocks: Vec<_> = design
+            .blocks()
+            .map(|(pos, state)| (pos, state.clone()))
+            .collect();
+        blocks.sort_by_key(|(pos, _)| pos.layer_order_key());
+        Schematic {
+            bounds: design.bounds(),
+            blocks,
+            schedule: timeline.input_schedule().clone(),
+        }
+    }
+
+    /// Places every block in a new timeline. Blocks are placed bottom layer first so that a block
+    /// that rests on another can be listed before the block that supports it. Returns every block
+    /// that could not be placed if there are any.
+    pub fn to_timeline(&self) -> Result<Timeline, Vec<RejectedBlock>> {
+        let world = WorldState::new(self.bounds)
+            .place_blocks(self.blocks.iter().map(|(pos, state)| (*pos, state)))?;
+        let mut timeline = Timeline::from_world(world);
+        timeline.set_input_schedule(self.schedule.clone());
+        Ok(timeline)
+    }
+
+    /// An empty design with the bounds of a new timeline, which the text formats start from.
+    fn empty() -> Self {
+        Schematic {
+            bounds: (IntVec3::ZERO, IntVec3::ONE * WORLD_SIZE),
+            blocks: vec![],
+            schedule: InputSchedule::default(),
+        }
+    }
+
+    /// Reads a line that every text format has, given the keyword that starts the line and the
+    /// rest of the line. `bounds` gives the lowest and highest corners of the world, and `input`
+    /// adds entries in the format of `InputSchedule`. Returns false for other keywords, which are
+    /// left to each format.
+    fn parse_shared_line(&mut self, keyword: &str, rest: &str) -> Result<bool> {
+        match keyword {
+            "bounds" => {
+                let coords: Vec<i32> = rest
+                    .split_whitespace()
+                    .map(|word| word.parse())
+                    .collect::<Result<_, _>>()?;
+                if coords.len() != 6 {
+                    return Err(anyhow!("expected 6 integers"));
+                }
+                self.bounds = (
+                    IntVec3::new(coords[0], coords[1], coords[2]),
+                    IntVec3::new(coords[3], coords[4], coords[5]),
+                );
+            }
+            "input" => {
+                for entry in InputSchedule::parse(rest)?.entries() {
+                    self.schedule.push(entry.event, entry.ticks.clone());
+                }
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+/// File formats that designs can be read from and written to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Plain text with one line per block. See `block_list.rs`.
+    BlockList,
+
+    /// Plain text with a grid of characters for each layer. See `layers.rs`.
+    Layers,
+}
+
+impl Format {
+    pub const ALL: [Format; 2] = [Format::BlockList, Format::Layers];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::BlockList => "blocks",
+            Format::Layers => "layers",
+        }
+    }
+
+    /// Picks a format based on a file extension.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+        Self::ALL
+            .into_iter()
+            .find(|format| format.extension() == extension)
+            .ok_or(anyhow!(
+                "unrecognized file extension for {}; supported extensions are {}",
+                path.display(),
+                Self::ALL
+                    .map(|format| format!(".{}", format.extension()))
+                    .join(", ")
+            ))
+    }
+
+    pub fn read(self, data: &[u8]) -> Result<Schematic> {
+        match self {
+            Format::BlockList => block_list::parse(std::str::from_utf8(data)?),
+            Format::Layers => layers::parse(std::str::from_utf8(data)?),
+        }
+    }
+
+    pub fn write(self, schematic: &Schematic) -> Result<Vec<u8>> {
+        match self {
+            Format::BlockList => Ok(block_list::format(schematic).into_bytes()),
+            Format::Layers => Ok(layers::format(schematic)?.into_bytes()),
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! Short strings that hold a whole design, for pasting small circuits into chat. A share string is
+//! a version prefix followed by URL safe base64 without padding:
+//!
+//! ```text
+//! redstone1:<base64>
+//! ```
+//!
+//! The encoded bytes are a CRC-32 checksum of the design in the block list format, stored big
+//! endian, followed by that text compressed with raw deflate.
+
+use std::io::{Read, Write};
+
+use anyhow::{anyhow, Context, Result};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression, Crc};
+
+use super::{block_list, Schematic};
+
+const PREFIX: &str = "redstone";
+const VERSION: u32 = 1;
+const CHECKSUM_LEN: usize = 4;
+
+/// Longest design text that a share string may unpack to. A few bytes of deflate data can unpack
+/// to gigabytes, so strings from other people are not trusted to stay small.
+const MAX_TEXT_LEN: u64 = 1 << 20;
+
+impl Schematic {
+    pub fn to_share_string(&self) -> String {
+        let text = block_list::format(self);
+        let mut crc = Crc::new();
+        crc.update(text.as_bytes());
+        let mut encoder =
+            DeflateEncoder::new(crc.sum().to_be_bytes().to_vec(), Compression::best());
+        // Writing to a vector cannot fail.
+        encoder.write_all(text.as_bytes()).unwrap();
+        let bytes = encoder.finish().unwrap();
+        format!(
+            "{}{}:{}",
+            PREFIX,
+            VERSION,
+            base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+        )
+    }
+
+    /// Reads a share string. Whitespace anywhere in the string is ignored, since chat clients
+    /// tend to wrap long strings.
+    pub fn from_share_string(input: &str) -> Result<Self> {
+        let input: String = input.split_whitespace().collect();
+        let (version, data) = input
+            .strip_prefix(PREFIX)
+            .and_then(|rest| rest.split_once(':'))
+            .ok_or(anyhow!("not a design string"))?;
+        let version: u32 = version
+            .parse()
+            .map_err(|_| anyhow!("not a design string"))?;
+        if version != VERSION {
+            return Err(anyhow!(
+                "design strings of version {} are not supported, expected version {}",
+                version,
+                VERSION
+            ));
+        }
+        let bytes = base64::decode_config(data, base64::URL_SAFE_NO_PAD)
+            .context("design string is not valid base64")?;
+        if bytes.len() < CHECKSUM_LEN {
+            return Err(anyhow!("design string is too short"));
+        }
+        let (checksum, compressed) = bytes.split_at(CHECKSUM_LEN);
+        let mut text = String::new();
+        DeflateDecoder::new(compressed)
+            .take(MAX_TEXT_LEN + 1)
+            .read_to_string(&mut text)
+            .context("design string is damaged")?;
+        if text.len() as u64 > MAX_TEXT_LEN {
+            return Err(anyhow!(
+                "design string unpacks to more than {} bytes",
+                MAX_TEXT_LEN
+            ));
+        }
+        let mut crc = Crc::new();
+        crc.update(text.as_bytes());
+        if crc.sum().to_be_bytes() != checksum {
+            return Err(anyhow!("design string is damaged, checksum does not match"));
+        }
+        block_list::parse(&text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::DeflateEncoder, Compression};
+
+    use crate::schematic::{block_list, Schematic};
+
+    use super::MAX_TEXT_LEN;
+
+    const DESIGN: &str = "\
+bounds 0 0 0 16 8 8
+block 0 0 0 lever[face=floor,facing=north,powered=false]
+block 15 0 0 redstone_lamp[lit=false]
+input toggle 0 0 0 @ 0, 10
+";
+
+    /// A lever that powers a lamp through a line of wire.
+    fn design() -> String {
+        let wires: String = (1..15)
+            .map(|x| format!("block {} 0 0 redstone_wire[power=0]\n", x))
+            .collect();
+        format!("{}{}", DESIGN, wires)
+    }
+
+    #[test]
+    fn round_trips_a_design() {
+        let text = design();
+        let schematic = block_list::parse(&text).unwrap();
+        let shared = schematic.to_share_string();
+        assert!(shared.starts_with("redstone1:"));
+        assert!(shared.len() < text.len() / 2, "{} is not compact", shared);
+        let (prefix, data) = shared.split_at(40);
+        let wrapped = format!("  {}\n{} ", prefix, data);
+        assert_eq!(Schematic::from_share_string(&wrapped).unwrap(), schematic);
+    }
+
+    #[test]
+    fn rejects_damaged_strings_and_other_versions() {
+        let shared = block_list::parse(&design()).unwrap().to_share_string();
+        let data = &shared["redstone1:".len()..];
+        let err = |input: &str| Schematic::from_share_string(input).unwrap_err().to_string();
+
+        let mut bytes = base64::decode_config(data, base64::URL_SAFE_NO_PAD).unwrap();
+        bytes[0] ^= 1;
+        let damaged = base64::encode_config(bytes, base64::URL_SAFE_NO_PAD);
+        assert_eq!(
+            err(&format!("redstone1:{}", damaged)),
+            "design string is damaged, checksum does not match"
+        );
+        assert_eq!(
+            err(&format!("redstone2:{}", data)),
+            "design strings of version 2 are not supported, expected version 1"
+        );
+        assert_eq!(err("hello"), "not a design string");
+    }
+
+    #[test]
+    fn stops_unpacking_large_designs() {
+        let mut encoder = DeflateEncoder::new(vec![0; 4], Compression::best());
+        encoder
+            .write_all(&vec![b' '; MAX_TEXT_LEN as usize * 4])
+            .unwrap();
+        let bytes = encoder.finish().unwrap();
+        assert!(bytes.len() < 10_000);
+        let shared = format!(
+            "redstone1:{}",
+            base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+        );
+        assert_eq!(
+            Schematic::from_share_string(&shared)
+                .unwrap_err()
+                .to_string(),
+            format!("design string unpacks to more than {} bytes", MAX_TEXT_LEN)
+        );
+    }
+}
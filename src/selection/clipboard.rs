@@ -0,0 +1,267 @@
+use crate::{
+    block_state::{BlockState, BlockTransform},
+    int_vec3::IntVec3,
+    schematic::RejectedBlock,
+    timeline::WorldState,
+};
+
+/// A box of positions between two corners, inclusive of both. The corners can be any two opposite
+/// corners of the box.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Selection {
+    pub start: IntVec3,
+    pub end: IntVec3,
+}
+
+impl Selection {
+    pub fn new(start: IntVec3, end: IntVec3) -> Self {
+        Selection { start, end }
+    }
+
+    /// The corner with the lowest coordinates
+    pub fn min(&self) -> IntVec3 {
+        IntVec3::new(
+            self.start.x.min(self.end.x),
+            self.start.y.min(self.end.y),
+            self.start.z.min(self.end.z),
+        )
+    }
+
+    /// The corner with the highest coordinates
+    pub fn max(&self) -> IntVec3 {
+        IntVec3::new(
+            self.start.x.max(self.end.x),
+            self.start.y.max(self.end.y),
+            self.start.z.max(self.end.z),
+        )
+    }
+
+    /// The number of positions that the selection spans along each axis.
+    pub fn size(&self) -> IntVec3 {
+        self.max() - self.min() + IntVec3::ONE
+    }
+
+    pub fn contains(&self, pos: IntVec3) -> bool {
+        let (min, max) = (self.min(), self.max());
+        (min.x..=max.x).contains(&pos.x)
+            && (min.y..=max.y).contains(&pos.y)
+            && (min.z..=max.z).contains(&pos.z)
+    }
+
+    /// The smallest selection that covers both selections.
+    pub fn union(&self, other: &Selection) -> Self {
+        let (a, b) = (self.min(), other.min());
+        let min = IntVec3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z));
+        let (a, b) = (self.max(), other.max());
+        let max = IntVec3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z));
+        Selection::new(min, max)
+    }
+
+    /// Every position in the selection, bottom layer first.
+    pub fn positions(&self) -> impl Iterator<Item = IntVec3> {
+        let (min, max) = (self.min(), self.max());
+        (min.y..=max.y).flat_map(move |y| {
+            (min.x..=max.x).flat_map(move |x| (min.z..=max.z).map(move |z| IntVec3::new(x, y, z)))
+        })
+    }
+}
+
+/// Blocks copied from a selection. Positions are relative to the lowest corner of the selection.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Clipboard {
+    /// Blocks ordered bottom layer first so that they can be placed in order.
+    pub blocks: Vec<(IntVec3, BlockState)>,
+
+    /// The size of the selection that the blocks were copied from
+    pub size: IntVec3,
+}
+
+impl Clipboard {
+    pub fn copy(world: &WorldState, selection: &Selection) -> Self {
+        let blocks = selection
+            .positions()
+            .filter_map(|pos| Some((pos - selection.min(), world.block_at(pos)?.clone())))
+            .collect();
+        Clipboard {
+            blocks,
+            size: selection.size(),
+        }
+    }
+
+    /// Turns or mirrors the copied blocks within the box that they were copied from. The box keeps
+    /// its lowest corner, and directional properties like `facing` are transformed with the blocks.
+    pub fn transformed(&self, transform: BlockTransform) -> Self {
+        let corners = Selection::new(
+            transform.pos(IntVec3::ZERO),
+            transform.pos(self.size - IntVec3::ONE),
+        );
+        let mut blocks: Vec<_> = self
+            .blocks
+            .iter()
+            .map(|(pos, state)| (transform.pos(*pos) - corners.min(), transform.state(state)))
+            .collect();
+        blocks.sort_by_key(|(pos, _)| pos.layer_order_key());
+        Clipboard {
+            blocks,
+            size: corners.size(),
+        }
+    }
+
+    /// Places the copied blocks with the lowest corner of the copied selection at `origin`.
+    /// Nothing is placed unless every block fits. Returns the placed blocks bottom layer first.
+    pub fn paste(
+        &self,
+        world: &mut WorldState,
+        origin: IntVec3,
+    ) -> Result<Vec<(IntVec3, BlockState)>, RejectedBlock> {
+        let mut pasted = world.clone();
+        let blocks: Vec<_> = self
+            .blocks
+            .iter()
+            .map(|(pos, state)| (origin + *pos, state.clone()))
+            .collect();
+        for (pos, state) in blocks.iter() {
+            pasted
+                .insert_block(*pos, state.clone())
+                .map_err(|reason| RejectedBlock {
+                    pos: *pos,
+                    state: state.clone(),
+                    reason,
+                })?;
+        }
+        *world = pasted;
+        Ok(blocks)
+    }
+}
+
+/// Turns or mirrors the blocks in a selection in place. Returns the selection that holds the
+/// transformed blocks, which has the same lowest corner. The world is unchanged if any transformed
+/// block does not fit.
+pub fn transform_selection(
+    world: &mut WorldState,
+    selection: &Selection,
+    transform: BlockTransform,
+) -> Result<Selection, RejectedBlock> {
+    let clipboard = Clipboard::copy(world, selection).transformed(transform);
+    let mut transformed = world.clone();
+    for pos in selection.positions() {
+        transformed.remove_block(pos);
+    }
+    clipboard.paste(&mut transformed, selection.min())?;
+    *world = transformed;
+    Ok(Selection::new(
+        selection.min(),
+        selection.min() + clipboard.size - IntVec3::ONE,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        block_state::BlockState,
+        int_vec3::IntVec3,
+        timeline::{InvalidPlacement, WorldState},
+    };
+
+    use super::{transform_selection, BlockTransform, Clipboard, Selection};
+
+    fn world_with_torch() -> WorldState {
+        let mut world = WorldState::new((IntVec3::ZERO, IntVec3::ONE * 15));
+        for (pos, block_type, state) in [
+            (IntVec3::new(2, 0, 2), "iron_block", ""),
+            (IntVec3::new(2, 1, 2), "redstone_torch", "lit=true"),
+            (IntVec3::new(5, 0, 5), "iron_block", ""),
+        ] {
+            world
+                .insert_block(pos, BlockState::new(block_type, state))
+                .unwrap();
+        }
+        world
+    }
+
+    #[test]
+    fn selects_a_box_from_any_two_corners() {
+        let selection = Selection::new(IntVec3::new(3, 0, 1), IntVec3::new(1, 2, 1));
+        assert_eq!(selection.min(), IntVec3::new(1, 0, 1));
+        assert_eq!(selection.size(), IntVec3::new(3, 3, 1));
+        assert_eq!(selection.positions().count(), 9);
+        assert_eq!(selection.positions().next(), Some(IntVec3::new(1, 0, 1)));
+        assert!(selection.contains(IntVec3::new(2, 1, 1)));
+        assert!(!selection.contains(IntVec3::new(2, 1, 2)));
+    }
+
+    #[test]
+    fn copies_and_pastes_a_selection() {
+        let mut world = world_with_torch();
+        let selection = Selection::new(IntVec3::new(1, 0, 1), IntVec3::new(3, 1, 3));
+        let clipboard = Clipboard::copy(&world, &selection);
+        assert_eq!(clipboard.blocks.len(), 2);
+        assert_eq!(clipboard.blocks[0].0, IntVec3::new(1, 0, 1));
+
+        let pasted = clipboard.paste(&mut world, IntVec3::new(8, 0, 8)).unwrap();
+        assert_eq!(pasted[1].0, IntVec3::new(9, 1, 9));
+        assert_eq!(
+            world.block_at(IntVec3::new(9, 1, 9)),
+            world.block_at(IntVec3::new(2, 1, 2))
+        );
+    }
+
+    #[test]
+    fn pastes_nothing_when_a_block_does_not_fit() {
+        let mut world = world_with_torch();
+        let selection = Selection::new(IntVec3::new(1, 0, 1), IntVec3::new(3, 1, 3));
+        let clipboard = Clipboard::copy(&world, &selection);
+        let before = world.clone();
+        let rejected = clipboard
+            .paste(&mut world, IntVec3::new(4, 0, 4))
+            .unwrap_err();
+        assert_eq!(rejected.pos, IntVec3::new(5, 0, 5));
+        assert_eq!(rejected.reason, InvalidPlacement::PositionOccupied);
+        assert_eq!(world, before);
+    }
+
+    #[test]
+    fn rotates_a_selection_in_place() {
+        let mut world = WorldState::new((IntVec3::ZERO, IntVec3::ONE * 15));
+        for (pos, state) in [
+            (IntVec3::new(1, 0, 1), "repeater[delay=2,facing=west]"),
+            (
+                IntVec3::new(2, 0, 1),
+                "redstone_wire[east=none,north=none,power=0,south=none,west=side]",
+            ),
+        ] {
+            world.insert_block(pos, state.parse().unwrap()).unwrap();
+        }
+        let selection = Selection::new(IntVec3::new(1, 0, 1), IntVec3::new(2, 0, 1));
+        let rotated =
+            transform_selection(&mut world, &selection, BlockTransform::Rotate(1)).unwrap();
+        assert_eq!(
+            rotated,
+            Selection::new(IntVec3::new(1, 0, 1), IntVec3::new(1, 0, 2))
+        );
+        assert_eq!(world.blocks().count(), 2);
+        assert_eq!(
+            world.block_at(IntVec3::new(1, 0, 1)),
+            Some(&"repeater[delay=2,facing=north]".parse().unwrap())
+        );
+        assert_eq!(
+            world.block_at(IntVec3::new(1, 0, 2)),
+            Some(
+                &"redstone_wire[east=none,north=side,power=0,south=none,west=none]"
+                    .parse()
+                    .unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn mirrors_a_clipboard_within_its_box() {
+        let world = world_with_torch();
+        let selection = Selection::new(IntVec3::new(2, 0, 2), IntVec3::new(5, 1, 5));
+        let mirrored = Clipboard::copy(&world, &selection).transformed(BlockTransform::MirrorX);
+        assert_eq!(mirrored.size, selection.size());
+        assert_eq!(mirrored.blocks[0].0, IntVec3::new(0, 0, 3));
+        assert_eq!(mirrored.blocks[1].0, IntVec3::new(3, 0, 0));
+        assert_eq!(mirrored.blocks[2].0, IntVec3::new(3, 1, 0));
+    }
+}
@@ -0,0 +1,413 @@
+mod clipboard;
+mod region;
+mod stack;
+
+use bevy::prelude::*;
+
+use crate::{
+    block::{bounding_box_to_line_list, respawn_blocks},
+    block_picker::SelectedBlockType,
+    block_state::BlockState,
+    constants::PIXELS,
+    cursor::Cursor,
+    int_vec3::IntVec3,
+    lines::LineMaterial,
+    timeline::{Timeline, WorldState},
+    user_input::{sent_command, UiCommand},
+};
+
+pub use self::{
+    clipboard::{transform_selection, Clipboard, Selection},
+    region::{fill, hollow, replace, RegionOperation, RegionReport},
+    stack::{stack_selection, Stack},
+};
+
+const SELECTION_COLOR: Color = Color::rgb(1.0, 0.85, 0.1);
+
+pub struct SelectionPlugin;
+
+impl Plugin for SelectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ActiveSelection::default())
+            .insert_resource(ClipboardContents::default())
+            .add_system(update_selection)
+            .add_system(draw_selection)
+            .add_system(copy_selection)
+            .add_system(cut_selection)
+            .add_system(paste_clipboard)
+            .add_system(transform_active_selection)
+            .add_system(edit_selection)
+            .add_system(stack_active_selection);
+    }
+}
+
+/// The box selected with the select tool, if any.
+#[derive(Debug, Default, Resource)]
+pub struct ActiveSelection {
+    pub selection: Option<Selection>,
+
+    /// True while the select tool is held down. The far corner of the selection follows the cursor
+    /// until it is released.
+    dragging: bool,
+}
+
+#[derive(Debug, Default, Resource)]
+struct ClipboardContents(Option<Clipboard>);
+
+#[derive(Component)]
+struct SelectionOutline;
+
+/// Starts a selection at the block under the cursor, and drags its far corner along with the
+/// cursor. Starting a selection away from any block clears the selection.
+fn update_selection(
+    mut user_input: EventReader<UiCommand>,
+    cursor: Res<Cursor>,
+    mut active: ResMut<ActiveSelection>,
+    query_blocks: Query<&Transform, With<BlockState>>,
+) {
+    let pos = cursor
+        .current_block
+        .and_then(|block| query_blocks.get(block).ok())
+        .map(|transform| IntVec3::from_translation(transform.translation));
+    for command in user_input.iter() {
+        match command {
+            UiCommand::StartSelection => {
+                active.selection = pos.map(|pos| Selection::new(pos, pos));
+                active.dragging = pos.is_some();
+            }
+            UiCommand::FinishSelection => active.dragging = false,
+            _ => {}
+        }
+    }
+    if !active.dragging {
+        return;
+    }
+    if let (Some(selection), Some(pos)) = (active.selection, pos) {
+        if selection.end != pos {
+            active.selection = Some(Selection::new(selection.start, pos));
+        }
+    }
+}
+
+fn draw_selection(
+    mut commands: Commands,
+    active: Res<ActiveSelection>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut line_materials: ResMut<Assets<LineMaterial>>,
+    query_outlines: Query<Entity, With<SelectionOutline>>,
+) {
+    if !active.is_changed() {
+        return;
+    }
+    for outline in query_outlines.iter() {
+        commands.entity(outline).despawn();
+    }
+    let selection = match active.selection {
+        Some(selection) => selection,
+        None => return,
+    };
+    // Block translations are at block centers.
+    let half_block = Vec3::ONE * (8.0 * PIXELS);
+    let bounding_box = (
+        selection.min().to_translation() - half_block,
+        selection.max().to_translation() + half_block,
+    );
+    commands
+        .spawn(MaterialMeshBundle {
+            mesh: meshes.add(Mesh::from(bounding_box_to_line_list(bounding_box))),
+            material: line_materials.add(LineMaterial::new(SELECTION_COLOR)),
+            ..default()
+        })
+        .insert(SelectionOutline);
+}
+
+fn copy_selection(
+    user_input: EventReader<UiCommand>,
+    active: Res<ActiveSelection>,
+    timeline: Res<Timeline>,
+    mut clipboard: ResMut<ClipboardContents>,
+) {
+    if !sent_command(user_input, UiCommand::CopySelection) {
+        return;
+    }
+    if let Some(selection) = active.selection {
+        let copied = Clipboard::copy(timeline.design(), &selection);
+        info!("copied {} blocks", copied.blocks.len());
+        clipboard.0 = Some(copied);
+    }
+}
+
+fn cut_selection(
+    user_input: EventReader<UiCommand>,
+    active: Res<ActiveSelection>,
+    mut timeline: ResMut<Timeline>,
+    mut clipboard: ResMut<ClipboardContents>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut line_materials: ResMut<Assets<LineMaterial>>,
+    query_blocks: Query<(Entity, &Transform), With<BlockState>>,
+) {
+    if !sent_command(user_input, UiCommand::CutSelection) {
+        return;
+    }
+    let selection = match active.selection {
+        Some(selection) => selection,
+        None => return,
+    };
+    let cut = Clipboard::copy(timeline.design(), &selection);
+    let mut world = timeline.design().clone();
+    for pos in selection.positions() {
+        world.remove_block(pos);
+    }
+    if apply_edit(
+        "cut selection",
+        &world,
+        &mut timeline,
+        &mut commands,
+        &asset_server,
+        &mut meshes,
+        &mut materials,
+        &mut line_materials,
+        &query_blocks,
+    ) {
+        info!("cut {} blocks", cut.blocks.len());
+        clipboard.0 = Some(cut);
+    }
+}
+
+/// Pastes the clipboard with the lowest corner of the copied selection at the cursor. Nothing is
+/// pasted if any block does not fit.
+fn paste_clipboard(
+    user_input: EventReader<UiCommand>,
+    cursor: Res<Cursor>,
+    clipboard: Res<ClipboardContents>,
+    mut timeline: ResMut<Timeline>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut line_materials: ResMut<Assets<LineMaterial>>,
+    query_blocks: Query<(Entity, &Transform), With<BlockState>>,
+) {
+    if !sent_command(user_input, UiCommand::PasteClipboard) {
+        return;
+    }
+    let (clipboard, transform) = match (&clipboard.0, cursor.place_block_transform) {
+        (Some(clipboard), Some(transform)) => (clipboard, transform),
+        _ => return,
+    };
+    let origin = IntVec3::from_translation(transform.translation);
+    let mut world = timeline.design().clone();
+    if let Err(rejected) = clipboard.paste(&mut world, origin) {
+        info!(
+            "cannot paste at {:?}: {} at {:?}: {:?}",
+            origin, rejected.state, rejected.pos, rejected.reason
+        );
+        return;
+    }
+    apply_edit(
+        "paste",
+        &world,
+        &mut timeline,
+        &mut commands,
+        &asset_server,
+        &mut meshes,
+        &mut materials,
+        &mut line_materials,
+        &query_blocks,
+    );
+}
+
+/// Turns or mirrors the selected blocks in place, and moves the selection to cover them.
+fn transform_active_selection(
+    mut user_input: EventReader<UiCommand>,
+    mut active: ResMut<ActiveSelection>,
+    mut timeline: ResMut<Timeline>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut line_materials: ResMut<Assets<LineMaterial>>,
+    query_blocks: Query<(Entity, &Transform), With<BlockState>>,
+) {
+    let transform = user_input.iter().find_map(|command| match command {
+        UiCommand::TransformSelection(transform) => Some(*transform),
+        _ => None,
+    });
+    let (transform, selection) = match (transform, active.selection) {
+        (Some(transform), Some(selection)) => (transform, selection),
+        _ => return,
+    };
+    let mut world = timeline.design().clone();
+    let transformed = match transform_selection(&mut world, &selection, transform) {
+        Ok(transformed) => transformed,
+        Err(rejected) => {
+            info!(
+                "cannot transform selection: {} at {:?}: {:?}",
+                rejected.state, rejected.pos, rejected.reason
+            );
+            return;
+        }
+    };
+    if apply_edit(
+        "transform selection",
+        &world,
+        &mut timeline,
+        &mut commands,
+        &asset_server,
+        &mut meshes,
+        &mut materials,
+        &mut line_materials,
+        &query_blocks,
+    ) {
+        active.selection = Some(transformed);
+    }
+}
+
+/// Fills, replaces or hollows out the selection with the block selected in the block picker.
+/// Replacing swaps out every block with the type of the block under the cursor. Positions where a
+/// block cannot be placed are left as they were, and the report says why.
+fn edit_selection(
+    mut user_input: EventReader<UiCommand>,
+    active: Res<ActiveSelection>,
+    selected: Res<SelectedBlockType>,
+    cursor: Res<Cursor>,
+    mut timeline: ResMut<Timeline>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut line_materials: ResMut<Assets<LineMaterial>>,
+    query_blocks: Query<(Entity, &Transform), With<BlockState>>,
+    query_states: Query<&BlockState>,
+) {
+    let operation = user_input.iter().find_map(|command| match command {
+        UiCommand::EditSelection(operation) => Some(*operation),
+        _ => None,
+    });
+    let (operation, selection) = match (operation, active.selection) {
+        (Some(operation), Some(selection)) => (operation, selection),
+        _ => return,
+    };
+    let state = &selected.block.1;
+    let mut world = timeline.design().clone();
+    let report = match operation {
+        RegionOperation::Fill => fill(&mut world, &selection, state),
+        RegionOperation::Replace => {
+            let from = match cursor
+                .current_block
+                .and_then(|block| query_states.get(block).ok())
+            {
+                Some(from) => BlockState::new(&from.block_type, ""),
+                None => {
+                    info!("point at a block to pick the block type to replace");
+                    return;
+                }
+            };
+            replace(&mut world, &selection, &from, state)
+        }
+        RegionOperation::Hollow => hollow(&mut world, &selection, state),
+    };
+    info!("{:?} selection: {}", operation, report);
+    apply_edit(
+        "edit selection",
+        &world,
+        &mut timeline,
+        &mut commands,
+        &asset_server,
+        &mut meshes,
+        &mut materials,
+        &mut line_materials,
+        &query_blocks,
+    );
+}
+
+/// Repeats the selection toward the cursor until a copy reaches it, and grows the selection to
+/// cover every copy. Nothing is placed if any copy does not fit.
+fn stack_active_selection(
+    user_input: EventReader<UiCommand>,
+    cursor: Res<Cursor>,
+    mut active: ResMut<ActiveSelection>,
+    mut timeline: ResMut<Timeline>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut line_materials: ResMut<Assets<LineMaterial>>,
+    query_blocks: Query<(Entity, &Transform), With<BlockState>>,
+) {
+    if !sent_command(user_input, UiCommand::StackSelection) {
+        return;
+    }
+    let (selection, transform) = match (active.selection, cursor.place_block_transform) {
+        (Some(selection), Some(transform)) => (selection, transform),
+        _ => return,
+    };
+    let stack = match Stack::toward(&selection, IntVec3::from_translation(transform.translation)) {
+        Some(stack) => stack,
+        None => return,
+    };
+    let mut world = timeline.design().clone();
+    let stacked = match stack_selection(&mut world, &selection, &stack) {
+        Ok(stacked) => stacked,
+        Err(rejected) => {
+            info!(
+                "cannot stack {} copies: {} at {:?}: {:?}",
+                stack.count, rejected.state, rejected.pos, rejected.reason
+            );
+            return;
+        }
+    };
+    if apply_edit(
+        "stack selection",
+        &world,
+        &mut timeline,
+        &mut commands,
+        &asset_server,
+        &mut meshes,
+        &mut materials,
+        &mut line_materials,
+        &query_blocks,
+    ) {
+        active.selection = Some(stacked);
+    }
+}
+
+/// Makes an edited copy of the design the new design in one step, and despawns and spawns again
+/// the blocks that changed. Returns false if the timeline rejects the edit, which only happens if
+/// the edit placed blocks in a different order than the timeline does.
+fn apply_edit(
+    action: &str,
+    world: &WorldState,
+    timeline: &mut Timeline,
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    line_materials: &mut ResMut<Assets<LineMaterial>>,
+    query_blocks: &Query<(Entity, &Transform), With<BlockState>>,
+) -> bool {
+    let changed = match timeline.set_design(world) {
+        Ok(changed) => changed,
+        Err(rejected) => {
+            info!(
+                "cannot {}: {} at {:?}: {:?}",
+                action, rejected[0].state, rejected[0].pos, rejected[0].reason
+            );
+            return false;
+        }
+    };
+    respawn_blocks(
+        commands,
+        asset_server,
+        meshes,
+        materials,
+        line_materials,
+        timeline.design(),
+        &changed,
+        query_blocks,
+    );
+    true
+}
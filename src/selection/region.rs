@@ -0,0 +1,267 @@
+use std::fmt;
+
+use crate::{
+    block_state::BlockState,
+    int_vec3::IntVec3,
+    schematic::RejectedBlock,
+    timeline::{InvalidPlacement, WorldState},
+};
+
+use super::Selection;
+
+/// An edit that applies to every position in a selection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RegionOperation {
+    /// Sets every position to the same block
+    Fill,
+
+    /// Swaps every block of one type for another block
+    Replace,
+
+    /// Fills the outer faces of the selection and clears everything inside
+    Hollow,
+}
+
+/// What a region operation changed. Each placement goes through `WorldState::insert_block`, and
+/// positions where the world rejected the new block keep the block that was there before.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RegionReport {
+    pub placed: usize,
+    pub removed: usize,
+    pub rejected: Vec<RejectedBlock>,
+}
+
+impl RegionReport {
+    /// Counts rejected placements for each reason, in the order that the reasons first came up.
+    pub fn rejections_by_reason(&self) -> Vec<(InvalidPlacement, usize)> {
+        let mut counts: Vec<(InvalidPlacement, usize)> = vec![];
+        for rejected in self.rejected.iter() {
+            match counts
+                .iter_mut()
+                .find(|(reason, _)| *reason == rejected.reason)
+            {
+                Some((_, count)) => *count += 1,
+                None => counts.push((rejected.reason, 1)),
+            }
+        }
+        counts
+    }
+}
+
+/// Formats a one line summary, like `placed 8 blocks, removed 2, rejected 3 (NotAFlatSurface: 3)`.
+impl fmt::Display for RegionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "placed {} blocks, removed {}, rejected {}",
+            self.placed,
+            self.removed,
+            self.rejected.len()
+        )?;
+        if self.rejected.is_empty() {
+            return Ok(());
+        }
+        let reasons: Vec<String> = self
+            .rejections_by_reason()
+            .into_iter()
+            .map(|(reason, count)| format!("{:?}: {}", reason, count))
+            .collect();
+        write!(f, " ({})", reasons.join(", "))
+    }
+}
+
+/// Sets every position in the selection to the given block, bottom layer first so that blocks
+/// like wire can rest on the layer below.
+pub fn fill(world: &mut WorldState, selection: &Selection, state: &BlockState) -> RegionReport {
+    let mut report = RegionReport::default();
+    for pos in selection.positions() {
+        set_block(world, pos, state, &mut report);
+    }
+    report
+}
+
+/// Swaps every block in the selection that matches `from` for `to`. See `BlockState::matches`.
+pub fn replace(
+    world: &mut WorldState,
+    selection: &Selection,
+    from: &BlockState,
+    to: &BlockState,
+) -> RegionReport {
+    let mut report = RegionReport::default();
+    let matching: Vec<IntVec3> = selection
+        .positions()
+        .filter(|pos| matches!(world.block_at(*pos), Some(state) if state.matches(from)))
+        .collect();
+    for pos in matching {
+        set_block(world, pos, to, &mut report);
+    }
+    report
+}
+
+/// Builds a box with walls, floor and ceiling of the given block around the selection's interior,
+/// and removes every block inside.
+pub fn hollow(world: &mut WorldState, selection: &Selection, state: &BlockState) -> RegionReport {
+    let mut report = RegionReport::default();
+    let (min, max) = (selection.min(), selection.max());
+    let on_shell = |pos: &IntVec3| {
+        pos.x == min.x
+            || pos.x == max.x
+            || pos.y == min.y
+            || pos.y == max.y
+            || pos.z == min.z
+            || pos.z == max.z
+    };
+    for pos in selection.positions().filter(|pos| !on_shell(pos)) {
+        if world.remove_block(pos).is_some() {
+            report.removed += 1;
+        }
+    }
+    for pos in selection.positions().filter(on_shell) {
+        set_block(world, pos, state, &mut report);
+    }
+    report
+}
+
+/// Replaces the block at a position, and puts the previous block back if the new one is rejected.
+fn set_block(world: &mut WorldState, pos: IntVec3, state: &BlockState, report: &mut RegionReport) {
+    if world.block_at(pos) == Some(state) {
+        return;
+    }
+    let previous = world.remove_block(pos);
+    match world.insert_block(pos, state.clone()) {
+        Ok(()) => {
+            report.placed += 1;
+            if previous.is_some() {
+                report.removed += 1;
+            }
+        }
+        Err(reason) => {
+            report.rejected.push(RejectedBlock {
+                pos,
+                state: state.clone(),
+                reason,
+            });
+            // The previous block can only be rejected if the edit already replaced the block
+            // that it rested on.
+            if let Some(previous) = previous {
+                if world.insert_block(pos, previous).is_err() {
+                    report.removed += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        block_state::BlockState,
+        int_vec3::IntVec3,
+        timeline::{InvalidPlacement, WorldState},
+    };
+
+    use super::{fill, hollow, replace, Selection};
+
+    fn world() -> WorldState {
+        WorldState::new((IntVec3::ZERO, IntVec3::ONE * 7))
+    }
+
+    #[test]
+    fn fills_a_selection_and_counts_rejections() {
+        let mut world = world();
+        world
+            .insert_block(IntVec3::new(6, 0, 0), BlockState::new("iron_block", ""))
+            .unwrap();
+        let wire = BlockState::new("redstone_wire", "power=0");
+        let selection = Selection::new(IntVec3::new(5, 0, 0), IntVec3::new(8, 1, 0));
+        let report = fill(&mut world, &selection, &wire);
+        assert_eq!(report.placed, 3);
+        assert_eq!(report.removed, 1);
+        assert_eq!(
+            report.rejections_by_reason(),
+            vec![
+                (InvalidPlacement::OutOfBounds, 2),
+                (InvalidPlacement::NotAFlatSurface, 3),
+            ]
+        );
+        assert_eq!(
+            report.to_string(),
+            "placed 3 blocks, removed 1, rejected 5 (OutOfBounds: 2, NotAFlatSurface: 3)"
+        );
+        assert_eq!(world.block_at(IntVec3::new(6, 0, 0)), Some(&wire));
+        assert_eq!(world.block_at(IntVec3::new(6, 1, 0)), None);
+    }
+
+    #[test]
+    fn keeps_blocks_that_cannot_be_replaced() {
+        let mut world = world();
+        let lamp = BlockState::new("redstone_lamp", "lit=false");
+        world
+            .insert_block(IntVec3::ZERO, BlockState::new("redstone_wire", "power=0"))
+            .unwrap();
+        world.insert_block(IntVec3::Y, lamp.clone()).unwrap();
+        let column = Selection::new(IntVec3::Y, IntVec3::Y);
+        let report = fill(&mut world, &column, &BlockState::new("redstone_torch", ""));
+        assert_eq!(report.placed, 0);
+        assert_eq!(report.rejected[0].reason, InvalidPlacement::NotAFlatSurface);
+        assert_eq!(world.block_at(IntVec3::Y), Some(&lamp));
+    }
+
+    #[test]
+    fn removes_blocks_that_lose_their_support() {
+        let mut world = world();
+        world
+            .insert_block(IntVec3::ZERO, BlockState::new("iron_block", ""))
+            .unwrap();
+        world
+            .insert_block(IntVec3::Y, BlockState::new("redstone_torch", "lit=true"))
+            .unwrap();
+        let column = Selection::new(IntVec3::ZERO, IntVec3::Y);
+        let report = fill(&mut world, &column, &BlockState::new("redstone_wire", ""));
+        assert_eq!((report.placed, report.removed), (1, 2));
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(world.block_at(IntVec3::Y), None);
+    }
+
+    #[test]
+    fn replaces_only_matching_properties() {
+        let mut world = world();
+        for (x, state) in [(0, "lit=true"), (1, "lit=false")] {
+            world
+                .insert_block(
+                    IntVec3::new(x, 0, 0),
+                    BlockState::new("redstone_lamp", state),
+                )
+                .unwrap();
+        }
+        let selection = Selection::new(IntVec3::ZERO, IntVec3::new(2, 0, 0));
+        let report = replace(
+            &mut world,
+            &selection,
+            &"redstone_lamp[lit=false]".parse().unwrap(),
+            &BlockState::new("iron_block", ""),
+        );
+        assert_eq!((report.placed, report.removed), (1, 1));
+        assert_eq!(
+            world.block_at(IntVec3::new(1, 0, 0)),
+            Some(&BlockState::new("iron_block", ""))
+        );
+        assert_eq!(
+            world.block_at(IntVec3::ZERO),
+            Some(&BlockState::new("redstone_lamp", "lit=true"))
+        );
+    }
+
+    #[test]
+    fn hollows_out_a_box() {
+        let mut world = world();
+        let sandstone = BlockState::new("sandstone", "");
+        let selection = Selection::new(IntVec3::ZERO, IntVec3::ONE * 2);
+        fill(&mut world, &selection, &BlockState::new("iron_block", ""));
+        let report = hollow(&mut world, &selection, &sandstone);
+        assert_eq!((report.placed, report.removed), (26, 27));
+        assert!(report.rejected.is_empty());
+        assert_eq!(world.block_at(IntVec3::ONE), None);
+        assert_eq!(world.block_at(IntVec3::new(1, 2, 1)), Some(&sandstone));
+    }
+}
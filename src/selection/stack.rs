@@ -0,0 +1,191 @@
+use crate::{
+    block_state::BlockTransform, int_vec3::IntVec3, schematic::RejectedBlock, timeline::WorldState,
+};
+
+use super::{Clipboard, Selection};
+
+/// Repeats a selection along a direction, for designs like buses and memory banks that are the
+/// same slice over and over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stack {
+    /// Number of copies to add after the selection
+    pub count: usize,
+
+    /// One of the six unit vectors along an axis
+    pub direction: IntVec3,
+
+    /// Empty positions left between neighboring copies
+    pub spacing: i32,
+
+    /// Moves each copy further on top of its step along the direction, for stacks that climb
+    /// diagonally like a staircase.
+    pub offset: IntVec3,
+
+    /// Applied to every other copy within its own box. Mirroring alternate copies lets
+    /// neighboring cells share the wiring on the face between them.
+    pub alternate: Option<BlockTransform>,
+}
+
+impl Stack {
+    pub fn new(count: usize, direction: IntVec3) -> Self {
+        Stack {
+            count,
+            direction,
+            spacing: 0,
+            offset: IntVec3::ZERO,
+            alternate: None,
+        }
+    }
+
+    /// Stacks along the axis where the target is furthest outside of the selection, with enough
+    /// copies to reach the target. Returns `None` if the target is inside the selection.
+    pub fn toward(selection: &Selection, target: IntVec3) -> Option<Self> {
+        let (min, max, size) = (selection.min(), selection.max(), selection.size());
+        let outside = |pos: i32, min: i32, max: i32| {
+            if pos > max {
+                pos - max
+            } else if pos < min {
+                pos - min
+            } else {
+                0
+            }
+        };
+        let (distance, direction, extent) = [
+            (outside(target.x, min.x, max.x), IntVec3::X, size.x),
+            (outside(target.y, min.y, max.y), IntVec3::Y, size.y),
+            (outside(target.z, min.z, max.z), IntVec3::Z, size.z),
+        ]
+        .into_iter()
+        .max_by_key(|(distance, _, _)| distance.abs())?;
+        if distance == 0 {
+            return None;
+        }
+        let count = (distance.abs() + extent - 1) / extent;
+        Some(Stack::new(count as usize, direction * distance.signum()))
+    }
+
+    /// How far each copy is from the one before it.
+    pub fn step(&self, size: IntVec3) -> IntVec3 {
+        let d = self.direction;
+        let extent = d.x.abs() * size.x + d.y.abs() * size.y + d.z.abs() * size.z;
+        d * (extent + self.spacing) + self.offset
+    }
+}
+
+/// Pastes copies of a selection one step apart. Returns a selection that covers the original and
+/// every copy. The world is unchanged if any copy does not fit.
+pub fn stack_selection(
+    world: &mut WorldState,
+    selection: &Selection,
+    stack: &Stack,
+) -> Result<Selection, RejectedBlock> {
+    let clipboard = Clipboard::copy(world, selection);
+    let alternate = stack
+        .alternate
+        .map(|transform| clipboard.transformed(transform));
+    let step = stack.step(selection.size());
+    let mut stacked = world.clone();
+    let mut covered = *selection;
+    for copy in 1..=stack.count {
+        let blocks = match &alternate {
+            Some(alternate) if copy % 2 == 1 => alternate,
+            _ => &clipboard,
+        };
+        let origin = selection.min() + step * copy as i32;
+        blocks.paste(&mut stacked, origin)?;
+        covered = covered.union(&Selection::new(origin, origin + blocks.size - IntVec3::ONE));
+    }
+    *world = stacked;
+    Ok(covered)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        block_state::{BlockState, BlockTransform},
+        int_vec3::IntVec3,
+        timeline::{InvalidPlacement, WorldState},
+    };
+
+    use super::{stack_selection, Selection, Stack};
+
+    /// A repeater with a wire behind it, one block wide along z.
+    fn world_with_cell() -> WorldState {
+        let mut world = WorldState::new((IntVec3::ZERO, IntVec3::ONE * 15));
+        for (pos, state) in [
+            (IntVec3::new(0, 0, 0), "redstone_wire[power=0]"),
+            (IntVec3::new(1, 0, 0), "repeater[delay=1,facing=west]"),
+        ] {
+            world.insert_block(pos, state.parse().unwrap()).unwrap();
+        }
+        world
+    }
+
+    #[test]
+    fn stacks_copies_with_spacing_and_offset() {
+        let mut world = world_with_cell();
+        let selection = Selection::new(IntVec3::ZERO, IntVec3::new(1, 0, 0));
+        let mut stack = Stack::new(3, IntVec3::Z);
+        stack.spacing = 1;
+        let covered = stack_selection(&mut world, &selection, &stack).unwrap();
+        assert_eq!(
+            covered,
+            Selection::new(IntVec3::ZERO, IntVec3::new(1, 0, 6))
+        );
+        assert_eq!(world.blocks().count(), 8);
+        assert_eq!(
+            world.block_at(IntVec3::new(1, 0, 6)),
+            world.block_at(IntVec3::new(1, 0, 0))
+        );
+        assert_eq!(world.block_at(IntVec3::new(1, 0, 5)), None);
+
+        let mut world = world_with_cell();
+        let mut stack = Stack::new(2, IntVec3::X);
+        stack.offset = IntVec3::Z;
+        stack_selection(&mut world, &selection, &stack).unwrap();
+        assert!(world.block_at(IntVec3::new(5, 0, 2)).is_some());
+    }
+
+    #[test]
+    fn mirrors_alternate_copies() {
+        let mut world = world_with_cell();
+        let selection = Selection::new(IntVec3::ZERO, IntVec3::new(1, 0, 0));
+        let mut stack = Stack::new(2, IntVec3::X);
+        stack.alternate = Some(BlockTransform::MirrorX);
+        stack_selection(&mut world, &selection, &stack).unwrap();
+        assert_eq!(
+            world.block_at(IntVec3::new(2, 0, 0)),
+            Some(&"repeater[delay=1,facing=east]".parse().unwrap())
+        );
+        assert_eq!(
+            world.block_at(IntVec3::new(5, 0, 0)),
+            Some(&BlockState::new("repeater", "delay=1,facing=west"))
+        );
+    }
+
+    #[test]
+    fn stacks_nothing_when_a_copy_does_not_fit() {
+        let mut world = world_with_cell();
+        let before = world.clone();
+        let selection = Selection::new(IntVec3::ZERO, IntVec3::new(1, 0, 0));
+        let rejected =
+            stack_selection(&mut world, &selection, &Stack::new(8, IntVec3::X)).unwrap_err();
+        assert_eq!(rejected.pos, IntVec3::new(16, 0, 0));
+        assert_eq!(rejected.reason, InvalidPlacement::OutOfBounds);
+        assert_eq!(world, before);
+    }
+
+    #[test]
+    fn stacks_toward_a_target() {
+        let selection = Selection::new(IntVec3::new(2, 0, 2), IntVec3::new(3, 1, 2));
+        assert_eq!(
+            Stack::toward(&selection, IntVec3::new(3, 0, 7)),
+            Some(Stack::new(5, IntVec3::Z))
+        );
+        assert_eq!(
+            Stack::toward(&selection, IntVec3::new(-1, 1, 3)),
+            Some(Stack::new(2, IntVec3::NEG_X))
+        );
+        assert_eq!(Stack::toward(&selection, IntVec3::new(2, 1, 2)), None);
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    block_state::BlockState,
+    constants::{PIXELS, UI_FONT},
+    cursor::Cursor,
+    int_vec3::IntVec3,
+    lines::{LineList, LineMaterial},
+    logic_analyzer::CurrentTick,
+    timeline::{decays_to_zero_at, power_level, SignalTrace, Timeline, TraceSegment, MAX_POWER},
+    user_input::{sent_command, UiCommand},
+};
+
+/// Arrows stop short of block centers so that arrows into and out of the same block do not touch.
+const ARROW_INSET: f32 = 3.0 * PIXELS;
+const ARROW_HEAD_LENGTH: f32 = 3.0 * PIXELS;
+const ARROW_HEAD_WIDTH: f32 = 2.0 * PIXELS;
+
+/// Color for connections that would carry power if their source were switched on
+const UNPOWERED_COLOR: Color = Color::rgb(0.4, 0.4, 0.5);
+
+const READOUT_FONT_SIZE: f32 = 18.0;
+const READOUT_BACKGROUND_COLOR: Color = Color::rgba(0.1, 0.1, 0.1, 0.85);
+
+pub struct SignalTracePlugin;
+
+impl Plugin for SignalTracePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ActiveTrace::default())
+            .add_startup_system(spawn_hover_readout)
+            .add_system(update_trace)
+            .add_system(draw_trace)
+            .add_system(describe_hovered_block);
+    }
+}
+
+/// The trace shown in the world, if any. Tracing the target again clears it.
+#[derive(Debug, Default, Resource)]
+struct ActiveTrace(Option<SignalTrace>);
+
+#[derive(Component)]
+struct TraceArrow;
+
+/// Text in the top left corner that describes the traced block under the cursor.
+#[derive(Component)]
+struct HoverReadout;
+
+fn spawn_hover_readout(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn(TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load(UI_FONT),
+                    font_size: READOUT_FONT_SIZE,
+                    color: Color::WHITE,
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(6.0),
+                    top: Val::Px(6.0),
+                    ..default()
+                },
+                padding: UiRect::all(Val::Px(4.0)),
+                ..default()
+            },
+            background_color: READOUT_BACKGROUND_COLOR.into(),
+            ..default()
+        })
+        .insert(HoverReadout);
+}
+
+/// Starts or clears a trace when the trace tool is used, and recomputes the active trace when the
+/// design or the current tick changes.
+fn update_trace(
+    user_input: EventReader<UiCommand>,
+    cursor: Res<Cursor>,
+    current_tick: Res<CurrentTick>,
+    mut timeline: ResMut<Timeline>,
+    mut active_trace: ResMut<ActiveTrace>,
+    query_blocks: Query<&Transform, With<BlockState>>,
+) {
+    let mut target = active_trace.0.as_ref().map(|trace| trace.target);
+    if sent_command(user_input, UiCommand::TraceSignal) {
+        let pos = cursor
+            .current_block
+            .and_then(|block| query_blocks.get(block).ok())
+            .map(|transform| IntVec3::from_translation(transform.translation));
+        target = if pos == target { None } else { pos };
+    } else if !(timeline.is_changed() || current_tick.is_changed()) {
+        return;
+    }
+
+    // Only borrow the timeline mutably when more ticks need to be simulated. Otherwise the change
+    // would trigger a retrace on every frame.
+    if target.is_some() && timeline.last_simulated_time() < current_tick.0 {
+        timeline.simulate_to(current_tick.0);
+    }
+    let trace = target.and_then(|target| {
+        let world = timeline.state_at(current_tick.0)?;
+        world.block_at(target)?;
+        Some(SignalTrace::new(&world, target))
+    });
+    if active_trace.0 != trace {
+        active_trace.0 = trace;
+    }
+}
+
+/// Draws an arrow for each connection in the active trace. Arrows are brighter where the power
+/// level is higher.
+fn draw_trace(
+    mut commands: Commands,
+    active_trace: Res<ActiveTrace>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut line_materials: ResMut<Assets<LineMaterial>>,
+    query_arrows: Query<Entity, With<TraceArrow>>,
+) {
+    if !active_trace.is_changed() {
+        return;
+    }
+    for arrow in query_arrows.iter() {
+        commands.entity(arrow).despawn();
+    }
+    let trace = match &active_trace.0 {
+        Some(trace) => trace,
+        None => return,
+    };
+
+    // One mesh per power level so that each level gets its own color.
+    let mut lines_by_level: BTreeMap<i32, Vec<(Vec3, Vec3)>> = BTreeMap::new();
+    for segment in trace.segments.iter() {
+        lines_by_level
+            .entry(segment.level)
+            .or_default()
+            .extend(arrow_lines(segment));
+    }
+    for (level, lines) in lines_by_level {
+        commands
+            .spawn(MaterialMeshBundle {
+                mesh: meshes.add(Mesh::from(LineList::new(lines))),
+                material: line_materials.add(LineMaterial::new(level_color(level))),
+                ..default()
+            })
+            .insert(TraceArrow);
+    }
+}
+
+fn level_color(level: i32) -> Color {
+    if level == 0 {
+        UNPOWERED_COLOR
+    } else {
+        Color::rgb(0.3 + 0.7 * level as f32 / MAX_POWER as f32, 0.1, 0.1)
+    }
+}
+
+/// A line from the center of the source block to the center of the powered block, with a head at
+/// the powered end.
+fn arrow_lines(segment: &TraceSegment) -> [(Vec3, Vec3); 3] {
+    let from = segment.from.to_translation();
+    let to = segment.to.to_translation();
+    let direction = (to - from).normalize();
+    let start = from + direction * ARROW_INSET;
+    let end = to - direction * ARROW_INSET;
+
+    // Arrow heads lie flat where possible so that they are visible from above.
+    let side = if direction.y.abs() > 0.9 {
+        Vec3::X
+    } else {
+        direction.cross(Vec3::Y).normalize()
+    } * ARROW_HEAD_WIDTH;
+    let back = end - direction * ARROW_HEAD_LENGTH;
+    [(start, end), (end, back + side), (end, back - side)]
+}
+
+/// Shows the power level of the traced block under the cursor at the current tick, and for wire,
+/// where the signal decays to zero. The readout is empty while no traced block is hovered.
+fn describe_hovered_block(
+    cursor: Res<Cursor>,
+    current_tick: Res<CurrentTick>,
+    timeline: Res<Timeline>,
+    active_trace: Res<ActiveTrace>,
+    query_blocks: Query<&Transform, With<BlockState>>,
+    mut query_readout: Query<(&mut Text, &mut Visibility), With<HoverReadout>>,
+    mut described: Local<Option<IntVec3>>,
+) {
+    let pos = active_trace.0.as_ref().and_then(|trace| {
+        cursor
+            .current_block
+            .and_then(|block| query_blocks.get(block).ok())
+            .map(|transform| IntVec3::from_translation(transform.translation))
+            .filter(|pos| trace.contains(*pos))
+    });
+    // The same block can have a different power level after the tick or the design changes.
+    if pos == *described && !(current_tick.is_changed() || timeline.is_changed()) {
+        return;
+    }
+    *described = pos;
+    let description = match (pos, timeline.state_at(current_tick.0)) {
+        (Some(pos), Some(world)) => {
+            let level = power_level(&world, pos);
+            match decays_to_zero_at(&world, pos) {
+                Some(end) => format!(
+                    "{} {} {}: power {} at tick {}, decays to zero at {} {} {}",
+                    pos.x, pos.y, pos.z, level, current_tick.0, end.x, end.y, end.z
+                ),
+                None => format!(
+                    "{} {} {}: power {} at tick {}",
+                    pos.x, pos.y, pos.z, level, current_tick.0
+                ),
+            }
+        }
+        _ => String::new(),
+    };
+    let (mut text, mut visibility) = query_readout.single_mut();
+    visibility.is_visible = !description.is_empty();
+    if text.sections[0].value != description {
+        text.sections[0].value = description;
+    }
+}
@@ -0,0 +1,99 @@
+mod validator;
+
+use bevy::prelude::*;
+
+use crate::{
+    block::bounding_box_to_line_list,
+    constants::PIXELS,
+    lines::LineMaterial,
+    timeline::Timeline,
+    user_input::{sent_command, UiCommand},
+};
+
+pub use self::validator::{survival_issues, Reason, SurvivalIssue};
+
+const ISSUE_MARKER_COLOR: Color = Color::ORANGE_RED;
+
+pub struct SurvivalCheckPlugin;
+
+impl Plugin for SurvivalCheckPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SurvivalCheck {
+            is_enabled: true,
+            issues: vec![],
+        })
+        .add_system(toggle_survival_check)
+        .add_system(check_design)
+        .add_system(update_issue_markers);
+    }
+}
+
+/// Blocks in the design that could not be placed in survival mode. These are marked in the world
+/// while the check is enabled.
+#[derive(Debug, Resource)]
+pub struct SurvivalCheck {
+    is_enabled: bool,
+    pub issues: Vec<SurvivalIssue>,
+}
+
+#[derive(Component)]
+struct IssueMarker;
+
+fn toggle_survival_check(user_input: EventReader<UiCommand>, mut check: ResMut<SurvivalCheck>) {
+    if sent_command(user_input, UiCommand::ToggleSurvivalCheck) {
+        check.is_enabled = !check.is_enabled;
+        info!(
+            "survival check {}",
+            if check.is_enabled { "on" } else { "off" }
+        );
+    }
+}
+
+/// Checks the design whenever it changes, and logs any issues that were not found before.
+fn check_design(timeline: Res<Timeline>, mut check: ResMut<SurvivalCheck>) {
+    if !(timeline.is_changed() || check.is_changed()) {
+        return;
+    }
+    let issues = if check.is_enabled {
+        survival_issues(timeline.design())
+    } else {
+        vec![]
+    };
+    if issues == check.issues {
+        return;
+    }
+    for issue in issues.iter().filter(|issue| !check.issues.contains(issue)) {
+        warn!("not survival legal: {}", issue);
+    }
+    check.issues = issues;
+}
+
+/// Draws an outline around each block that has an issue.
+fn update_issue_markers(
+    mut commands: Commands,
+    check: Res<SurvivalCheck>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut line_materials: ResMut<Assets<LineMaterial>>,
+    query_markers: Query<Entity, With<IssueMarker>>,
+) {
+    if !check.is_changed() {
+        return;
+    }
+    for marker in query_markers.iter() {
+        commands.entity(marker).despawn();
+    }
+    // Larger than probe markers so that both are visible on the same block.
+    let half_size = Vec3::ONE * 9.0 * PIXELS;
+    for issue in check.issues.iter() {
+        commands
+            .spawn(MaterialMeshBundle {
+                mesh: meshes.add(Mesh::from(bounding_box_to_line_list((
+                    -half_size, half_size,
+                )))),
+                material: line_materials.add(LineMaterial::new(ISSUE_MARKER_COLOR)),
+                transform: Transform::from_translation(issue.pos.to_translation()),
+                ..default()
+            })
+            .insert(IssueMarker);
+    }
+}
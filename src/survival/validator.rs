@@ -0,0 +1,156 @@
+use std::fmt;
+
+use minecraft_assets::schemas::models::BlockFace;
+
+use crate::{
+    block::{attachment_face, has_gravity, is_flat_surface, is_transparent},
+    block_state::BlockState,
+    int_vec3::IntVec3,
+    timeline::WorldState,
+};
+
+/// A block that a survival player could not place as it is in the design.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SurvivalIssue {
+    pub pos: IntVec3,
+    pub state: BlockState,
+    pub reason: Reason,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reason {
+    /// A block that must be attached to another block, such as a torch, has nothing to attach to.
+    Floating,
+
+    /// A block is attached to a transparent block such as glass.
+    TransparentSupport,
+
+    /// A block is attached to a block without a solid face, such as a lever on a repeater.
+    NotSolidSupport,
+
+    /// A block that falls, such as sand, has nothing under it.
+    UnsupportedGravityBlock,
+}
+
+/// Checks every block in the world against placement rules in survival mode. These are stricter
+/// than the rules that the editor enforces on placement. Issues are ordered bottom layer first.
+pub fn survival_issues(world: &WorldState) -> Vec<SurvivalIssue> {
+    let mut issues: Vec<SurvivalIssue> = world
+        .blocks()
+        .filter_map(|(pos, state)| {
+            let reason = check_block(world, pos, state)?;
+            Some(SurvivalIssue {
+                pos,
+                state: state.clone(),
+                reason,
+            })
+        })
+        .collect();
+    issues.sort_by_key(|issue| issue.pos.layer_order_key());
+    issues
+}
+
+fn check_block(world: &WorldState, pos: IntVec3, state: &BlockState) -> Option<Reason> {
+    if let Some(face) = attachment_face(state) {
+        let support = pos + IntVec3::from_block_face(face);
+        // The ground under the world bounds supports blocks on top of it.
+        if face == BlockFace::Down && is_below_bounds(world, support) {
+            return None;
+        }
+        return match world.block_at(support) {
+            None => Some(Reason::Floating),
+            Some(support) if is_transparent(support) => Some(Reason::TransparentSupport),
+            Some(support) if !is_flat_surface(support) => Some(Reason::NotSolidSupport),
+            Some(_) => None,
+        };
+    }
+    if has_gravity(state) {
+        let below = pos + IntVec3::NEG_Y;
+        if !is_below_bounds(world, below) && world.block_at(below).is_none() {
+            return Some(Reason::UnsupportedGravityBlock);
+        }
+    }
+    None
+}
+
+fn is_below_bounds(world: &WorldState, pos: IntVec3) -> bool {
+    pos.y < world.bounds().0.y
+}
+
+impl fmt::Display for SurvivalIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self.reason {
+            Reason::Floating => "not attached to a block",
+            Reason::TransparentSupport => "attached to a transparent block",
+            Reason::NotSolidSupport => "attached to a block without a solid face",
+            Reason::UnsupportedGravityBlock => "nothing underneath to stop it falling",
+        };
+        write!(
+            f,
+            "{} at {} {} {}: {}",
+            self.state, self.pos.x, self.pos.y, self.pos.z, reason
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{block_state::BlockState, int_vec3::IntVec3, timeline::WorldState};
+
+    use super::{survival_issues, Reason};
+
+    fn world(blocks: &[(IntVec3, &str, &str)]) -> WorldState {
+        let mut world = WorldState::new((IntVec3::ZERO, IntVec3::ONE * 15));
+        for (pos, block_type, state_values) in blocks {
+            world
+                .insert_block(*pos, BlockState::new(block_type, state_values))
+                .unwrap();
+        }
+        world
+    }
+
+    #[test]
+    fn accepts_blocks_on_solid_ground() {
+        let world = world(&[
+            (IntVec3::new(0, 0, 0), "sand", ""),
+            (IntVec3::new(1, 0, 0), "iron_block", ""),
+            (IntVec3::new(1, 1, 0), "redstone_torch", "lit=true"),
+            (
+                IntVec3::new(2, 0, 0),
+                "lever",
+                "face=wall,facing=east,powered=false",
+            ),
+        ]);
+        assert_eq!(survival_issues(&world), vec![]);
+    }
+
+    #[test]
+    fn reports_unsupported_blocks() {
+        let world = world(&[
+            (IntVec3::new(0, 3, 0), "sand", ""),
+            (
+                IntVec3::new(5, 2, 5),
+                "lever",
+                "face=wall,facing=north,powered=false",
+            ),
+            (IntVec3::new(8, 0, 8), "glass", ""),
+            (
+                IntVec3::new(8, 0, 7),
+                "lever",
+                "face=wall,facing=north,powered=false",
+            ),
+        ]);
+        let issues: Vec<_> = survival_issues(&world)
+            .into_iter()
+            .map(|issue| (issue.pos, issue.reason))
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                (IntVec3::new(8, 0, 7), Reason::TransparentSupport),
+                (IntVec3::new(5, 2, 5), Reason::Floating),
+                (IntVec3::new(0, 3, 0), Reason::UnsupportedGravityBlock),
+            ]
+        );
+    }
+}
@@ -0,0 +1,466 @@
+//! Places a netlist on the grid with a fixed floor plan.
+//!
+//! Every signal gets a lane: a line of redstone wire on the ground that runs along the x axis for
+//! the full width of the layout, with lanes four blocks apart along z. Input lanes are driven by
+//! levers at x = 0, and output lanes end in lamps. Gates are placed one after another along x,
+//! and each gate is built as a cell that sits on top of the lanes:
+//!
+//! - At the first column of the cell each input lane is tapped by a solid block beside the lane
+//!   with a tower of torches on top. One torch gives the inverted signal two blocks up. Two
+//!   torches give the signal four blocks up.
+//! - A repeater carries each tap into a bus wire that runs along z above the lanes, which ORs the
+//!   taps together. The bus has a repeater every four blocks so that it can span any number of
+//!   lanes.
+//! - The bus runs along x at the row next to the output lane, optionally through a torch that
+//!   inverts it, and then steps down to the ground with a block and a repeater for each level. The
+//!   last step joins the output lane.
+//!
+//! With inverted taps and an inverted output a cell can compute OR, NOR, NAND, AND, and NOT.
+//! Other gates are built from several cells. Since every lane spans the whole layout, signals can
+//! feed back into cells that come before the cell that drives them, which is how latches work.
+
+use std::collections::HashMap;
+
+use crate::{block_state::BlockState, int_vec3::IntVec3, timeline::WorldState};
+
+use super::netlist::{GateKind, Netlist};
+
+const LANE_SPACING: i32 = 4;
+const CELL_WIDTH: i32 = 17;
+
+/// Cells start after the lever column and one column of wire.
+const FIRST_CELL: i32 = 2;
+
+/// Wire loses one power level per block, so lanes get a repeater at least this often.
+const LANE_REPEATER_SPACING: i32 = 12;
+
+/// Height of the bus for cells with inverted taps, and for cells with plain taps
+const INVERTED_TAP_HEIGHT: i32 = 2;
+const TAP_HEIGHT: i32 = 4;
+
+const BLOCK: &str = "sandstone";
+const WIRE: &str = "east=none,north=none,south=none,west=none,power=0";
+
+/// A synthesized design, and the positions of the levers and lamps for each input and output.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layout {
+    pub world: WorldState,
+    pub inputs: Vec<(String, IntVec3)>,
+    pub outputs: Vec<(String, IntVec3)>,
+}
+
+/// A gate that can be built in one cell. The output is the OR of the inputs, with each input
+/// inverted if `inverted_taps` is set, and with the result inverted if `inverted_output` is set.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Cell {
+    inputs: Vec<usize>,
+    inverted_taps: bool,
+    inverted_output: bool,
+    output: usize,
+}
+
+impl Cell {
+    fn new(kind: GateKind, inputs: Vec<usize>, output: usize) -> Self {
+        let (inverted_taps, inverted_output) = match kind {
+            GateKind::Buf | GateKind::Or => (false, false),
+            GateKind::Nor => (false, true),
+            GateKind::Not | GateKind::Nand => (true, false),
+            GateKind::And => (true, true),
+            GateKind::Xor | GateKind::Xnor | GateKind::DLatch => {
+                unreachable!("{} is built from several cells", kind)
+            }
+        };
+        let mut inputs = inputs;
+        inputs.sort();
+        inputs.dedup();
+        Cell {
+            inputs,
+            inverted_taps,
+            inverted_output,
+            output,
+        }
+    }
+
+    fn bus_height(&self) -> i32 {
+        if self.inverted_taps {
+            INVERTED_TAP_HEIGHT
+        } else {
+            TAP_HEIGHT
+        }
+    }
+
+    fn evaluate(&self, lanes: &[bool]) -> bool {
+        self.bus_value(lanes) != self.inverted_output
+    }
+
+    fn bus_value(&self, lanes: &[bool]) -> bool {
+        self.inputs
+            .iter()
+            .any(|input| lanes[*input] != self.inverted_taps)
+    }
+}
+
+pub fn synthesize(netlist: &Netlist) -> Layout {
+    let (lanes, cells) = lower(netlist);
+    let values = initial_values(lanes.len(), &cells);
+    let width = FIRST_CELL + cells.len() as i32 * CELL_WIDTH;
+    let mut builder = Builder::default();
+
+    // Cells are built first so that the lanes can avoid the columns where cells connect to them.
+    let mut drivers = vec![0; lanes.len()];
+    let mut tap_columns = vec![];
+    for (index, cell) in cells.iter().enumerate() {
+        let x0 = FIRST_CELL + index as i32 * CELL_WIDTH;
+        tap_columns.push(x0);
+        drivers[cell.output] = builder.cell(x0, cell, &values);
+    }
+
+    let mut inputs = vec![];
+    for (lane, name) in lanes.iter().enumerate().take(netlist.inputs.len()) {
+        let pos = IntVec3::new(0, 0, lane_z(lane));
+        builder.put(pos, "lever", "face=floor,facing=north,powered=false");
+        inputs.push((name.clone(), pos));
+    }
+    for (lane, driver) in drivers.iter().enumerate() {
+        builder.lane(lane, *driver, width, &tap_columns, values[lane]);
+    }
+    let mut outputs = vec![];
+    for name in netlist.outputs.iter() {
+        let lane = lanes.iter().position(|lane| lane == name).unwrap();
+        let pos = IntVec3::new(width, 0, lane_z(lane));
+        let lit = if values[lane] {
+            "lit=true"
+        } else {
+            "lit=false"
+        };
+        builder.put(pos, "redstone_lamp", lit);
+        outputs.push((name.clone(), pos));
+    }
+
+    let bounds = (
+        IntVec3::ZERO,
+        IntVec3::new(width, TAP_HEIGHT + 2, lane_z(lanes.len())),
+    );
+    Layout {
+        world: builder.build(bounds),
+        inputs,
+        outputs,
+    }
+}
+
+/// Splits gates into cells. Returns the name of the signal in each lane, with inputs first, and
+/// the cells in the order that they are placed.
+fn lower(netlist: &Netlist) -> (Vec<String>, Vec<Cell>) {
+    let mut lanes: Vec<String> = netlist.inputs.clone();
+    lanes.extend(netlist.gates.iter().map(|gate| gate.output.clone()));
+    let index: HashMap<String, usize> = lanes
+        .iter()
+        .enumerate()
+        .map(|(lane, name)| (name.clone(), lane))
+        .collect();
+
+    let mut cells = vec![];
+    let helper = |lanes: &mut Vec<String>, name: String| {
+        lanes.push(name);
+        lanes.len() - 1
+    };
+    for gate in netlist.gates.iter() {
+        let inputs: Vec<usize> = gate.inputs.iter().map(|input| index[input]).collect();
+        let output = index[&gate.output];
+        match gate.kind {
+            GateKind::Xor | GateKind::Xnor => {
+                // Combine inputs two at a time. Each step is the AND of the NAND and the OR of its
+                // inputs, and the last step for XNOR is the OR of the AND and the NOR instead.
+                let mut acc = inputs[0];
+                for (step, input) in inputs.iter().enumerate().skip(1) {
+                    let is_last = step == inputs.len() - 1;
+                    let pair = vec![acc, *input];
+                    let a = helper(&mut lanes, format!("{}:{}a", gate.output, step));
+                    let b = helper(&mut lanes, format!("{}:{}b", gate.output, step));
+                    let result = if is_last {
+                        output
+                    } else {
+                        helper(&mut lanes, format!("{}:{}", gate.output, step))
+                    };
+                    if is_last && gate.kind == GateKind::Xnor {
+                        cells.push(Cell::new(GateKind::And, pair.clone(), a));
+                        cells.push(Cell::new(GateKind::Nor, pair, b));
+                        cells.push(Cell::new(GateKind::Or, vec![a, b], result));
+                    } else {
+                        cells.push(Cell::new(GateKind::Nand, pair.clone(), a));
+                        cells.push(Cell::new(GateKind::Or, pair, b));
+                        cells.push(Cell::new(GateKind::And, vec![a, b], result));
+                    }
+                    acc = result;
+                }
+            }
+            GateKind::DLatch => {
+                // The third term holds the output while enable turns off, which would otherwise
+                // glitch because the inverted enable signal arrives late.
+                let (data, enable) = (inputs[0], inputs[1]);
+                let name = |suffix: &str| format!("{}:{}", gate.output, suffix);
+                let not_enable = helper(&mut lanes, name("not_enable"));
+                let set = helper(&mut lanes, name("set"));
+                let hold = helper(&mut lanes, name("hold"));
+                let keep = helper(&mut lanes, name("keep"));
+                cells.push(Cell::new(GateKind::Not, vec![enable], not_enable));
+                cells.push(Cell::new(GateKind::And, vec![data, enable], set));
+                cells.push(Cell::new(GateKind::And, vec![output, not_enable], hold));
+                cells.push(Cell::new(GateKind::And, vec![data, output], keep));
+                cells.push(Cell::new(GateKind::Or, vec![set, hold, keep], output));
+            }
+            kind => cells.push(Cell::new(kind, inputs, output)),
+        }
+    }
+    (lanes, cells)
+}
+
+/// Logic level of each lane when every input is off. Blocks are placed in the states that match
+/// these levels so that the design starts out settled. Latches start out off.
+fn initial_values(lanes: usize, cells: &[Cell]) -> Vec<bool> {
+    let mut values = vec![false; lanes];
+    // Each pass settles at least one more cell unless there is a loop, so a design without an
+    // oscillator settles within one pass per cell.
+    for _ in 0..=cells.len() {
+        let next: Vec<(usize, bool)> = cells
+            .iter()
+            .map(|cell| (cell.output, cell.evaluate(&values)))
+            .collect();
+        if next.iter().all(|(lane, value)| values[*lane] == *value) {
+            break;
+        }
+        for (lane, value) in next {
+            values[lane] = value;
+        }
+    }
+    values
+}
+
+fn lane_z(lane: usize) -> i32 {
+    lane as i32 * LANE_SPACING
+}
+
+#[derive(Default)]
+struct Builder {
+    blocks: HashMap<IntVec3, BlockState>,
+}
+
+impl Builder {
+    /// Panics if the position is already taken, since that means that the floor plan is broken.
+    fn put(&mut self, pos: IntVec3, block_type: &str, state_values: &str) {
+        let previous = self
+            .blocks
+            .insert(pos, BlockState::new(block_type, state_values));
+        assert!(previous.is_none(), "two blocks placed at {:?}", pos);
+    }
+
+    fn block(&mut self, pos: IntVec3) {
+        self.put(pos, BLOCK, "");
+    }
+
+    fn wire(&mut self, pos: IntVec3) {
+        self.put(pos, "redstone_wire", WIRE);
+    }
+
+    fn torch(&mut self, pos: IntVec3, lit: bool) {
+        self.put(pos, "redstone_torch", &format!("lit={}", lit));
+    }
+
+    /// Places a repeater with a block under it, unless it is on the ground. The repeater's input
+    /// is on the given side.
+    fn repeater(&mut self, pos: IntVec3, input_side: &str, powered: bool) {
+        if pos.y > 0 {
+            self.block(pos + IntVec3::NEG_Y);
+        }
+        self.put(
+            pos,
+            "repeater",
+            &format!(
+                "delay=1,facing={},locked=false,powered={}",
+                input_side, powered
+            ),
+        );
+    }
+
+    /// Wire with a block under it, unless it is on the ground.
+    fn supported_wire(&mut self, pos: IntVec3) {
+        if pos.y > 0 {
+            self.block(pos + IntVec3::NEG_Y);
+        }
+        self.wire(pos);
+    }
+
+    /// Builds a cell with its first column at `x0`, and returns the column where it joins its
+    /// output lane.
+    fn cell(&mut self, x0: i32, cell: &Cell, values: &[bool]) -> i32 {
+        let height = cell.bus_height();
+        let bus_value = cell.bus_value(values);
+        let output_value = values[cell.output];
+
+        // Taps
+        for input in cell.inputs.iter() {
+            let z = lane_z(*input) + 1;
+            let lane_value = values[*input];
+            self.block(IntVec3::new(x0, 0, z));
+            self.torch(IntVec3::new(x0, 1, z), !lane_value);
+            self.block(IntVec3::new(x0, 2, z));
+            if !cell.inverted_taps {
+                self.torch(IntVec3::new(x0, 3, z), lane_value);
+                self.block(IntVec3::new(x0, 4, z));
+            }
+            let tap_value = lane_value != cell.inverted_taps;
+            self.repeater(IntVec3::new(x0 + 1, height, z), "west", tap_value);
+        }
+
+        // The bus runs along z past every tap to the row beside the output lane. Repeaters point
+        // toward the output row.
+        let output_z = lane_z(cell.output) + 2;
+        let tap_rows = cell.inputs.iter().map(|input| lane_z(*input) + 1);
+        let low = tap_rows.clone().chain([output_z]).min().unwrap();
+        let high = tap_rows.chain([output_z]).max().unwrap();
+        let bus_x = x0 + 2;
+        for z in low..=high {
+            let pos = IntVec3::new(bus_x, height, z);
+            if z.rem_euclid(LANE_SPACING) == 3 {
+                let input_side = if z > output_z { "south" } else { "north" };
+                self.repeater(pos, input_side, bus_value);
+            } else {
+                self.supported_wire(pos);
+            }
+        }
+
+        // Invert the bus with a torch on a block, which raises the signal one level.
+        let (mut x, mut level) = (bus_x + 1, height);
+        if cell.inverted_output {
+            self.supported_wire(IntVec3::new(x, level, output_z));
+            self.block(IntVec3::new(x + 1, level, output_z));
+            self.torch(IntVec3::new(x + 1, level + 1, output_z), !bus_value);
+            x += 2;
+            level += 1;
+        }
+
+        // Step down to the ground. Each step is wire on a block that powers a repeater one level
+        // down.
+        while level > 0 {
+            self.supported_wire(IntVec3::new(x, level, output_z));
+            self.repeater(
+                IntVec3::new(x + 1, level - 1, output_z),
+                "west",
+                output_value,
+            );
+            x += 2;
+            level -= 1;
+        }
+        self.wire(IntVec3::new(x, 0, output_z));
+        self.wire(IntVec3::new(x, 0, output_z - 1));
+        x
+    }
+
+    /// Lays wire along the lane for the full width of the layout with repeaters that point away
+    /// from the column where the lane is driven. Repeaters avoid the columns where cells tap the
+    /// lanes.
+    fn lane(&mut self, lane: usize, driver: i32, width: i32, tap_columns: &[i32], value: bool) {
+        let mut repeaters = vec![];
+        let mut x = driver;
+        while x + LANE_REPEATER_SPACING < width {
+            x += LANE_REPEATER_SPACING;
+            if tap_columns.contains(&x) {
+                x -= 1;
+            }
+            repeaters.push((x, "west"));
+        }
+        let mut x = driver;
+        while x - LANE_REPEATER_SPACING > 1 {
+            x -= LANE_REPEATER_SPACING;
+            if tap_columns.contains(&x) {
+                x += 1;
+            }
+            repeaters.push((x, "east"));
+        }
+        for x in 1..width {
+            let pos = IntVec3::new(x, 0, lane_z(lane));
+            match repeaters.iter().find(|(column, _)| *column == x) {
+                Some((_, input_side)) => self.repeater(pos, input_side, value),
+                None => self.wire(pos),
+            }
+        }
+    }
+
+    /// Inserts blocks from the bottom up so that each block has its support in place.
+    fn build(self, bounds: (IntVec3, IntVec3)) -> WorldState {
+        let mut blocks: Vec<_> = self.blocks.into_iter().collect();
+        blocks.sort_by_key(|(pos, _)| pos.layer_order_key());
+        let mut world = WorldState::new(bounds);
+        for (pos, state) in blocks {
+            world
+                .insert_block(pos, state)
+                .unwrap_or_else(|err| panic!("cannot place block at {:?}: {:?}", pos, err));
+        }
+        world
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        harness::CircuitTest, logic_analyzer::Probe, synthesis::Netlist, timeline::Timeline,
+        truth_table::TruthTable,
+    };
+
+    use super::{synthesize, Layout};
+
+    fn truth_table(layout: &Layout) -> Vec<Vec<bool>> {
+        let probes = |signals: &[(String, _)]| -> Vec<Probe> {
+            signals.iter().map(|(_, pos)| Probe::at(*pos)).collect()
+        };
+        let table = TruthTable::generate(
+            &Timeline::from_world(layout.world.clone()),
+            &probes(&layout.inputs),
+            &probes(&layout.outputs),
+            200,
+        )
+        .unwrap();
+        assert_eq!(table.unstable_rows().count(), 0, "every row settles");
+        table.rows.into_iter().map(|row| row.outputs).collect()
+    }
+
+    #[test]
+    fn builds_a_half_adder() {
+        let netlist = Netlist::parse("sum = a ^ b\ncarry = a & b").unwrap();
+        let layout = synthesize(&netlist);
+        assert_eq!(
+            truth_table(&layout),
+            vec![
+                vec![false, false],
+                vec![true, false],
+                vec![true, false],
+                vec![false, true],
+            ]
+        );
+    }
+
+    #[test]
+    fn builds_inverting_gates() {
+        // The half adder covers NAND, AND, and OR.
+        let netlist = Netlist::from_expression("nor(a, !b)").unwrap();
+        let layout = synthesize(&netlist);
+        assert_eq!(
+            truth_table(&layout),
+            vec![vec![false], vec![true], vec![false], vec![false]]
+        );
+    }
+
+    #[test]
+    fn builds_a_latch() {
+        let layout = synthesize(&Netlist::parse("q = dlatch(d, e)").unwrap());
+        let (d, e) = (layout.inputs[0].1, layout.inputs[1].1);
+        let q = layout.outputs[0].1;
+        let mut test = CircuitTest::from_world(layout.world);
+        test.run(50).assert_unpowered(q);
+        test.set_lever(d, true).run(50).assert_unpowered(q);
+        test.set_lever(e, true).run(50).assert_powered(q);
+        test.set_lever(e, false).run(50).assert_powered(q);
+        test.set_lever(d, false).run(50).assert_powered(q);
+        test.set_lever(e, true).run(50).assert_unpowered(q);
+    }
+}
@@ -0,0 +1,5 @@
+mod layout;
+mod netlist;
+
+pub use self::layout::{synthesize, Layout};
+pub use self::netlist::{Gate, GateKind, Netlist};
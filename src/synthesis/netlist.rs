@@ -0,0 +1,444 @@
+use std::{collections::HashSet, fmt, iter::Peekable, str::Chars};
+
+use anyhow::{anyhow, Context, Result};
+
+/// A list of logic gates that connect named signals. Netlists have a small text format with one
+/// statement per line:
+///
+/// ```text
+/// # half adder
+/// input a b
+/// output sum carry
+/// sum = a ^ b
+/// carry = and(a, b)
+/// ```
+///
+/// Assignments take an expression with the operators `!` (or `~`), `&`, `^`, and `|` in order of
+/// precedence, parentheses, and the functions `and`, `or`, `not`, `nand`, `nor`, `xor`, `xnor`, and
+/// `dlatch(data, enable)`. A signal may be used before the line that assigns it, which is how
+/// feedback loops are written.
+///
+/// `input` and `output` lines are optional. Without them the inputs are the signals that are used
+/// but never assigned, and the outputs are the assigned signals that are never used. Blank lines,
+/// and lines that start with `#` are ignored.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Netlist {
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    pub gates: Vec<Gate>,
+}
+
+/// A gate that drives the `output` signal. Gates that are written as nested expressions are
+/// split up, and the intermediate signals get names that contain a `.` so that they cannot clash
+/// with names in the source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Gate {
+    pub output: String,
+    pub kind: GateKind,
+    pub inputs: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GateKind {
+    /// Copies its input
+    Buf,
+    Not,
+    And,
+    Or,
+    Nand,
+    Nor,
+    Xor,
+    Xnor,
+
+    /// Level-triggered latch with the inputs `data` and `enable`. The output follows `data` while
+    /// `enable` is on, and holds its value while `enable` is off.
+    DLatch,
+}
+
+impl GateKind {
+    fn from_name(name: &str) -> Option<Self> {
+        let kind = match name {
+            "buf" => GateKind::Buf,
+            "not" => GateKind::Not,
+            "and" => GateKind::And,
+            "or" => GateKind::Or,
+            "nand" => GateKind::Nand,
+            "nor" => GateKind::Nor,
+            "xor" => GateKind::Xor,
+            "xnor" => GateKind::Xnor,
+            "dlatch" => GateKind::DLatch,
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    /// Returns true if a gate of this kind can take the given number of inputs.
+    fn accepts_inputs(self, count: usize) -> bool {
+        match self {
+            GateKind::Buf | GateKind::Not => count == 1,
+            GateKind::DLatch => count == 2,
+            GateKind::Xor | GateKind::Xnor => count >= 2,
+            GateKind::And | GateKind::Or | GateKind::Nand | GateKind::Nor => count >= 1,
+        }
+    }
+}
+
+impl fmt::Display for GateKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            GateKind::Buf => "buf",
+            GateKind::Not => "not",
+            GateKind::And => "and",
+            GateKind::Or => "or",
+            GateKind::Nand => "nand",
+            GateKind::Nor => "nor",
+            GateKind::Xor => "xor",
+            GateKind::Xnor => "xnor",
+            GateKind::DLatch => "dlatch",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Netlist {
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut netlist = Netlist::default();
+        let mut declared_inputs = false;
+        let mut declared_outputs = false;
+        let mut signals = vec![];
+        for (index, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let context = || format!("invalid statement on line {}", index + 1);
+            let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            match keyword {
+                "input" => {
+                    declared_inputs = true;
+                    netlist
+                        .inputs
+                        .extend(parse_names(rest).with_context(context)?);
+                }
+                "output" => {
+                    declared_outputs = true;
+                    netlist
+                        .outputs
+                        .extend(parse_names(rest).with_context(context)?);
+                }
+                _ => parse_assignment(&mut netlist, &mut signals, line).with_context(context)?,
+            }
+        }
+        netlist.check(&signals, declared_inputs, declared_outputs)?;
+        Ok(netlist)
+    }
+
+    /// Parses a single expression with a made up output signal named `out`.
+    pub fn from_expression(expression: &str) -> Result<Self> {
+        Self::parse(&format!("out = {}", expression))
+    }
+
+    /// Checks that every signal is assigned once, and fills in inputs and outputs that were not
+    /// declared. `signals` lists the signals used in expressions in the order that they appear.
+    fn check(
+        &mut self,
+        signals: &[String],
+        declared_inputs: bool,
+        declared_outputs: bool,
+    ) -> Result<()> {
+        let mut assigned = HashSet::new();
+        for gate in self.gates.iter() {
+            if !assigned.insert(gate.output.as_str()) {
+                return Err(anyhow!("{} is assigned more than once", gate.output));
+            }
+            if self.inputs.contains(&gate.output) {
+                return Err(anyhow!(
+                    "{} is an input, and cannot be assigned",
+                    gate.output
+                ));
+            }
+        }
+        for signal in signals {
+            if assigned.contains(signal.as_str()) || self.inputs.contains(signal) {
+                continue;
+            }
+            if declared_inputs {
+                return Err(anyhow!("{} is not an input, and is never assigned", signal));
+            }
+            self.inputs.push(signal.clone());
+        }
+        if !declared_outputs {
+            self.outputs = self
+                .gates
+                .iter()
+                .map(|gate| &gate.output)
+                .filter(|output| !output.contains('.') && !signals.contains(output))
+                .cloned()
+                .collect();
+        }
+        if self.outputs.is_empty() {
+            return Err(anyhow!("netlist has no outputs"));
+        }
+        for output in self.outputs.iter() {
+            if !assigned.contains(output.as_str()) && !self.inputs.contains(output) {
+                return Err(anyhow!("output {} is never assigned", output));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_names(input: &str) -> Result<Vec<String>> {
+    input
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            if is_name(name) {
+                Ok(name.to_owned())
+            } else {
+                Err(anyhow!("invalid signal name, {}", name))
+            }
+        })
+        .collect()
+}
+
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_assignment(netlist: &mut Netlist, signals: &mut Vec<String>, line: &str) -> Result<()> {
+    let (target, expression) = line.split_once('=').ok_or(anyhow!(
+        "expected an assignment, or an input or output list"
+    ))?;
+    let target = target.trim();
+    if !is_name(target) {
+        return Err(anyhow!("invalid signal name, {}", target));
+    }
+    let mut parser = Parser {
+        chars: expression.chars().peekable(),
+        signals,
+    };
+    let expr = parser.expression()?;
+    parser.skip_whitespace();
+    if let Some(c) = parser.chars.next() {
+        return Err(anyhow!("unexpected '{}'", c));
+    }
+    let mut temporaries = 0;
+    let (kind, inputs) = match expr {
+        Expr::Signal(name) => (GateKind::Buf, vec![name]),
+        Expr::Gate(kind, args) => {
+            let inputs = args
+                .into_iter()
+                .map(|arg| flatten(netlist, target, &mut temporaries, arg))
+                .collect();
+            (kind, inputs)
+        }
+    };
+    netlist.gates.push(Gate {
+        output: target.to_owned(),
+        kind,
+        inputs,
+    });
+    Ok(())
+}
+
+/// Adds a gate for each nested expression, and returns the name of the signal that holds the
+/// value of the expression.
+fn flatten(netlist: &mut Netlist, target: &str, temporaries: &mut usize, expr: Expr) -> String {
+    match expr {
+        Expr::Signal(name) => name,
+        Expr::Gate(kind, args) => {
+            let inputs = args
+                .into_iter()
+                .map(|arg| flatten(netlist, target, temporaries, arg))
+                .collect();
+            *temporaries += 1;
+            let output = format!("{}.{}", target, temporaries);
+            netlist.gates.push(Gate {
+                output: output.clone(),
+                kind,
+                inputs,
+            });
+            output
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Expr {
+    Signal(String),
+    Gate(GateKind, Vec<Expr>),
+}
+
+/// Recursive descent parser for expressions. Signal names are added to `signals` as they are
+/// parsed.
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    signals: &'a mut Vec<String>,
+}
+
+impl<'a> Parser<'a> {
+    fn expression(&mut self) -> Result<Expr> {
+        self.chain('|', GateKind::Or, Self::xor_expression)
+    }
+
+    fn xor_expression(&mut self) -> Result<Expr> {
+        self.chain('^', GateKind::Xor, Self::and_expression)
+    }
+
+    fn and_expression(&mut self) -> Result<Expr> {
+        self.chain('&', GateKind::And, Self::unary)
+    }
+
+    /// Parses operands separated by the given operator into a single gate with every operand as
+    /// an input.
+    fn chain(
+        &mut self,
+        operator: char,
+        kind: GateKind,
+        operand: fn(&mut Self) -> Result<Expr>,
+    ) -> Result<Expr> {
+        let mut operands = vec![operand(self)?];
+        while self.eat(operator) {
+            operands.push(operand(self)?);
+        }
+        if operands.len() == 1 {
+            Ok(operands.remove(0))
+        } else {
+            Ok(Expr::Gate(kind, operands))
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.eat('!') || self.eat('~') {
+            return Ok(Expr::Gate(GateKind::Not, vec![self.unary()?]));
+        }
+        if self.eat('(') {
+            let expr = self.expression()?;
+            self.expect(')')?;
+            return Ok(expr);
+        }
+        let name = self.name()?;
+        if !self.eat('(') {
+            self.signals.push(name.clone());
+            return Ok(Expr::Signal(name));
+        }
+        let kind = GateKind::from_name(&name).ok_or(anyhow!("unknown gate, {}", name))?;
+        let mut args = vec![self.expression()?];
+        while self.eat(',') {
+            args.push(self.expression()?);
+        }
+        self.expect(')')?;
+        if !kind.accepts_inputs(args.len()) {
+            return Err(anyhow!("{} cannot take {} inputs", kind, args.len()));
+        }
+        Ok(Expr::Gate(kind, args))
+    }
+
+    fn name(&mut self) -> Result<String> {
+        self.skip_whitespace();
+        let mut name = String::new();
+        while let Some(c) = self
+            .chars
+            .next_if(|c| c.is_ascii_alphanumeric() || *c == '_')
+        {
+            name.push(c);
+        }
+        if is_name(&name) {
+            Ok(name)
+        } else {
+            match self.chars.peek() {
+                Some(c) => Err(anyhow!("unexpected '{}'", c)),
+                None => Err(anyhow!("unexpected end of expression")),
+            }
+        }
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        self.skip_whitespace();
+        self.chars.next_if_eq(&expected).is_some()
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(anyhow!("expected '{}'", expected))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Gate, GateKind, Netlist};
+
+    fn gate(output: &str, kind: GateKind, inputs: &[&str]) -> Gate {
+        Gate {
+            output: output.to_owned(),
+            kind,
+            inputs: inputs.iter().map(|input| input.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn parses_a_netlist() {
+        let netlist = Netlist::parse(
+            "# half adder\n\
+             input a b\n\
+             output sum carry\n\
+             sum = a ^ b\n\
+             carry = and(a, b)\n",
+        )
+        .unwrap();
+        assert_eq!(netlist.inputs, vec!["a", "b"]);
+        assert_eq!(netlist.outputs, vec!["sum", "carry"]);
+        assert_eq!(
+            netlist.gates,
+            vec![
+                gate("sum", GateKind::Xor, &["a", "b"]),
+                gate("carry", GateKind::And, &["a", "b"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_nested_expressions_into_gates() {
+        let netlist = Netlist::from_expression("a & !(b | c) & d").unwrap();
+        assert_eq!(netlist.inputs, vec!["a", "b", "c", "d"]);
+        assert_eq!(netlist.outputs, vec!["out"]);
+        assert_eq!(
+            netlist.gates,
+            vec![
+                gate("out.1", GateKind::Or, &["b", "c"]),
+                gate("out.2", GateKind::Not, &["out.1"]),
+                gate("out", GateKind::And, &["a", "out.2", "d"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_invalid_netlists() {
+        let error = |input: &str| format!("{:#}", Netlist::parse(input).unwrap_err());
+        assert_eq!(
+            error("input a\nq = a & b"),
+            "b is not an input, and is never assigned"
+        );
+        assert_eq!(error("q = a\nq = b"), "q is assigned more than once");
+        assert_eq!(
+            error("q = not(a, b)"),
+            "invalid statement on line 1: not cannot take 2 inputs"
+        );
+        assert_eq!(
+            error("q = a &"),
+            "invalid statement on line 1: unexpected end of expression"
+        );
+    }
+}
@@ -0,0 +1,402 @@
+// Blocks in a world state are split into cubic chunks. Each chunk lists the distinct block states
+// that it holds in a palette, and stores a small palette index for each position, packed into as
+// few bits as the palette allows. Chunks are reference counted so that cloning a world state, which
+// the timeline does for every simulated tick, shares every chunk until one of the clones changes
+// it.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use crate::{block_state::BlockState, int_vec3::IntVec3};
+
+/// Width, height, and depth of a chunk.
+const CHUNK_SIZE: i32 = 16;
+
+const CHUNK_VOLUME: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+/// Bits per position in a new chunk. Chunks widen their entries when the palette outgrows them.
+const MIN_BITS: u32 = 4;
+
+/// Blocks keyed by position with constant time lookup.
+#[derive(Clone, Default)]
+pub struct BlockStorage {
+    /// Chunks keyed by chunk coordinates. Chunks without any blocks are removed.
+    chunks: HashMap<IntVec3, Arc<Chunk>>,
+    len: usize,
+}
+
+impl BlockStorage {
+    pub fn get(&self, pos: IntVec3) -> Option<&BlockState> {
+        let (key, index) = chunk_index(pos);
+        self.chunks.get(&key)?.get(index)
+    }
+
+    pub fn contains(&self, pos: IntVec3) -> bool {
+        self.get(pos).is_some()
+    }
+
+    /// Sets the block at a position, and returns the block that was there before.
+    pub fn insert(&mut self, pos: IntVec3, state: BlockState) -> Option<BlockState> {
+        let (key, index) = chunk_index(pos);
+        let chunk = self
+            .chunks
+            .entry(key)
+            .or_insert_with(|| Arc::new(Chunk::new()));
+        let previous = Arc::make_mut(chunk).set(index, Some(state));
+        if previous.is_none() {
+            self.len += 1;
+        }
+        previous
+    }
+
+    pub fn remove(&mut self, pos: IntVec3) -> Option<BlockState> {
+        let (key, index) = chunk_index(pos);
+        let chunk = self.chunks.get_mut(&key)?;
+        // Check first so that a shared chunk is not copied when there is nothing to remove.
+        chunk.get(index)?;
+        let chunk = Arc::make_mut(chunk);
+        let previous = chunk.set(index, None);
+        if chunk.len == 0 {
+            self.chunks.remove(&key);
+        }
+        self.len -= 1;
+        previous
+    }
+
+    /// Lists positions that hold different blocks in the two storages. Chunks that are still
+    /// shared are skipped without comparing their contents.
+    pub fn changed_positions(&self, other: &BlockStorage) -> Vec<IntVec3> {
+        let keys: BTreeSet<IntVec3> = self
+            .chunks
+            .keys()
+            .chain(other.chunks.keys())
+            .copied()
+            .collect();
+        let mut changed = vec![];
+        for key in keys {
+            let (ours, theirs) = (self.chunks.get(&key), other.chunks.get(&key));
+            if let (Some(ours), Some(theirs)) = (ours, theirs) {
+                if Arc::ptr_eq(ours, theirs) {
+                    continue;
+                }
+            }
+            changed.extend(
+                (0..CHUNK_VOLUME)
+                    .filter(|index| {
+                        ours.and_then(|chunk| chunk.get(*index))
+                            != theirs.and_then(|chunk| chunk.get(*index))
+                    })
+                    .map(|index| position(key, index)),
+            );
+        }
+        changed
+    }
+
+    /// Iterates over every block. Chunks are visited in order of their coordinates, and positions
+    /// within a chunk bottom layer first, so the order only depends on which blocks are stored.
+    pub fn iter(&self) -> impl Iterator<Item = (IntVec3, &BlockState)> {
+        let mut keys: Vec<IntVec3> = self.chunks.keys().copied().collect();
+        keys.sort_by_key(|key| key.layer_order_key());
+        keys.into_iter().flat_map(move |key| {
+            self.chunks[&key]
+                .iter()
+                .map(move |(index, state)| (position(key, index), state))
+        })
+    }
+}
+
+/// Storages are equal if they hold the same blocks, regardless of how each chunk's palette is
+/// laid out. Chunks that are still shared are equal without comparing their contents.
+impl PartialEq for BlockStorage {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len
+            && self.chunks.len() == other.chunks.len()
+            && self.chunks.iter().all(|(key, chunk)| {
+                other.chunks.get(key).map_or(false, |other| {
+                    Arc::ptr_eq(chunk, other) || **chunk == **other
+                })
+            })
+    }
+}
+
+impl Hash for BlockStorage {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for (pos, block) in self.iter() {
+            pos.hash(state);
+            block.hash(state);
+        }
+    }
+}
+
+impl fmt::Debug for BlockStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Splits a position into the coordinates of its chunk and its index within the chunk.
+fn chunk_index(pos: IntVec3) -> (IntVec3, usize) {
+    let key = IntVec3::new(
+        pos.x.div_euclid(CHUNK_SIZE),
+        pos.y.div_euclid(CHUNK_SIZE),
+        pos.z.div_euclid(CHUNK_SIZE),
+    );
+    let local = pos - key * CHUNK_SIZE;
+    let index = (local.y * CHUNK_SIZE + local.x) * CHUNK_SIZE + local.z;
+    (key, index as usize)
+}
+
+fn position(key: IntVec3, index: usize) -> IntVec3 {
+    let index = index as i32;
+    let local = IntVec3::new(
+        index / CHUNK_SIZE % CHUNK_SIZE,
+        index / (CHUNK_SIZE * CHUNK_SIZE),
+        index % CHUNK_SIZE,
+    );
+    key * CHUNK_SIZE + local
+}
+
+#[derive(Clone, Debug)]
+struct Chunk {
+    /// Block states in use in this chunk. A cell value of `i` refers to `palette[i - 1]`, and 0
+    /// is an empty position.
+    palette: Vec<PaletteEntry>,
+    cells: PackedArray,
+
+    /// Number of occupied positions
+    len: usize,
+}
+
+#[derive(Clone, Debug)]
+struct PaletteEntry {
+    state: BlockState,
+
+    /// Number of positions with this state. Entries that drop to zero are reused.
+    count: usize,
+}
+
+impl Chunk {
+    fn new() -> Self {
+        Chunk {
+            palette: vec![],
+            cells: PackedArray::new(MIN_BITS),
+            len: 0,
+        }
+    }
+
+    fn get(&self, index: usize) -> Option<&BlockState> {
+        match self.cells.get(index) {
+            0 => None,
+            value => Some(&self.palette[value - 1].state),
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (usize, &BlockState)> {
+        (0..CHUNK_VOLUME).filter_map(|index| Some((index, self.get(index)?)))
+    }
+
+    /// Sets or clears a position, and returns the state that was there before.
+    fn set(&mut self, index: usize, state: Option<BlockState>) -> Option<BlockState> {
+        // The new state is looked up before the old one is released so that a palette entry that
+        // is about to drop to zero is not handed out again.
+        let value = match state {
+            Some(state) => self.palette_value(state),
+            None => 0,
+        };
+        let previous = self.cells.get(index);
+        self.cells.set(index, value);
+        if value != 0 {
+            self.palette[value - 1].count += 1;
+            self.len += 1;
+        }
+        if previous == 0 {
+            return None;
+        }
+        let entry = &mut self.palette[previous - 1];
+        entry.count -= 1;
+        self.len -= 1;
+        Some(entry.state.clone())
+    }
+
+    /// Finds or adds a palette entry for the given state, and returns the cell value for it.
+    fn palette_value(&mut self, state: BlockState) -> usize {
+        if let Some(index) = self.palette.iter().position(|entry| entry.state == state) {
+            return index + 1;
+        }
+        if let Some(index) = self.palette.iter().position(|entry| entry.count == 0) {
+            self.palette[index] = PaletteEntry { state, count: 0 };
+            return index + 1;
+        }
+        self.palette.push(PaletteEntry { state, count: 0 });
+        let bits = bits_for(self.palette.len());
+        if bits > self.cells.bits {
+            self.cells = self.cells.widened(bits);
+        }
+        self.palette.len()
+    }
+}
+
+/// Chunks are equal if every position holds the same state.
+impl PartialEq for Chunk {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && (0..CHUNK_VOLUME).all(|index| self.get(index) == other.get(index))
+    }
+}
+
+/// Number of bits needed to store every value up to and including `max`.
+fn bits_for(max: usize) -> u32 {
+    (usize::BITS - max.leading_zeros()).max(MIN_BITS)
+}
+
+/// Fixed size entries packed into 64 bit words. Entries do not straddle words, so some bits at
+/// the end of each word may go unused.
+#[derive(Clone, Debug)]
+struct PackedArray {
+    bits: u32,
+    words: Vec<u64>,
+}
+
+impl PackedArray {
+    fn new(bits: u32) -> Self {
+        let per_word = (u64::BITS / bits) as usize;
+        PackedArray {
+            bits,
+            words: vec![0; (CHUNK_VOLUME + per_word - 1) / per_word],
+        }
+    }
+
+    fn get(&self, index: usize) -> usize {
+        let (word, shift) = self.locate(index);
+        ((self.words[word] >> shift) & self.mask()) as usize
+    }
+
+    fn set(&mut self, index: usize, value: usize) {
+        let (word, shift) = self.locate(index);
+        let mask = self.mask();
+        self.words[word] = (self.words[word] & !(mask << shift)) | ((value as u64 & mask) << shift);
+    }
+
+    /// Copies every entry into an array with more bits per entry.
+    fn widened(&self, bits: u32) -> Self {
+        let mut widened = PackedArray::new(bits);
+        for index in 0..CHUNK_VOLUME {
+            widened.set(index, self.get(index));
+        }
+        widened
+    }
+
+    fn locate(&self, index: usize) -> (usize, u32) {
+        let per_word = (u64::BITS / self.bits) as usize;
+        (index / per_word, (index % per_word) as u32 * self.bits)
+    }
+
+    fn mask(&self) -> u64 {
+        (1 << self.bits) - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{block_state::BlockState, int_vec3::IntVec3};
+
+    use super::{chunk_index, position, BlockStorage};
+
+    fn wire(power: i32) -> BlockState {
+        BlockState::new("redstone_wire", &format!("power={}", power))
+    }
+
+    #[test]
+    fn maps_positions_to_chunks_and_back() {
+        for pos in [
+            IntVec3::ZERO,
+            IntVec3::new(15, 16, 17),
+            IntVec3::new(-1, -16, -17),
+            IntVec3::new(100, 3, -42),
+        ] {
+            let (key, index) = chunk_index(pos);
+            assert_eq!(position(key, index), pos);
+        }
+        assert_eq!(chunk_index(IntVec3::new(-1, 0, 0)).0, IntVec3::NEG_X);
+    }
+
+    #[test]
+    fn stores_blocks_across_chunks() {
+        let mut storage = BlockStorage::default();
+        let positions = [IntVec3::new(-3, 0, 5), IntVec3::new(20, 1, 40)];
+        for pos in positions {
+            assert_eq!(storage.insert(pos, wire(0)), None);
+        }
+        assert_eq!(storage.insert(positions[0], wire(3)), Some(wire(0)));
+        assert_eq!(storage.get(positions[0]), Some(&wire(3)));
+        assert!(!storage.contains(IntVec3::new(-3, 1, 5)));
+        assert_eq!(storage.iter().count(), 2);
+
+        assert_eq!(storage.remove(positions[1]), Some(wire(0)));
+        assert_eq!(storage.remove(positions[1]), None);
+        assert_eq!(storage.chunks.len(), 1);
+        assert_eq!(
+            storage.iter().collect::<Vec<_>>(),
+            vec![(positions[0], &wire(3))]
+        );
+    }
+
+    #[test]
+    fn widens_cells_when_the_palette_grows() {
+        let mut storage = BlockStorage::default();
+        for x in 0..16 {
+            for z in 0..3 {
+                storage.insert(IntVec3::new(x, 0, z), wire(x + z * 16));
+            }
+        }
+        for x in 0..16 {
+            for z in 0..3 {
+                assert_eq!(storage.get(IntVec3::new(x, 0, z)), Some(&wire(x + z * 16)));
+            }
+        }
+        // Removing blocks frees palette entries for new states.
+        storage.remove(IntVec3::ZERO);
+        storage.insert(IntVec3::X, wire(99));
+        let chunk = &storage.chunks[&IntVec3::ZERO];
+        assert_eq!(chunk.palette.len(), 48);
+        assert_eq!(chunk.len, 47);
+    }
+
+    #[test]
+    fn shares_chunks_between_clones_until_changed() {
+        let mut storage = BlockStorage::default();
+        storage.insert(IntVec3::ZERO, wire(0));
+        storage.insert(IntVec3::new(16, 0, 0), wire(0));
+        let mut clone = storage.clone();
+        clone.insert(IntVec3::ZERO, wire(1));
+        assert!(!Arc::ptr_eq(
+            &storage.chunks[&IntVec3::ZERO],
+            &clone.chunks[&IntVec3::ZERO]
+        ));
+        assert!(Arc::ptr_eq(
+            &storage.chunks[&IntVec3::X],
+            &clone.chunks[&IntVec3::X]
+        ));
+        assert_eq!(storage.get(IntVec3::ZERO), Some(&wire(0)));
+        assert_ne!(storage, clone);
+        assert_eq!(storage.changed_positions(&clone), vec![IntVec3::ZERO]);
+    }
+
+    #[test]
+    fn compares_contents_regardless_of_history() {
+        let mut a = BlockStorage::default();
+        a.insert(IntVec3::ZERO, wire(5));
+        a.insert(IntVec3::X, wire(0));
+        let mut b = BlockStorage::default();
+        b.insert(IntVec3::X, wire(7));
+        b.insert(IntVec3::ZERO, wire(5));
+        b.insert(IntVec3::X, wire(0));
+        assert_eq!(a, b);
+        assert_eq!(format!("{:?}", a), format!("{:?}", b));
+    }
+}
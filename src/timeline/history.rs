@@ -0,0 +1,248 @@
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeSet, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use crate::int_vec3::IntVec3;
+
+use super::{
+    apply_inputs_at, simulation::next_tick, world_state::WorldDelta, InputEvent, InputSchedule,
+    TimeIndex, WorldState,
+};
+
+/// A span of ticks that repeats forever once the simulation reaches it, because no more inputs
+/// are applied after the span starts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cycle {
+    /// First tick of the repeating span
+    pub start: TimeIndex,
+
+    /// Number of ticks before the world returns to the state at `start`. A period of 1 means that
+    /// the world has reached a steady state.
+    pub period: TimeIndex,
+}
+
+/// Number of ticks between full copies of the world in a history. States between keyframes are
+/// rebuilt by applying the changes for each tick to the keyframe before them.
+const KEYFRAME_INTERVAL: usize = 32;
+
+/// Simulated world states for consecutive ticks starting from the design at tick 0. Each state is
+/// hashed as it is simulated. When a state repeats an earlier one the simulation stops, and later
+/// ticks are extrapolated from the one period that is stored.
+///
+/// Only every `KEYFRAME_INTERVAL`th state is stored in full. Other ticks store the positions that
+/// changed since the tick before.
+#[derive(Clone, Debug, PartialEq)]
+pub struct History {
+    /// Full states for every `KEYFRAME_INTERVAL`th stored tick, starting with the design.
+    keyframes: Vec<WorldState>,
+
+    /// Changes from the tick before for every stored tick after tick 0, so `deltas[t - 1]` leads
+    /// to tick `t`. If there is a cycle the stored ticks end with one full period.
+    deltas: Vec<WorldDelta>,
+
+    /// The last stored state, kept in full so that the simulation can continue from it.
+    latest: WorldState,
+
+    /// Ticks grouped by the hash of the state at each tick
+    fingerprints: HashMap<u64, Vec<TimeIndex>>,
+
+    cycle: Option<Cycle>,
+
+    /// Changes from the last tick of the cycle back to its first tick
+    cycle_delta: Option<WorldDelta>,
+
+    /// Latest tick that has been simulated or extrapolated
+    horizon: TimeIndex,
+}
+
+impl History {
+    pub fn new(design: WorldState) -> Self {
+        let mut history = History {
+            keyframes: vec![],
+            deltas: vec![],
+            latest: design.clone(),
+            fingerprints: HashMap::new(),
+            cycle: None,
+            cycle_delta: None,
+            horizon: 0,
+        };
+        history.record(design);
+        history
+    }
+
+    /// The world at tick 0.
+    pub fn design(&self) -> &WorldState {
+        &self.keyframes[0]
+    }
+
+    /// Rebuilds the world at the given tick from the nearest keyframe.
+    pub fn state_at(&self, time: TimeIndex) -> Option<WorldState> {
+        if time < 0 || time > self.horizon {
+            return None;
+        }
+        Some(self.stored_state(self.index_of(time)))
+    }
+
+    pub fn last_simulated_time(&self) -> TimeIndex {
+        self.horizon
+    }
+
+    pub fn cycle(&self) -> Option<Cycle> {
+        self.cycle
+    }
+
+    /// Positions where a block changed on any tick after `from` up to and including `to`, taken
+    /// from the stored changes without rebuilding either state. A position that changed and then
+    /// changed back is included. Ticks past the horizon are ignored.
+    pub fn changed_between(&self, from: TimeIndex, to: TimeIndex) -> BTreeSet<IntVec3> {
+        let (from, to) = (from.min(to).max(0), from.max(to).min(self.horizon));
+        // Past one full period a cycle only repeats changes that were already collected.
+        let to = match self.cycle {
+            Some(cycle) => to.min(from.max(cycle.start) + cycle.period),
+            None => to,
+        };
+        let mut changed = BTreeSet::new();
+        for time in from + 1..=to {
+            let index = self.index_of(time);
+            // Only ticks that wrap around from the end of the cycle use the change back to its
+            // start. The first time the cycle starts is reached from the tick before it.
+            let delta = match (self.cycle, &self.cycle_delta) {
+                (Some(cycle), Some(cycle_delta))
+                    if time > cycle.start && index == cycle.start as usize =>
+                {
+                    cycle_delta
+                }
+                _ => &self.deltas[index - 1],
+            };
+            changed.extend(delta.positions());
+        }
+        changed
+    }
+
+    /// Simulates ticks after the horizon up to and including the given tick, applying inputs as
+    /// the simulation passes the tick for each. Once a cycle is found later ticks do not need to
+    /// be simulated.
+    pub fn simulate_to(
+        &mut self,
+        schedule: &InputSchedule,
+        inputs: &[(TimeIndex, InputEvent)],
+        time: TimeIndex,
+    ) {
+        // A repeated state only starts a cycle if no inputs are applied from that tick on.
+        let last_input = schedule
+            .last_tick()
+            .into_iter()
+            .chain(inputs.iter().map(|(t, _)| *t))
+            .max()
+            .unwrap_or(-1);
+        while self.horizon < time {
+            if self.cycle.is_some() {
+                self.horizon = time;
+                return;
+            }
+            let mut world = self.latest.clone();
+            apply_inputs_at(&mut world, schedule, inputs, self.horizon);
+            let next = next_tick(&world);
+            let next_time = self.horizon + 1;
+            match self.find_repeat(&next, last_input) {
+                Some(start) => {
+                    self.cycle = Some(Cycle {
+                        start,
+                        period: next_time - start,
+                    });
+                    self.cycle_delta = Some(self.latest.delta_to(&next));
+                }
+                None => self.record(next),
+            }
+            self.horizon = next_time;
+        }
+    }
+
+    /// Discards states after the given tick. If the tick is inside a cycle the states up to that
+    /// tick are filled in from the cycle so that the simulation can continue from there with
+    /// different inputs.
+    pub fn truncate(&mut self, time: TimeIndex) {
+        let time = time.max(0);
+        if self.cycle.is_some() {
+            let end = time.min(self.horizon);
+            let extrapolated: Vec<_> = (self.stored_len() as TimeIndex..=end)
+                .map(|t| self.stored_state(self.index_of(t)))
+                .collect();
+            self.cycle = None;
+            self.cycle_delta = None;
+            for world in extrapolated {
+                self.record(world);
+            }
+        }
+        if (time as usize) < self.stored_len() - 1 {
+            self.latest = self.stored_state(time as usize);
+            self.deltas.truncate(time as usize);
+            self.keyframes
+                .truncate(time as usize / KEYFRAME_INTERVAL + 1);
+        }
+        self.fingerprints.retain(|_, ticks| {
+            ticks.retain(|t| *t <= time);
+            !ticks.is_empty()
+        });
+        self.horizon = self.horizon.min(time);
+    }
+
+    fn index_of(&self, time: TimeIndex) -> usize {
+        let time = match self.cycle {
+            Some(cycle) if time >= cycle.start => cycle.start + (time - cycle.start) % cycle.period,
+            _ => time,
+        };
+        time as usize
+    }
+
+    /// Number of ticks with a stored state
+    fn stored_len(&self) -> usize {
+        self.deltas.len() + 1
+    }
+
+    fn stored_state(&self, index: usize) -> WorldState {
+        if index == self.stored_len() - 1 {
+            return self.latest.clone();
+        }
+        let keyframe = index / KEYFRAME_INTERVAL;
+        let mut world = self.keyframes[keyframe].clone();
+        for delta in &self.deltas[keyframe * KEYFRAME_INTERVAL..index] {
+            world.apply_delta(delta);
+        }
+        world
+    }
+
+    fn record(&mut self, world: WorldState) {
+        let time = self.stored_len() as TimeIndex;
+        let time = if self.keyframes.is_empty() {
+            0
+        } else {
+            self.deltas.push(self.latest.delta_to(&world));
+            time
+        };
+        self.fingerprints
+            .entry(fingerprint(&world))
+            .or_default()
+            .push(time);
+        if time as usize % KEYFRAME_INTERVAL == 0 {
+            self.keyframes.push(world.clone());
+        }
+        self.latest = world;
+    }
+
+    /// Finds the earliest tick after `after` with the same state as the given world.
+    fn find_repeat(&self, world: &WorldState, after: TimeIndex) -> Option<TimeIndex> {
+        self.fingerprints
+            .get(&fingerprint(world))?
+            .iter()
+            .copied()
+            .find(|t| *t > after && self.stored_state(*t as usize) == *world)
+    }
+}
+
+fn fingerprint(world: &WorldState) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    world.hash(&mut hasher);
+    hasher.finish()
+}
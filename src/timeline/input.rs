@@ -0,0 +1,31 @@
+use crate::int_vec3::IntVec3;
+
+/// An interaction from outside of the simulation that changes the world at a specific tick. For
+/// example a player flipping a lever.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputEvent {
+    /// Flip a lever to the opposite of its current state.
+    ToggleLever(IntVec3),
+
+    /// Set a lever to on (`true`), or off (`false`) regardless of its current state.
+    SetLever(IntVec3, bool),
+}
+
+impl InputEvent {
+    /// Position of the block that the event interacts with.
+    pub fn pos(&self) -> IntVec3 {
+        match self {
+            InputEvent::ToggleLever(pos) => *pos,
+            InputEvent::SetLever(pos, _) => *pos,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvalidInput {
+    /// Inputs cannot be placed before the first tick of the timeline.
+    BeforeStart,
+
+    /// The event requires a lever, but there is no lever at the given position.
+    NotALever(IntVec3),
+}
@@ -0,0 +1,183 @@
+use std::fmt;
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::int_vec3::IntVec3;
+
+use super::{input::InputEvent, TimeIndex};
+
+/// A list of inputs that is stored with a design, and that is replayed every time the design is
+/// simulated. A schedule documents how a circuit is meant to be used, and makes it possible to
+/// check that a circuit still behaves the same way after it is modified.
+///
+/// Schedules have a text format with one entry per line. For example this schedule toggles the
+/// lever at x=3, y=0, z=4 at ticks 0, 20, and 40, and switches on the lever at 5,0,4 at tick 10:
+///
+/// ```text
+/// toggle 3 0 4 @ 0, 20, 40
+/// on 5 0 4 @ 10
+/// ```
+///
+/// Blank lines, and lines that start with `#` are ignored.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InputSchedule {
+    entries: Vec<ScheduleEntry>,
+}
+
+/// An input event that is repeated at each of a list of ticks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScheduleEntry {
+    pub event: InputEvent,
+    pub ticks: Vec<TimeIndex>,
+}
+
+impl InputSchedule {
+    pub fn new(entries: Vec<ScheduleEntry>) -> Self {
+        InputSchedule { entries }
+    }
+
+    pub fn entries(&self) -> &[ScheduleEntry] {
+        &self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn push(&mut self, event: InputEvent, ticks: Vec<TimeIndex>) {
+        self.entries.push(ScheduleEntry { event, ticks });
+    }
+
+    /// Events that apply at the given tick in the order that they appear in the schedule.
+    pub fn events_at(&self, time: TimeIndex) -> impl Iterator<Item = &InputEvent> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.ticks.contains(&time))
+            .map(|entry| &entry.event)
+    }
+
+    /// The latest tick with a scheduled event.
+    pub fn last_tick(&self) -> Option<TimeIndex> {
+        self.entries
+            .iter()
+            .flat_map(|entry| entry.ticks.iter().copied())
+            .max()
+    }
+
+    pub fn parse(input: &str) -> Result<Self> {
+        let entries = input
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .map(|(index, line)| {
+                parse_entry(line)
+                    .with_context(|| format!("invalid schedule entry on line {}", index + 1))
+            })
+            .collect::<Result<_>>()?;
+        Ok(InputSchedule { entries })
+    }
+}
+
+fn parse_entry(line: &str) -> Result<ScheduleEntry> {
+    let (action, ticks) = line
+        .split_once('@')
+        .ok_or(anyhow!("expected '@' followed by a list of ticks"))?;
+    let mut words = action.split_whitespace();
+    let verb = words.next().ok_or(anyhow!("missing action"))?;
+    let coords = words
+        .map(|word| word.parse::<i32>())
+        .collect::<Result<Vec<_>, _>>()
+        .context("position must be three integers")?;
+    let pos = match coords[..] {
+        [x, y, z] => IntVec3::new(x, y, z),
+        _ => return Err(anyhow!("position must be three integers")),
+    };
+    let event = match verb {
+        "toggle" => InputEvent::ToggleLever(pos),
+        "on" => InputEvent::SetLever(pos, true),
+        "off" => InputEvent::SetLever(pos, false),
+        _ => return Err(anyhow!("unknown action, {}", verb)),
+    };
+    let ticks = ticks
+        .split(',')
+        .map(|tick| tick.trim().parse::<TimeIndex>())
+        .collect::<Result<Vec<_>, _>>()
+        .context("ticks must be a comma-separated list of integers")?;
+    if ticks.iter().any(|tick| *tick < 0) {
+        return Err(anyhow!("ticks may not be negative"));
+    }
+    Ok(ScheduleEntry { event, ticks })
+}
+
+impl fmt::Display for InputSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in self.entries.iter() {
+            let (verb, pos) = match entry.event {
+                InputEvent::ToggleLever(pos) => ("toggle", pos),
+                InputEvent::SetLever(pos, true) => ("on", pos),
+                InputEvent::SetLever(pos, false) => ("off", pos),
+            };
+            let ticks: Vec<String> = entry.ticks.iter().map(|tick| tick.to_string()).collect();
+            writeln!(
+                f,
+                "{} {} {} {} @ {}",
+                verb,
+                pos.x,
+                pos.y,
+                pos.z,
+                ticks.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{int_vec3::IntVec3, timeline::InputEvent};
+
+    use super::{InputSchedule, ScheduleEntry};
+
+    #[test]
+    fn parses_a_schedule() {
+        let schedule = InputSchedule::parse(
+            "# clock input\n\
+             toggle 3 0 4 @ 0, 20, 40\n\
+             \n\
+             on 5 0 4 @ 10\n",
+        )
+        .unwrap();
+        assert_eq!(
+            schedule,
+            InputSchedule::new(vec![
+                ScheduleEntry {
+                    event: InputEvent::ToggleLever(IntVec3::new(3, 0, 4)),
+                    ticks: vec![0, 20, 40],
+                },
+                ScheduleEntry {
+                    event: InputEvent::SetLever(IntVec3::new(5, 0, 4), true),
+                    ticks: vec![10],
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn formats_a_schedule_that_can_be_parsed_again() {
+        let mut schedule = InputSchedule::default();
+        schedule.push(
+            InputEvent::SetLever(IntVec3::new(1, 2, 3), false),
+            vec![4, 8],
+        );
+        let text = schedule.to_string();
+        assert_eq!(text, "off 1 2 3 @ 4, 8\n");
+        assert_eq!(InputSchedule::parse(&text).unwrap(), schedule);
+    }
+
+    #[test]
+    fn rejects_an_entry_without_ticks() {
+        let result = InputSchedule::parse("toggle 3 0 4");
+        assert!(result.is_err());
+    }
+}
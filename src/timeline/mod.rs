@@ -1,21 +1,65 @@
+mod block_storage;
+mod history;
+mod input;
+mod input_schedule;
+mod simulation;
+mod trace;
 mod world_state;
 
+use std::collections::BTreeSet;
+
 use bevy::prelude::*;
 
-use crate::{block_state::BlockState, int_vec3::IntVec3, constants::WORLD_SIZE};
+use crate::{
+    block_state::BlockState, constants::WORLD_SIZE, int_vec3::IntVec3, schematic::RejectedBlock,
+};
+
+use self::history::History;
 
-use self::world_state::{InvalidPlacement, WorldState};
+pub use self::history::Cycle;
+pub use self::input::{InputEvent, InvalidInput};
+pub use self::input_schedule::{InputSchedule, ScheduleEntry};
+pub use self::simulation::{power_inputs, power_level, MAX_POWER};
+pub use self::trace::{decays_to_zero_at, SignalTrace, TraceSegment};
+pub use self::world_state::{InvalidPlacement, ScheduledTick, WorldState};
 
-type TimeIndex = i32;
+pub type TimeIndex = i32;
 
 #[derive(Clone, Debug, PartialEq, Resource)]
 pub struct Timeline {
     bounds: (IntVec3, IntVec3),
-    world_states: Vec<(TimeIndex, WorldState)>,
+    history: History,
+    inputs: Vec<(TimeIndex, InputEvent)>,
+    input_schedule: InputSchedule,
+    branches: Vec<Branch>,
     random_seed: i32,
 }
 
+/// An alternate history that diverged from the active timeline when an input was inserted
+/// mid-simulation. Branches are kept so that the outcomes of different inputs can be compared.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Branch {
+    /// Tick at which this branch and the history it was forked from diverge.
+    pub forked_at: TimeIndex,
+    history: History,
+    inputs: Vec<(TimeIndex, InputEvent)>,
+}
+
+/// There is no saved branch at the given index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidBranch(pub usize);
+
 impl Timeline {
+    /// Starts a timeline with the given world as its design. The bounds of the timeline are taken
+    /// from the world.
+    pub fn from_world(world: WorldState) -> Self {
+        Timeline {
+            bounds: world.bounds(),
+            history: History::new(world),
+            ..Timeline::default()
+        }
+    }
+
     /// Insert a block into the world at the start of the timeline if it is legal to do so. Will
     /// fail if there is already a block at the given position, or if the given block type is not
     /// allowed at the given position. (For example, placing redstone dust on top of a torch.)
@@ -24,16 +68,212 @@ impl Timeline {
         pos: IntVec3,
         state: BlockState,
     ) -> Result<(), InvalidPlacement> {
-        let (_, world_state) = self
-            .world_states
-            .iter_mut()
-            .find(|(time_index, _)| *time_index == 0)
-            .unwrap();
-        world_state.insert_block(pos, state)?;
+        let mut design = self.design().clone();
+        design.insert_block(pos, state)?;
+        self.design_changed(design);
         Ok(())
     }
 
-    
+    /// Remove a block from the world at the start of the timeline. Returns the state of the
+    /// removed block, or `None` if there was no block at the given position.
+    pub fn remove_block(&mut self, pos: IntVec3) -> Option<BlockState> {
+        let mut design = self.design().clone();
+        let removed = design.remove_block(pos)?;
+        self.design_changed(design);
+        Some(removed)
+    }
+
+    /// Replaces the design with the given world in one step. Blocks that differ are removed, and
+    /// the blocks of the world at those positions are inserted bottom layer first, as
+    /// `insert_block` would. Nothing changes if any of them is rejected. Returns the positions that
+    /// changed. Edits to many blocks should go through here, since every call to `insert_block` or
+    /// `remove_block` starts the history over.
+    pub fn set_design(&mut self, world: &WorldState) -> Result<Vec<IntVec3>, Vec<RejectedBlock>> {
+        let changed = self.design().changed_positions(world);
+        let mut design = self.design().clone();
+        for pos in changed.iter() {
+            design.remove_block(*pos);
+        }
+        let design = design.place_blocks(
+            changed
+                .iter()
+                .filter_map(|pos| Some((*pos, world.block_at(*pos)?))),
+        )?;
+        self.design_changed(design);
+        Ok(changed)
+    }
+
+    /// Lowest and highest corners of the world, inclusive of both.
+    pub fn bounds(&self) -> (IntVec3, IntVec3) {
+        self.bounds
+    }
+
+    /// Changes the bounds of the world. Every block in the design is placed again within the new
+    /// bounds, bottom layer first, and nothing changes if any block would be rejected. Raising the
+    /// bottom of the bounds takes away the ground under blocks in the lowest layer.
+    pub fn resize(&mut self, bounds: (IntVec3, IntVec3)) -> Result<(), Vec<RejectedBlock>> {
+        let world = WorldState::new(bounds).place_blocks(self.design().blocks())?;
+        self.bounds = bounds;
+        self.design_changed(world);
+        Ok(())
+    }
+
+    /// The world at the start of the timeline before any inputs are applied.
+    pub fn design(&self) -> &WorldState {
+        self.history.design()
+    }
+
+    /// Returns the world at the given tick if that tick has been simulated. Most ticks are
+    /// rebuilt from the changes since the nearest stored keyframe, so callers that only need to
+    /// know what changed should use `changed_between`.
+    pub fn state_at(&self, time: TimeIndex) -> Option<WorldState> {
+        self.history.state_at(time)
+    }
+
+    /// Positions where a block changed on any simulated tick after `from` up to and including
+    /// `to`, for redrawing only the blocks that changed when moving between ticks. Ticks that have
+    /// not been simulated are ignored.
+    pub fn changed_between(&self, from: TimeIndex, to: TimeIndex) -> BTreeSet<IntVec3> {
+        self.history.changed_between(from, to)
+    }
+
+    /// The latest tick that has been simulated.
+    pub fn last_simulated_time(&self) -> TimeIndex {
+        self.history.last_simulated_time()
+    }
+
+    /// Runs the simulation until world states are available up to and including the given tick.
+    pub fn simulate_to(&mut self, time: TimeIndex) {
+        self.history
+            .simulate_to(&self.input_schedule, &self.inputs, time);
+    }
+
+    /// The repeating span of ticks that the simulation settled into, if it has been reached.
+    /// States for ticks after the start of the cycle are not simulated again, so simulating far
+    /// into the future is cheap once a cycle is found.
+    pub fn cycle(&self) -> Option<Cycle> {
+        self.history.cycle()
+    }
+
+    /// Input events in order of the tick where each applies. This does not include events from
+    /// the input schedule.
+    pub fn inputs(&self) -> &[(TimeIndex, InputEvent)] {
+        &self.inputs
+    }
+
+    pub fn input_schedule(&self) -> &InputSchedule {
+        &self.input_schedule
+    }
+
+    /// Replaces the input schedule that is replayed on every simulation run. Simulated states are
+    /// discarded since the new schedule might change any tick after the start of the timeline.
+    /// Branches keep their own inputs, but share the schedule with the active history.
+    pub fn set_input_schedule(&mut self, schedule: InputSchedule) {
+        self.input_schedule = schedule;
+        self.invalidate_after(0);
+        for branch in self.branches.iter_mut() {
+            branch.history.truncate(0);
+        }
+    }
+
+    /// Records an input event at the given tick, and re-simulates every tick after it. The event
+    /// takes effect in the transition from `time` to `time + 1` so the states up to and including
+    /// `time` are not changed.
+    ///
+    /// If `keep_branch` is true the history as it was before the edit is saved as a branch so
+    /// that the two outcomes can be compared. In that case the index of the new branch is
+    /// returned.
+    pub fn insert_input(
+        &mut self,
+        time: TimeIndex,
+        event: InputEvent,
+        keep_branch: bool,
+    ) -> Result<Option<usize>, InvalidInput> {
+        if time < 0 {
+            return Err(InvalidInput::BeforeStart);
+        }
+        let horizon = self.last_simulated_time().max(time + 1);
+        self.simulate_to(time);
+
+        // Check that the event applies to the world as it is at the given tick, including the
+        // effects of other inputs at the same tick.
+        let mut world = self.state_at(time).unwrap();
+        apply_inputs_at(&mut world, &self.input_schedule, &self.inputs, time);
+        world.apply_input(&event)?;
+
+        let branch_index = if keep_branch {
+            self.branches.push(Branch {
+                forked_at: time,
+                history: self.history.clone(),
+                inputs: self.inputs.clone(),
+            });
+            Some(self.branches.len() - 1)
+        } else {
+            None
+        };
+
+        let index = self.inputs.partition_point(|(t, _)| *t <= time);
+        self.inputs.insert(index, (time, event));
+        self.invalidate_after(time);
+        self.simulate_to(horizon);
+        Ok(branch_index)
+    }
+
+    pub fn branches(&self) -> &[Branch] {
+        &self.branches
+    }
+
+    /// Makes a saved branch the active history. The history that was active takes the place of
+    /// the branch so that switching again switches back.
+    pub fn switch_to_branch(&mut self, index: usize) -> Result<(), InvalidBranch> {
+        let branch = self.branches.get_mut(index).ok_or(InvalidBranch(index))?;
+        std::mem::swap(&mut self.history, &mut branch.history);
+        std::mem::swap(&mut self.inputs, &mut branch.inputs);
+        Ok(())
+    }
+
+    /// Compares the active history with a saved branch tick by tick up to and including `until`.
+    /// Returns each tick where the two histories differ paired with the positions that differ.
+    pub fn compare_with_branch(
+        &mut self,
+        index: usize,
+        until: TimeIndex,
+    ) -> Result<Vec<(TimeIndex, Vec<IntVec3>)>, InvalidBranch> {
+        if index >= self.branches.len() {
+            return Err(InvalidBranch(index));
+        }
+        self.simulate_to(until);
+        let branch = &mut self.branches[index];
+        branch
+            .history
+            .simulate_to(&self.input_schedule, &branch.inputs, until);
+        Ok((0..=until)
+            .filter_map(|time| {
+                let ours = self.history.state_at(time)?;
+                let theirs = branch.history.state_at(time)?;
+                let changed = ours.changed_positions(&theirs);
+                if changed.is_empty() {
+                    None
+                } else {
+                    Some((time, changed))
+                }
+            })
+            .collect())
+    }
+
+    /// Replaces the design, and discards simulated states that were computed from the earlier
+    /// version. Branches share the same starting design as the active history.
+    fn design_changed(&mut self, design: WorldState) {
+        for branch in self.branches.iter_mut() {
+            branch.history = History::new(design.clone());
+        }
+        self.history = History::new(design);
+    }
+
+    /// Discards simulated states after the given tick.
+    fn invalidate_after(&mut self, time: TimeIndex) {
+        self.history.truncate(time);
+    }
 }
 
 impl Default for Timeline {
@@ -41,8 +281,349 @@ impl Default for Timeline {
         let bounds = (IntVec3::ZERO, IntVec3::ONE * WORLD_SIZE);
         Timeline {
             bounds,
-            world_states: vec![(0, WorldState::new(bounds))],
+            history: History::new(WorldState::new(bounds)),
+            inputs: vec![],
+            input_schedule: InputSchedule::default(),
+            branches: vec![],
             random_seed: 0,
         }
     }
 }
+
+/// Applies scheduled inputs for the given tick, followed by inputs that were inserted at that
+/// tick.
+fn apply_inputs_at(
+    world: &mut WorldState,
+    schedule: &InputSchedule,
+    inputs: &[(TimeIndex, InputEvent)],
+    time: TimeIndex,
+) {
+    let inserted = inputs
+        .iter()
+        .filter(|(t, _)| *t == time)
+        .map(|(_, event)| event);
+    for event in schedule.events_at(time).chain(inserted) {
+        // Schedules and inputs may refer to a lever that has since been removed from the design,
+        // in which case the event does nothing.
+        let _ = world.apply_input(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::{block_state::BlockState, int_vec3::IntVec3};
+
+    use super::{
+        Cycle, InputEvent, InputSchedule, InvalidBranch, InvalidInput, InvalidPlacement, Timeline,
+    };
+
+    const LEVER: IntVec3 = IntVec3::new(1, 1, 1);
+    const WIRE: IntVec3 = IntVec3::new(2, 1, 1);
+    const WIRE_STATE: &str = "east=none,north=none,south=none,west=none,power=0";
+
+    fn test_timeline() -> Timeline {
+        let mut timeline = Timeline::default();
+        for x in 0..4 {
+            for z in 0..4 {
+                let _ =
+                    timeline.insert_block(IntVec3::new(x, 0, z), BlockState::new("sandstone", ""));
+            }
+        }
+        let _ = timeline.insert_block(
+            LEVER,
+            BlockState::new("lever", "face=floor,facing=north,powered=false"),
+        );
+        let _ = timeline.insert_block(WIRE, BlockState::new("redstone_wire", WIRE_STATE));
+        timeline
+    }
+
+    fn wire_power(timeline: &Timeline, time: i32) -> Option<i32> {
+        let world = timeline.state_at(time)?;
+        world.block_at(WIRE)?.get_int("power")
+    }
+
+    #[test]
+    fn resimulates_after_inserted_input() {
+        let mut timeline = test_timeline();
+        timeline.simulate_to(10);
+        assert_eq!(wire_power(&timeline, 10), Some(0));
+
+        timeline
+            .insert_input(5, InputEvent::ToggleLever(LEVER), false)
+            .unwrap();
+        assert_eq!(
+            timeline.last_simulated_time(),
+            10,
+            "simulated to the same tick"
+        );
+        assert_eq!(
+            wire_power(&timeline, 5),
+            Some(0),
+            "state at edit time is unchanged"
+        );
+        assert_eq!(
+            wire_power(&timeline, 6),
+            Some(15),
+            "state after edit is powered"
+        );
+        assert_eq!(wire_power(&timeline, 10), Some(15));
+        assert!(timeline.branches().is_empty(), "no branch was saved");
+    }
+
+    #[test]
+    fn keeps_the_previous_branch_for_comparison() {
+        let mut timeline = test_timeline();
+        timeline.simulate_to(10);
+        let branch = timeline
+            .insert_input(5, InputEvent::ToggleLever(LEVER), true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(timeline.branches()[branch].forked_at, 5);
+
+        let differences = timeline.compare_with_branch(branch, 8).unwrap();
+        assert_eq!(
+            differences,
+            vec![
+                (6, vec![LEVER, WIRE]),
+                (7, vec![LEVER, WIRE]),
+                (8, vec![LEVER, WIRE]),
+            ]
+        );
+
+        timeline.switch_to_branch(branch).unwrap();
+        assert_eq!(
+            wire_power(&timeline, 8),
+            Some(0),
+            "switched to the unpowered branch"
+        );
+        assert_eq!(
+            timeline.switch_to_branch(branch + 1),
+            Err(InvalidBranch(branch + 1))
+        );
+    }
+
+    #[test]
+    fn replays_the_input_schedule() {
+        let mut timeline = test_timeline();
+        timeline.set_input_schedule(InputSchedule::parse("toggle 1 1 1 @ 2, 4").unwrap());
+        timeline.simulate_to(6);
+        let powers: Vec<_> = (0..=6).map(|t| wire_power(&timeline, t)).collect();
+        assert_eq!(
+            powers,
+            [0, 0, 0, 15, 15, 0, 0].map(Some),
+            "wire is powered between toggles"
+        );
+
+        timeline.set_input_schedule(InputSchedule::default());
+        timeline.simulate_to(6);
+        assert_eq!(wire_power(&timeline, 3), Some(0), "schedule was removed");
+    }
+
+    #[test]
+    fn stops_simulating_at_a_steady_state() {
+        let mut timeline = test_timeline();
+        timeline.set_input_schedule(InputSchedule::parse("toggle 1 1 1 @ 2").unwrap());
+        timeline.simulate_to(1000);
+        assert_eq!(
+            timeline.cycle(),
+            Some(Cycle {
+                start: 3,
+                period: 1
+            })
+        );
+        assert_eq!(timeline.last_simulated_time(), 1000);
+        assert_eq!(wire_power(&timeline, 1000), Some(15), "extrapolated");
+
+        timeline
+            .insert_input(500, InputEvent::ToggleLever(LEVER), false)
+            .unwrap();
+        assert_eq!(wire_power(&timeline, 500), Some(15));
+        assert_eq!(wire_power(&timeline, 501), Some(0), "cycle was broken");
+        assert_eq!(
+            timeline.cycle(),
+            Some(Cycle {
+                start: 501,
+                period: 1
+            })
+        );
+    }
+
+    /// A timeline with a clock instead of the lever and wire.
+    fn test_clock() -> Timeline {
+        let mut timeline = test_timeline();
+        // A torch powers a loop of wire that leads into a repeater, which powers the block that
+        // the torch is on.
+        let clock = [
+            (IntVec3::new(1, 1, 3), "iron_block", ""),
+            (IntVec3::new(1, 2, 3), "redstone_torch", "lit=true"),
+            (
+                IntVec3::new(1, 1, 2),
+                "repeater",
+                "delay=2,facing=north,locked=false,powered=false",
+            ),
+            (IntVec3::new(1, 1, 1), "iron_block", ""),
+            (IntVec3::new(1, 2, 1), "redstone_wire", WIRE_STATE),
+            (IntVec3::new(2, 1, 1), "iron_block", ""),
+            (IntVec3::new(2, 2, 1), "redstone_wire", WIRE_STATE),
+            (IntVec3::new(2, 1, 2), "iron_block", ""),
+            (IntVec3::new(2, 2, 2), "redstone_wire", WIRE_STATE),
+            (IntVec3::new(2, 1, 3), "iron_block", ""),
+            (IntVec3::new(2, 2, 3), "redstone_wire", WIRE_STATE),
+        ];
+        let _ = timeline.remove_block(LEVER);
+        let _ = timeline.remove_block(WIRE);
+        for (pos, block_type, state_values) in clock {
+            timeline
+                .insert_block(pos, BlockState::new(block_type, state_values))
+                .unwrap();
+        }
+        timeline
+    }
+
+    #[test]
+    fn finds_the_period_of_a_clock() {
+        let mut timeline = test_clock();
+        timeline.simulate_to(100);
+        let cycle = timeline.cycle().expect("found a cycle");
+        assert!(cycle.period > 1, "clock does not settle");
+        assert_eq!(
+            timeline.state_at(cycle.start + cycle.period * 10),
+            timeline.state_at(cycle.start)
+        );
+        assert_ne!(
+            timeline.state_at(cycle.start + 1),
+            timeline.state_at(cycle.start)
+        );
+        let one_period = timeline.changed_between(cycle.start, cycle.start + cycle.period);
+        assert!(one_period.contains(&IntVec3::new(1, 2, 3)), "torch flips");
+        assert_eq!(
+            timeline.changed_between(cycle.start, cycle.start + cycle.period * 10),
+            one_period
+        );
+    }
+
+    #[test]
+    fn collects_changes_leading_into_a_cycle() {
+        let mut timeline = test_clock();
+        // A lever beside the clock changes on the way into the cycle, but not inside it.
+        let lever = IntVec3::new(0, 1, 0);
+        timeline
+            .insert_block(
+                lever,
+                BlockState::new("lever", "face=floor,facing=north,powered=false"),
+            )
+            .unwrap();
+        timeline.set_input_schedule(InputSchedule::parse("toggle 0 1 0 @ 0").unwrap());
+        timeline.simulate_to(100);
+        let cycle = timeline.cycle().expect("found a cycle");
+        let end = cycle.start + cycle.period * 2;
+        for from in 0..=cycle.start {
+            let mut expected = BTreeSet::new();
+            for time in from + 1..=end {
+                let before = timeline.state_at(time - 1).unwrap();
+                let after = timeline.state_at(time).unwrap();
+                expected.extend(before.delta_to(&after).positions());
+            }
+            assert!(from > 0 || expected.contains(&lever));
+            assert_eq!(
+                timeline.changed_between(from, end),
+                expected,
+                "from {}",
+                from
+            );
+        }
+    }
+
+    #[test]
+    fn rebuilds_ticks_between_keyframes() {
+        let mut timeline = test_timeline();
+        timeline.set_input_schedule(InputSchedule::parse("toggle 1 1 1 @ 2, 40, 70").unwrap());
+        timeline.simulate_to(100);
+        let powers: Vec<_> = [100, 41, 3, 71, 40, 69]
+            .map(|t| wire_power(&timeline, t))
+            .into();
+        assert_eq!(powers, [15, 0, 15, 15, 15, 0].map(Some));
+        assert_eq!(timeline.state_at(101), None, "not simulated yet");
+
+        assert!(timeline.changed_between(10, 39).is_empty());
+        assert_eq!(
+            timeline
+                .changed_between(41, 10)
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![LEVER, WIRE]
+        );
+        assert_eq!(
+            timeline.changed_between(60, 1000),
+            timeline.changed_between(60, 72),
+            "ticks past the horizon are ignored"
+        );
+
+        timeline
+            .insert_input(50, InputEvent::ToggleLever(LEVER), false)
+            .unwrap();
+        let powers: Vec<_> = [45, 60, 71, 100].map(|t| wire_power(&timeline, t)).into();
+        assert_eq!(powers, [0, 15, 0, 0].map(Some));
+    }
+
+    #[test]
+    fn rejects_input_for_a_missing_lever() {
+        let mut timeline = test_timeline();
+        let result = timeline.insert_input(3, InputEvent::ToggleLever(WIRE), true);
+        assert_eq!(result, Err(InvalidInput::NotALever(WIRE)));
+        assert!(timeline.branches().is_empty(), "no branch was saved");
+    }
+
+    #[test]
+    fn resizes_only_when_every_block_fits() {
+        let mut timeline = test_timeline();
+        let rejected = timeline
+            .resize((IntVec3::ZERO, IntVec3::new(2, 4, 2)))
+            .unwrap_err();
+        assert_eq!(rejected.len(), 7);
+        assert!(rejected
+            .iter()
+            .all(|block| block.reason == InvalidPlacement::OutOfBounds));
+        assert_eq!(timeline.bounds(), Timeline::default().bounds());
+
+        let bounds = (IntVec3::new(0, 0, 0), IntVec3::new(63, 15, 63));
+        timeline.resize(bounds).unwrap();
+        assert_eq!(timeline.bounds(), bounds);
+        assert_eq!(timeline.design().bounds(), bounds);
+        assert_eq!(timeline.design().blocks().count(), 18);
+        timeline
+            .insert_block(IntVec3::new(40, 0, 40), BlockState::new("sandstone", ""))
+            .unwrap();
+    }
+
+    #[test]
+    fn replaces_the_design_only_when_every_block_fits() {
+        let mut timeline = test_timeline();
+        let mut larger = test_timeline();
+        larger
+            .resize((IntVec3::ZERO, IntVec3::new(63, 15, 63)))
+            .unwrap();
+        larger
+            .insert_block(IntVec3::new(40, 0, 40), BlockState::new("sandstone", ""))
+            .unwrap();
+        let rejected = timeline.set_design(larger.design()).unwrap_err();
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].reason, InvalidPlacement::OutOfBounds);
+        assert_eq!(timeline.design(), test_timeline().design());
+
+        // Removed blocks are not checked, just like `remove_block`, so the wire can be left
+        // without the block under it.
+        let mut world = timeline.design().clone();
+        world.remove_block(IntVec3::new(2, 0, 1));
+        world.remove_block(LEVER);
+        world
+            .insert_block(LEVER + IntVec3::Y, BlockState::new("sandstone", ""))
+            .unwrap();
+        assert_eq!(timeline.set_design(&world).unwrap().len(), 3);
+        assert_eq!(timeline.design().changed_positions(&world), vec![]);
+        assert_eq!(timeline.bounds(), Timeline::default().bounds());
+        assert_eq!(timeline.state_at(0).as_ref(), Some(timeline.design()));
+    }
+}
@@ -0,0 +1,460 @@
+// Simplified redstone rules. Each call to `next_tick` advances the world by one redstone tick
+// (two game ticks). Redstone wire and lamps respond to power changes immediately. Torches and
+// repeaters respond after a delay, which is modeled with scheduled ticks stored in the world state.
+//
+// Some known differences from Minecraft: wire does not step up or down blocks, torches never
+// burn out, repeaters cannot be locked, and lamps turn off as soon as they lose power.
+
+use std::collections::{HashMap, VecDeque};
+
+use minecraft_assets::schemas::{blockstates::multipart::StateValue, models::BlockFace};
+
+use crate::{
+    block::{attachment_face, is_flat_surface},
+    block_state::{face_name, BlockState},
+    int_vec3::IntVec3,
+};
+
+use super::world_state::{ScheduledTick, WorldState};
+
+/// Highest power level that a power source can provide.
+pub const MAX_POWER: i32 = 15;
+
+const HORIZONTAL_FACES: [BlockFace; 4] = [
+    BlockFace::North,
+    BlockFace::South,
+    BlockFace::West,
+    BlockFace::East,
+];
+
+/// Computes the world state for the tick after the given state.
+pub fn next_tick(world: &WorldState) -> WorldState {
+    let mut next = world.clone();
+    for tick in next.take_due_ticks() {
+        let is_same_block_type = next
+            .block_at(tick.pos)
+            .map_or(false, |state| state.block_type == tick.state.block_type);
+        if is_same_block_type {
+            next.set_block_state(tick.pos, tick.state);
+        }
+    }
+    update_wire_power(&mut next);
+    update_lamps(&mut next);
+    schedule_delayed_updates(&mut next);
+    next
+}
+
+/// Computes the power level of every redstone wire in the world. Wire next to a power source has
+/// the maximum power level, and power decreases by one for each block of wire away from a source.
+pub fn wire_power_levels(world: &WorldState) -> HashMap<IntVec3, i32> {
+    let wires: Vec<IntVec3> = world
+        .blocks()
+        .filter(|(_, state)| state.block_type == "redstone_wire")
+        .map(|(pos, _)| pos)
+        .collect();
+    let mut levels: HashMap<IntVec3, i32> = wires.iter().map(|pos| (*pos, 0)).collect();
+    let mut queue = VecDeque::new();
+    for pos in wires {
+        let is_next_to_source = pos.neighbors().any(|(_, neighbor)| {
+            provides_power(world, neighbor, pos) || is_strongly_powered(world, neighbor)
+        });
+        if is_next_to_source {
+            levels.insert(pos, MAX_POWER);
+            queue.push_back(pos);
+        }
+    }
+    // Every source starts at the same level so a breadth-first traversal reaches each wire with
+    // its highest possible level first.
+    while let Some(pos) = queue.pop_front() {
+        let level = levels[&pos];
+        for face in HORIZONTAL_FACES {
+            let neighbor = pos + IntVec3::from_block_face(face);
+            if let Some(neighbor_level) = levels.get_mut(&neighbor) {
+                if *neighbor_level < level - 1 {
+                    *neighbor_level = level - 1;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+    }
+    levels
+}
+
+/// Returns true if the solid block at the given position is powered, either directly by a power
+/// source, or by redstone wire. A powered block activates components attached to it, such as
+/// torches.
+pub fn is_powered(world: &WorldState, pos: IntVec3) -> bool {
+    is_strongly_powered(world, pos) || is_weakly_powered(world, pos)
+}
+
+/// Power level of the block at the given position, as seen by a probe. Wire reports its power
+/// level; other components report either zero or the maximum level.
+pub fn power_level(world: &WorldState, pos: IntVec3) -> i32 {
+    let state = match world.block_at(pos) {
+        Some(state) => state,
+        None => return 0,
+    };
+    let is_on = match state.block_type.as_ref() {
+        "redstone_wire" => return state.get_int("power").unwrap_or(0),
+        "lever" | "repeater" => state.get_bool("powered") == Some(true),
+        "redstone_lamp" | "redstone_torch" => state.get_bool("lit") == Some(true),
+        _ => is_solid(state) && is_powered(world, pos),
+    };
+    if is_on {
+        MAX_POWER
+    } else {
+        0
+    }
+}
+
+fn update_wire_power(world: &mut WorldState) {
+    for (pos, level) in wire_power_levels(world) {
+        set_if_changed(world, pos, "power", StateValue::String(level.to_string()));
+    }
+}
+
+fn update_lamps(world: &mut WorldState) {
+    let lamps: Vec<IntVec3> = world
+        .blocks()
+        .filter(|(_, state)| state.block_type == "redstone_lamp")
+        .map(|(pos, _)| pos)
+        .collect();
+    for pos in lamps {
+        let lit = is_powered(world, pos)
+            || pos
+                .neighbors()
+                .any(|(_, neighbor)| provides_power(world, neighbor, pos));
+        set_if_changed(world, pos, "lit", StateValue::Bool(lit));
+    }
+}
+
+/// Torches and repeaters change state some number of ticks after their input changes.
+fn schedule_delayed_updates(world: &mut WorldState) {
+    let mut ticks = vec![];
+    for (pos, state) in world.blocks() {
+        if world.has_scheduled_tick(pos) {
+            continue;
+        }
+        let (prop, target, delay) = match state.block_type.as_ref() {
+            "redstone_torch" => {
+                let lit = !is_powered(world, pos + IntVec3::NEG_Y);
+                ("lit", lit, 1)
+            }
+            "repeater" => {
+                let powered = match repeater_input(pos, state) {
+                    Some(input) => is_input_powered(world, input, pos),
+                    None => false,
+                };
+                ("powered", powered, state.get_int("delay").unwrap_or(1))
+            }
+            _ => continue,
+        };
+        if state.get_bool(prop) != Some(target) {
+            let mut next_state = state.clone();
+            if next_state.update(prop, StateValue::Bool(target)).is_ok() {
+                ticks.push(ScheduledTick {
+                    pos,
+                    delay,
+                    state: next_state,
+                });
+            }
+        }
+    }
+    for tick in ticks {
+        world.schedule_tick(tick);
+    }
+}
+
+fn set_if_changed(world: &mut WorldState, pos: IntVec3, prop: &str, value: StateValue) {
+    if let Some(state) = world.block_at(pos) {
+        if state.get(prop) != Some(&value) {
+            let mut state = state.clone();
+            if state.update(prop, value).is_ok() {
+                world.set_block_state(pos, state);
+            }
+        }
+    }
+}
+
+/// Lists the positions that send power, or that would send power if they were on, into the block
+/// at the given position. This is the reverse of the rules that the simulation uses to spread
+/// power, and is used to trace how a signal reaches a block.
+pub fn power_inputs(world: &WorldState, pos: IntVec3) -> Vec<IntVec3> {
+    let state = match world.block_at(pos) {
+        Some(state) => state,
+        None => return vec![],
+    };
+    let is_input = |source: IntVec3| -> bool {
+        let source_state = match world.block_at(source) {
+            Some(source_state) => source_state,
+            None => return false,
+        };
+        match state.block_type.as_ref() {
+            "redstone_wire" => {
+                can_provide_power(source, source_state, pos)
+                    || (is_solid(source_state) && can_be_strongly_powered(world, source))
+                    || (source_state.block_type == "redstone_wire" && source.y == pos.y)
+            }
+            "redstone_lamp" => {
+                can_provide_power(source, source_state, pos)
+                    || can_strongly_power(source, source_state, pos)
+                    || wire_can_point_into(source, source_state, pos)
+            }
+            "redstone_torch" => attached_block(pos, state) == Some(source),
+            "repeater" => repeater_input(pos, state) == Some(source),
+            _ if is_solid(state) => {
+                can_strongly_power(source, source_state, pos)
+                    || wire_can_point_into(source, source_state, pos)
+            }
+            _ => false,
+        }
+    };
+    pos.neighbors()
+        .map(|(_, neighbor)| neighbor)
+        .filter(|neighbor| is_input(*neighbor))
+        .collect()
+}
+
+/// Returns true if the component at `source` sends power to the adjacent position `target`. This
+/// covers components that produce power on their own: levers, torches, and repeaters.
+fn provides_power(world: &WorldState, source: IntVec3, target: IntVec3) -> bool {
+    match world.block_at(source) {
+        Some(state) => is_on(state) && can_provide_power(source, state, target),
+        None => false,
+    }
+}
+
+/// Returns true if the component would send power to `target` when it is on.
+fn can_provide_power(source: IntVec3, state: &BlockState, target: IntVec3) -> bool {
+    match state.block_type.as_ref() {
+        "lever" => true,
+        // A torch does not power the block that it is attached to.
+        "redstone_torch" => attached_block(source, state) != Some(target),
+        "repeater" => repeater_output(source, state) == Some(target),
+        _ => false,
+    }
+}
+
+/// Returns true if a component that produces power is currently switched on.
+fn is_on(state: &BlockState) -> bool {
+    match state.block_type.as_ref() {
+        "lever" | "repeater" => state.get_bool("powered") == Some(true),
+        "redstone_torch" => state.get_bool("lit") == Some(true),
+        "redstone_wire" => state.get_int("power").unwrap_or(0) > 0,
+        _ => false,
+    }
+}
+
+/// A solid block is strongly powered when a power source points into it. Strongly powered blocks
+/// power adjacent redstone wire.
+fn is_strongly_powered(world: &WorldState, pos: IntVec3) -> bool {
+    match world.block_at(pos) {
+        Some(state) if is_solid(state) => pos
+            .neighbors()
+            .any(|(_, neighbor)| strongly_powers(world, neighbor, pos)),
+        _ => false,
+    }
+}
+
+fn strongly_powers(world: &WorldState, source: IntVec3, target: IntVec3) -> bool {
+    match world.block_at(source) {
+        Some(state) => is_on(state) && can_strongly_power(source, state, target),
+        None => false,
+    }
+}
+
+fn can_strongly_power(source: IntVec3, state: &BlockState, target: IntVec3) -> bool {
+    match state.block_type.as_ref() {
+        "lever" => attached_block(source, state) == Some(target),
+        "redstone_torch" => target == source + IntVec3::Y,
+        "repeater" => repeater_output(source, state) == Some(target),
+        _ => false,
+    }
+}
+
+/// Returns true if a power source could strongly power the solid block at the given position.
+fn can_be_strongly_powered(world: &WorldState, pos: IntVec3) -> bool {
+    world
+        .neighbors(pos)
+        .any(|(_, neighbor, state)| can_strongly_power(neighbor, state, pos))
+}
+
+/// A solid block is weakly powered when powered redstone wire points into it. Weakly powered
+/// blocks activate components, but do not power adjacent wire.
+fn is_weakly_powered(world: &WorldState, pos: IntVec3) -> bool {
+    match world.block_at(pos) {
+        Some(state) if is_solid(state) => pos
+            .neighbors()
+            .any(|(_, neighbor)| wire_points_into(world, neighbor, pos)),
+        _ => false,
+    }
+}
+
+/// Wire powers the block beneath it, and horizontally adjacent blocks on sides where the wire has
+/// a connection. Wire without any connections is treated as a cross that points in all four
+/// directions, which is how Minecraft places a lone piece of redstone dust.
+fn wire_points_into(world: &WorldState, wire_pos: IntVec3, target: IntVec3) -> bool {
+    match world.block_at(wire_pos) {
+        Some(state) => is_on(state) && wire_can_point_into(wire_pos, state, target),
+        None => false,
+    }
+}
+
+fn wire_can_point_into(wire_pos: IntVec3, state: &BlockState, target: IntVec3) -> bool {
+    if state.block_type != "redstone_wire" {
+        return false;
+    }
+    if target == wire_pos + IntVec3::NEG_Y {
+        return true;
+    }
+    let is_cross = HORIZONTAL_FACES
+        .iter()
+        .all(|face| state.get_str(face_name(*face)) == Some("none"));
+    HORIZONTAL_FACES.into_iter().any(|face| {
+        wire_pos + IntVec3::from_block_face(face) == target
+            && (is_cross || state.get_str(face_name(face)) != Some("none"))
+    })
+}
+
+/// Returns true if the block at `input` sends power into the component at `receiver`.
+fn is_input_powered(world: &WorldState, input: IntVec3, receiver: IntVec3) -> bool {
+    match world.block_at(input) {
+        Some(state) if state.block_type == "redstone_wire" => {
+            state.get_int("power").unwrap_or(0) > 0
+        }
+        Some(state) if is_solid(state) => is_powered(world, input),
+        Some(_) => provides_power(world, input, receiver),
+        None => false,
+    }
+}
+
+fn is_solid(state: &BlockState) -> bool {
+    is_flat_surface(state)
+}
+
+/// Position of the block that supports an attached block like a torch, or a lever.
+fn attached_block(pos: IntVec3, state: &BlockState) -> Option<IntVec3> {
+    attachment_face(state).map(|face| pos + IntVec3::from_block_face(face))
+}
+
+/// A repeater's `facing` property points toward its input.
+pub fn repeater_input(pos: IntVec3, state: &BlockState) -> Option<IntVec3> {
+    state
+        .facing()
+        .map(|face| pos + IntVec3::from_block_face(face))
+}
+
+pub fn repeater_output(pos: IntVec3, state: &BlockState) -> Option<IntVec3> {
+    state
+        .facing()
+        .map(|face| pos - IntVec3::from_block_face(face))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{block_state::BlockState, int_vec3::IntVec3};
+
+    use super::{next_tick, power_level, WorldState};
+
+    fn test_world(blocks: &[(IntVec3, &str, &str)]) -> WorldState {
+        let mut world = WorldState::new((IntVec3::ZERO, IntVec3::ONE * 8));
+        for x in 0..8 {
+            for z in 0..8 {
+                world
+                    .insert_block(IntVec3::new(x, 0, z), BlockState::new("sandstone", ""))
+                    .unwrap();
+            }
+        }
+        for (pos, block_type, state_values) in blocks {
+            let _ = world.remove_block(*pos);
+            world
+                .insert_block(*pos, BlockState::new(block_type, state_values))
+                .unwrap();
+        }
+        world
+    }
+
+    fn run(world: &WorldState, ticks: i32) -> WorldState {
+        let mut world = world.clone();
+        for _ in 0..ticks {
+            world = next_tick(&world);
+        }
+        world
+    }
+
+    const WIRE: &str = "east=none,north=none,south=none,west=none,power=0";
+
+    #[test]
+    fn powers_wire_from_a_lever() {
+        let world = test_world(&[
+            (
+                IntVec3::new(0, 1, 0),
+                "lever",
+                "face=floor,facing=north,powered=true",
+            ),
+            (IntVec3::new(1, 1, 0), "redstone_wire", WIRE),
+            (IntVec3::new(2, 1, 0), "redstone_wire", WIRE),
+            (IntVec3::new(3, 1, 0), "redstone_wire", WIRE),
+            (IntVec3::new(4, 1, 0), "redstone_lamp", "lit=false"),
+        ]);
+        let world = next_tick(&world);
+        assert_eq!(power_level(&world, IntVec3::new(1, 1, 0)), 15);
+        assert_eq!(power_level(&world, IntVec3::new(3, 1, 0)), 13);
+        assert_eq!(
+            world.block_at(IntVec3::new(4, 1, 0)),
+            Some(&BlockState::new("redstone_lamp", "lit=true")),
+            "lamp is lit"
+        );
+    }
+
+    #[test]
+    fn inverts_signal_with_a_torch() {
+        let world = test_world(&[
+            (
+                IntVec3::new(0, 1, 0),
+                "lever",
+                "face=floor,facing=north,powered=true",
+            ),
+            (IntVec3::new(1, 1, 0), "redstone_wire", WIRE),
+            (IntVec3::new(2, 1, 0), "iron_block", ""),
+            (IntVec3::new(2, 2, 0), "redstone_torch", "lit=true"),
+        ]);
+        let torch = IntVec3::new(2, 2, 0);
+        assert_eq!(
+            power_level(&run(&world, 1), torch),
+            15,
+            "torch has not responded yet"
+        );
+        assert_eq!(power_level(&run(&world, 2), torch), 0, "torch turned off");
+    }
+
+    #[test]
+    fn delays_signal_with_a_repeater() {
+        let world = test_world(&[
+            (
+                IntVec3::new(0, 1, 0),
+                "lever",
+                "face=floor,facing=north,powered=true",
+            ),
+            (IntVec3::new(1, 1, 0), "redstone_wire", WIRE),
+            (
+                IntVec3::new(2, 1, 0),
+                "repeater",
+                "delay=3,facing=west,locked=false,powered=false",
+            ),
+            (IntVec3::new(3, 1, 0), "redstone_wire", WIRE),
+        ]);
+        let output = IntVec3::new(3, 1, 0);
+        assert_eq!(power_level(&run(&world, 1), output), 0, "input is powered");
+        assert_eq!(
+            power_level(&run(&world, 3), output),
+            0,
+            "repeater is still off"
+        );
+        assert_eq!(
+            power_level(&run(&world, 4), output),
+            15,
+            "repeater turned on"
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::int_vec3::IntVec3;
+
+use super::{power_inputs, power_level, WorldState};
+
+/// Limit on the number of blocks in a trace so that tracing a large design stays responsive.
+const MAX_TRACED_BLOCKS: usize = 512;
+
+/// The blocks that power a target block, or that would power it if they were switched on, found
+/// by following power inputs backwards from the target.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignalTrace {
+    pub target: IntVec3,
+    pub segments: Vec<TraceSegment>,
+}
+
+/// A connection where power flows from one block into an adjacent block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceSegment {
+    pub from: IntVec3,
+    pub to: IntVec3,
+
+    /// Power level at `from`
+    pub level: i32,
+}
+
+impl SignalTrace {
+    pub fn new(world: &WorldState, target: IntVec3) -> Self {
+        let mut segments = vec![];
+        let mut visited = HashSet::from([target]);
+        let mut queue = VecDeque::from([target]);
+        while let Some(pos) = queue.pop_front() {
+            let level = power_level(world, pos);
+            for input in power_inputs(world, pos) {
+                let input_level = power_level(world, input);
+                // Wire passes power in both directions. When the wire is powered only follow the
+                // wire that power came from.
+                let is_wire_to_wire = is_wire(world, input) && is_wire(world, pos);
+                if is_wire_to_wire && level > 0 && input_level <= level {
+                    continue;
+                }
+                segments.push(TraceSegment {
+                    from: input,
+                    to: pos,
+                    level: input_level,
+                });
+                if visited.len() < MAX_TRACED_BLOCKS && visited.insert(input) {
+                    queue.push_back(input);
+                }
+            }
+        }
+        SignalTrace { target, segments }
+    }
+
+    /// Returns true if the given position is one of the blocks in the trace.
+    pub fn contains(&self, pos: IntVec3) -> bool {
+        pos == self.target
+            || self
+                .segments
+                .iter()
+                .any(|segment| segment.from == pos || segment.to == pos)
+    }
+}
+
+/// Follows redstone wire away from the given wire in the direction that power decreases until it
+/// reaches wire with no power. Returns `None` if the wire runs out before power reaches zero, or
+/// if the given position is not powered wire.
+pub fn decays_to_zero_at(world: &WorldState, wire: IntVec3) -> Option<IntVec3> {
+    if !is_wire(world, wire) {
+        return None;
+    }
+    let mut pos = wire;
+    let mut level = power_level(world, wire);
+    if level == 0 {
+        return None;
+    }
+    while level > 0 {
+        pos = pos
+            .neighbors()
+            .map(|(_, neighbor)| neighbor)
+            .find(|neighbor| {
+                neighbor.y == pos.y
+                    && is_wire(world, *neighbor)
+                    && power_level(world, *neighbor) == level - 1
+            })?;
+        level -= 1;
+    }
+    Some(pos)
+}
+
+fn is_wire(world: &WorldState, pos: IntVec3) -> bool {
+    world
+        .block_at(pos)
+        .map_or(false, |state| state.block_type == "redstone_wire")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        block_state::BlockState,
+        int_vec3::IntVec3,
+        timeline::{Timeline, WorldState},
+    };
+
+    use super::{decays_to_zero_at, SignalTrace, TraceSegment};
+
+    const WIRE: &str = "east=none,north=none,south=none,west=none,power=0";
+
+    /// A lever powers a line of 17 wires that ends at a lamp.
+    fn long_wire(powered: bool) -> Timeline {
+        let mut timeline =
+            Timeline::from_world(WorldState::new((IntVec3::ZERO, IntVec3::ONE * 32)));
+        let lever = format!("face=floor,facing=north,powered={}", powered);
+        timeline
+            .insert_block(IntVec3::new(0, 0, 0), BlockState::new("lever", &lever))
+            .unwrap();
+        for x in 1..=17 {
+            timeline
+                .insert_block(
+                    IntVec3::new(x, 0, 0),
+                    BlockState::new("redstone_wire", WIRE),
+                )
+                .unwrap();
+        }
+        timeline
+            .insert_block(
+                IntVec3::new(18, 0, 0),
+                BlockState::new("redstone_lamp", "lit=false"),
+            )
+            .unwrap();
+        timeline.simulate_to(1);
+        timeline
+    }
+
+    #[test]
+    fn traces_power_back_to_the_source() {
+        let timeline = long_wire(true);
+        let world = timeline.state_at(1).unwrap();
+        let trace = SignalTrace::new(&world, IntVec3::new(3, 0, 0));
+        assert_eq!(
+            trace.segments,
+            vec![
+                TraceSegment {
+                    from: IntVec3::new(2, 0, 0),
+                    to: IntVec3::new(3, 0, 0),
+                    level: 14
+                },
+                TraceSegment {
+                    from: IntVec3::new(1, 0, 0),
+                    to: IntVec3::new(2, 0, 0),
+                    level: 15
+                },
+                TraceSegment {
+                    from: IntVec3::new(0, 0, 0),
+                    to: IntVec3::new(1, 0, 0),
+                    level: 15
+                },
+            ]
+        );
+        assert!(trace.contains(IntVec3::new(0, 0, 0)));
+        assert_eq!(
+            decays_to_zero_at(&world, IntVec3::new(3, 0, 0)),
+            Some(IntVec3::new(16, 0, 0))
+        );
+    }
+
+    #[test]
+    fn traces_blocks_that_would_power_an_unlit_lamp() {
+        let timeline = long_wire(false);
+        let world = timeline.state_at(1).unwrap();
+        let trace = SignalTrace::new(&world, IntVec3::new(18, 0, 0));
+        assert!(trace.contains(IntVec3::new(0, 0, 0)), "reaches the lever");
+        assert!(trace.segments.iter().all(|segment| segment.level == 0));
+    }
+}
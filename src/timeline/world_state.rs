@@ -1,20 +1,30 @@
+use minecraft_assets::schemas::{blockstates::multipart::StateValue, models::BlockFace};
+
 use crate::{
     block::{is_flat_surface, requires_flat_surface},
     block_state::BlockState,
     int_vec3::IntVec3,
+    schematic::RejectedBlock,
 };
 
-#[derive(Clone, Debug, PartialEq)]
+use super::{
+    block_storage::BlockStorage,
+    input::{InputEvent, InvalidInput},
+};
+
+#[derive(Clone, Debug, PartialEq, Hash)]
 pub struct WorldState {
     bounds: (IntVec3, IntVec3),
-    positions: Vec<WorldPosition>,
+    blocks: BlockStorage,
+    scheduled_ticks: Vec<ScheduledTick>,
 }
 
 impl WorldState {
     pub fn new(bounds: (IntVec3, IntVec3)) -> Self {
         WorldState {
             bounds,
-            positions: vec![],
+            blocks: BlockStorage::default(),
+            scheduled_ticks: vec![],
         }
     }
 
@@ -24,10 +34,158 @@ impl WorldState {
         state: BlockState,
     ) -> Result<(), InvalidPlacement> {
         self.assert_valid_placement(pos, &state)?;
-        self.positions.push(WorldPosition { pos, state });
+        self.blocks.insert(pos, state);
+        Ok(())
+    }
+
+    /// Inserts each of the given blocks, bottom layer first so that a block that rests on another
+    /// can come before the block that supports it. Returns every block that could not be placed
+    /// if there are any.
+    pub fn place_blocks<'a>(
+        mut self,
+        blocks: impl IntoIterator<Item = (IntVec3, &'a BlockState)>,
+    ) -> Result<Self, Vec<RejectedBlock>> {
+        let mut blocks: Vec<_> = blocks.into_iter().collect();
+        blocks.sort_by_key(|(pos, _)| pos.layer_order_key());
+        let rejected: Vec<_> = blocks
+            .into_iter()
+            .filter_map(|(pos, state)| {
+                self.insert_block(pos, state.clone())
+                    .err()
+                    .map(|reason| RejectedBlock {
+                        pos,
+                        state: state.clone(),
+                        reason,
+                    })
+            })
+            .collect();
+        if rejected.is_empty() {
+            Ok(self)
+        } else {
+            Err(rejected)
+        }
+    }
+
+    /// Removes the block at the given position, if there is one, and returns its state.
+    pub fn remove_block(&mut self, pos: IntVec3) -> Option<BlockState> {
+        let removed = self.blocks.remove(pos)?;
+        self.scheduled_ticks.retain(|tick| tick.pos != pos);
+        Some(removed)
+    }
+
+    pub fn block_at(&self, pos: IntVec3) -> Option<&BlockState> {
+        self.blocks.get(pos)
+    }
+
+    /// Iterates over every block in the world. The order depends only on which blocks are in the
+    /// world, not on the order that they were placed in.
+    pub fn blocks(&self) -> impl Iterator<Item = (IntVec3, &BlockState)> {
+        self.blocks.iter()
+    }
+
+    /// The blocks that share a face with the given position, paired with the face of the given
+    /// position that each one touches.
+    pub fn neighbors(
+        &self,
+        pos: IntVec3,
+    ) -> impl Iterator<Item = (BlockFace, IntVec3, &BlockState)> {
+        pos.neighbors()
+            .filter_map(|(face, neighbor)| Some((face, neighbor, self.block_at(neighbor)?)))
+    }
+
+    pub fn bounds(&self) -> (IntVec3, IntVec3) {
+        self.bounds
+    }
+
+    /// Replaces the state of a block that is already in the world without checking placement
+    /// rules. This is used by the simulation to update properties like power levels. Does nothing
+    /// if there is no block at the given position.
+    pub fn set_block_state(&mut self, pos: IntVec3, state: BlockState) {
+        if self.blocks.contains(pos) {
+            self.blocks.insert(pos, state);
+        }
+    }
+
+    pub fn scheduled_ticks(&self) -> &[ScheduledTick] {
+        &self.scheduled_ticks
+    }
+
+    pub fn schedule_tick(&mut self, tick: ScheduledTick) {
+        self.scheduled_ticks.push(tick);
+    }
+
+    pub fn has_scheduled_tick(&self, pos: IntVec3) -> bool {
+        self.scheduled_ticks.iter().any(|tick| tick.pos == pos)
+    }
+
+    /// Counts down scheduled ticks by one, and returns the ticks that are now due.
+    pub fn take_due_ticks(&mut self) -> Vec<ScheduledTick> {
+        for tick in self.scheduled_ticks.iter_mut() {
+            tick.delay -= 1;
+        }
+        let (due, pending) = self
+            .scheduled_ticks
+            .drain(..)
+            .partition(|tick| tick.delay <= 0);
+        self.scheduled_ticks = pending;
+        due
+    }
+
+    /// Applies an input event from outside of the simulation, such as a player flipping a lever.
+    pub fn apply_input(&mut self, event: &InputEvent) -> Result<(), InvalidInput> {
+        let pos = event.pos();
+        let mut state = match self.block_at(pos) {
+            Some(state) if state.block_type == "lever" => state.clone(),
+            _ => return Err(InvalidInput::NotALever(pos)),
+        };
+        let powered = match event {
+            InputEvent::ToggleLever(_) => !state.get_bool("powered").unwrap_or(false),
+            InputEvent::SetLever(_, powered) => *powered,
+        };
+        state
+            .update("powered", StateValue::Bool(powered))
+            .map_err(|_| InvalidInput::NotALever(pos))?;
+        self.set_block_state(pos, state);
         Ok(())
     }
 
+    /// Lists positions where the block in this world differs from the block in the other world,
+    /// including positions that are occupied in only one of the two.
+    pub fn changed_positions(&self, other: &WorldState) -> Vec<IntVec3> {
+        let mut changed = self.blocks.changed_positions(&other.blocks);
+        changed.sort();
+        changed
+    }
+
+    /// The changes that turn this world into the next one.
+    pub(super) fn delta_to(&self, next: &WorldState) -> WorldDelta {
+        WorldDelta {
+            blocks: self
+                .blocks
+                .changed_positions(&next.blocks)
+                .into_iter()
+                .map(|pos| (pos, next.block_at(pos).cloned()))
+                .collect(),
+            scheduled_ticks: next.scheduled_ticks.clone(),
+        }
+    }
+
+    /// Applies changes without checking placement rules, since the changes were taken from a
+    /// world that was already valid.
+    pub(super) fn apply_delta(&mut self, delta: &WorldDelta) {
+        for (pos, state) in delta.blocks.iter() {
+            match state {
+                Some(state) => {
+                    self.blocks.insert(*pos, state.clone());
+                }
+                None => {
+                    self.blocks.remove(*pos);
+                }
+            }
+        }
+        self.scheduled_ticks = delta.scheduled_ticks.clone();
+    }
+
     fn assert_valid_placement(
         &self,
         pos: IntVec3,
@@ -56,25 +214,50 @@ impl WorldState {
     }
 
     fn is_position_occupied(&self, pos: IntVec3) -> bool {
-        self.positions.iter().any(|p| p.pos == pos)
+        self.blocks.contains(pos)
     }
 
+    /// The ground under the world bounds counts as a flat surface.
     fn is_flat_surface(&self, pos: IntVec3) -> bool {
-        match self.positions.iter().find(|p| p.pos == pos) {
-            Some(block) => is_flat_surface(&block.state),
-            None => false,
+        if pos.y < self.bounds.0.y {
+            return true;
         }
+        self.block_at(pos).map_or(false, is_flat_surface)
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum InvalidPlacement {
     OutOfBounds,
     PositionOccupied,
     NotAFlatSurface,
 }
 
+/// The changes between one world state and the next, as stored in a history between keyframes.
 #[derive(Clone, Debug, PartialEq)]
-struct WorldPosition {
-    pos: IntVec3,
-    state: BlockState,
+pub(super) struct WorldDelta {
+    /// The new block at each changed position, or `None` where a block was removed
+    blocks: Vec<(IntVec3, Option<BlockState>)>,
+
+    /// Scheduled ticks are stored in full, since most of them count down on every tick.
+    scheduled_ticks: Vec<ScheduledTick>,
+}
+
+impl WorldDelta {
+    pub(super) fn positions(&self) -> impl Iterator<Item = IntVec3> + '_ {
+        self.blocks.iter().map(|(pos, _)| *pos)
+    }
+}
+
+/// A block update that has been triggered, but that takes effect after a delay. For example
+/// a repeater switches on some number of ticks after its input is powered.
+#[derive(Clone, Debug, PartialEq, Hash)]
+pub struct ScheduledTick {
+    pub pos: IntVec3,
+
+    /// Number of ticks remaining before the update is applied
+    pub delay: i32,
+
+    /// State to give the block when the update is applied
+    pub state: BlockState,
 }
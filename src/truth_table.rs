@@ -0,0 +1,268 @@
+use std::fmt;
+
+use crate::{
+    logic_analyzer::Probe,
+    timeline::{power_level, InputEvent, InputSchedule, InvalidInput, TimeIndex, Timeline},
+};
+
+/// Limit on the number of inputs since every added input doubles the number of rows.
+pub const MAX_INPUTS: usize = 12;
+
+/// Output levels of a combinational circuit for every combination of input levels.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TruthTable {
+    pub inputs: Vec<Probe>,
+    pub outputs: Vec<Probe>,
+    pub rows: Vec<Row>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Row {
+    pub inputs: Vec<bool>,
+
+    /// Output levels once the circuit has settled, or at the last simulated tick if it did not.
+    pub outputs: Vec<bool>,
+
+    pub settling: Settling,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Settling {
+    /// The circuit stopped changing after the given number of ticks.
+    Settled(TimeIndex),
+
+    /// The circuit repeats the same sequence of states forever with the given period in ticks.
+    Oscillating(TimeIndex),
+
+    /// The circuit was still changing after the maximum number of ticks.
+    Unstable,
+}
+
+impl TruthTable {
+    /// Simulates the design once for each combination of inputs. Each input must be a lever. In
+    /// each run the levers are set at tick 0, and the simulation runs until the world stops
+    /// changing, until it repeats an earlier state, or until `max_ticks` have passed. The input
+    /// schedule of the design is not used.
+    ///
+    /// Rows are ordered by counting in binary with the first input as the most significant bit.
+    ///
+    /// Panics if there are more than `MAX_INPUTS` inputs.
+    pub fn generate(
+        design: &Timeline,
+        inputs: &[Probe],
+        outputs: &[Probe],
+        max_ticks: TimeIndex,
+    ) -> Result<Self, InvalidInput> {
+        assert!(
+            inputs.len() <= MAX_INPUTS,
+            "truth tables are limited to {} inputs",
+            MAX_INPUTS
+        );
+        let rows = (0..1usize << inputs.len())
+            .map(|n| {
+                let levels: Vec<bool> = (0..inputs.len())
+                    .map(|index| n & (1 << (inputs.len() - 1 - index)) != 0)
+                    .collect();
+                simulate_row(design, inputs, outputs, levels, max_ticks)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(TruthTable {
+            inputs: inputs.to_vec(),
+            outputs: outputs.to_vec(),
+            rows,
+        })
+    }
+
+    /// Rows where the circuit did not settle.
+    pub fn unstable_rows(&self) -> impl Iterator<Item = &Row> {
+        self.rows
+            .iter()
+            .filter(|row| !matches!(row.settling, Settling::Settled(_)))
+    }
+}
+
+fn simulate_row(
+    design: &Timeline,
+    inputs: &[Probe],
+    outputs: &[Probe],
+    levels: Vec<bool>,
+    max_ticks: TimeIndex,
+) -> Result<Row, InvalidInput> {
+    let mut schedule = InputSchedule::default();
+    for (probe, powered) in inputs.iter().zip(levels.iter()) {
+        let event = InputEvent::SetLever(probe.pos, *powered);
+        design.design().clone().apply_input(&event)?;
+        schedule.push(event, vec![0]);
+    }
+    let mut timeline = design.clone();
+    timeline.set_input_schedule(schedule);
+
+    // Simulation is deterministic so once a tick is identical to the tick before it, nothing will
+    // change again.
+    let mut settling = Settling::Unstable;
+    for time in 1..=max_ticks + 1 {
+        timeline.simulate_to(time);
+        if timeline.state_at(time) == timeline.state_at(time - 1) {
+            settling = Settling::Settled(time - 1);
+            break;
+        }
+        if let Some(cycle) = timeline.cycle().filter(|cycle| cycle.period > 1) {
+            settling = Settling::Oscillating(cycle.period);
+            break;
+        }
+    }
+    let final_time = match settling {
+        Settling::Settled(time) => time,
+        Settling::Oscillating(_) | Settling::Unstable => timeline.last_simulated_time(),
+    };
+    let world = timeline.state_at(final_time).unwrap();
+    Ok(Row {
+        inputs: levels,
+        outputs: outputs
+            .iter()
+            .map(|probe| power_level(&world, probe.pos) > 0)
+            .collect(),
+        settling,
+    })
+}
+
+/// Formats the table with a column per input and output, and a column with the number of ticks
+/// that each row took to settle.
+impl fmt::Display for TruthTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = |probes: &[Probe]| -> Vec<String> {
+            probes.iter().map(|probe| probe.name.clone()).collect()
+        };
+        writeln!(
+            f,
+            "{} | {} | ticks",
+            names(&self.inputs).join(" "),
+            names(&self.outputs).join(" ")
+        )?;
+        for row in self.rows.iter() {
+            let cells = |probes: &[Probe], levels: &[bool]| -> Vec<String> {
+                probes
+                    .iter()
+                    .zip(levels.iter())
+                    .map(|(probe, level)| {
+                        format!("{:<width$}", *level as u8, width = probe.name.len())
+                    })
+                    .collect()
+            };
+            let settling = match row.settling {
+                Settling::Settled(ticks) => ticks.to_string(),
+                Settling::Oscillating(period) => format!("oscillates with period {}", period),
+                Settling::Unstable => "unstable".to_owned(),
+            };
+            writeln!(
+                f,
+                "{} | {} | {}",
+                cells(&self.inputs, &row.inputs).join(" "),
+                cells(&self.outputs, &row.outputs).join(" "),
+                settling
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        block_state::BlockState, int_vec3::IntVec3, logic_analyzer::Probe, timeline::Timeline,
+    };
+
+    use super::{Settling, TruthTable};
+
+    const WIRE: &str = "east=none,north=none,south=none,west=none,power=0";
+    const LEVER: &str = "face=floor,facing=north,powered=false";
+
+    fn place(timeline: &mut Timeline, blocks: &[(IntVec3, &str, &str)]) {
+        for (pos, block_type, state_values) in blocks {
+            timeline
+                .insert_block(*pos, BlockState::new(block_type, state_values))
+                .unwrap();
+        }
+    }
+
+    /// Two levers feed a line of wire into a lamp. The wire is powered if either lever is on.
+    fn or_gate() -> Timeline {
+        let mut timeline = Timeline::default();
+        place(
+            &mut timeline,
+            &[
+                (IntVec3::new(0, 0, 1), "lever", LEVER),
+                (IntVec3::new(1, 0, 0), "lever", LEVER),
+                (IntVec3::new(1, 0, 1), "redstone_wire", WIRE),
+                (IntVec3::new(2, 0, 1), "redstone_wire", WIRE),
+                (IntVec3::new(3, 0, 1), "redstone_lamp", "lit=false"),
+            ],
+        );
+        timeline
+    }
+
+    #[test]
+    fn generates_a_table_for_an_or_gate() {
+        let table = TruthTable::generate(
+            &or_gate(),
+            &[
+                Probe::at(IntVec3::new(0, 0, 1)),
+                Probe::at(IntVec3::new(1, 0, 0)),
+            ],
+            &[Probe::at(IntVec3::new(3, 0, 1))],
+            20,
+        )
+        .unwrap();
+        let outputs: Vec<_> = table.rows.iter().map(|row| row.outputs[0]).collect();
+        assert_eq!(outputs, vec![false, true, true, true]);
+        assert_eq!(table.rows[0].settling, Settling::Settled(0));
+        assert_eq!(table.rows[3].settling, Settling::Settled(1));
+        assert_eq!(
+            table.to_string().lines().nth(2),
+            Some("0        1        | 1        | 1")
+        );
+    }
+
+    #[test]
+    fn flags_a_clock_as_unstable() {
+        // A torch powers a loop of wire that leads into a repeater, which powers the block that
+        // the torch is on.
+        let mut timeline = Timeline::default();
+        place(
+            &mut timeline,
+            &[
+                (IntVec3::new(5, 0, 5), "lever", LEVER),
+                (IntVec3::new(0, 0, 0), "iron_block", ""),
+                (IntVec3::new(1, 0, 0), "iron_block", ""),
+                (IntVec3::new(1, 0, 1), "iron_block", ""),
+                (IntVec3::new(1, 0, 2), "iron_block", ""),
+                (IntVec3::new(0, 0, 2), "iron_block", ""),
+                (IntVec3::new(0, 1, 0), "redstone_torch", "lit=true"),
+                (IntVec3::new(1, 1, 0), "redstone_wire", WIRE),
+                (IntVec3::new(1, 1, 1), "redstone_wire", WIRE),
+                (IntVec3::new(1, 1, 2), "redstone_wire", WIRE),
+                (IntVec3::new(0, 1, 2), "redstone_wire", WIRE),
+                (
+                    IntVec3::new(0, 0, 1),
+                    "repeater",
+                    "delay=1,facing=south,locked=false,powered=false",
+                ),
+            ],
+        );
+        let table = TruthTable::generate(
+            &timeline,
+            &[Probe::at(IntVec3::new(5, 0, 5))],
+            &[Probe::at(IntVec3::new(0, 1, 0))],
+            20,
+        )
+        .unwrap();
+        assert_eq!(table.unstable_rows().count(), 2);
+        assert!(matches!(table.rows[0].settling, Settling::Oscillating(_)));
+    }
+
+    #[test]
+    fn rejects_inputs_that_are_not_levers() {
+        let result = TruthTable::generate(&or_gate(), &[Probe::at(IntVec3::new(3, 0, 1))], &[], 20);
+        assert!(result.is_err());
+    }
+}
@@ -1,6 +1,6 @@
 use bevy::{prelude::*, utils::HashSet};
 
-use crate::util::RelativeDirection;
+use crate::{block_state::BlockTransform, selection::RegionOperation, util::RelativeDirection};
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum UiCommand {
@@ -10,6 +10,36 @@ pub enum UiCommand {
     CloseBlockPicker,
     RotateBlock(Option<RelativeDirection>),
     ToggleBlockPicker,
+    ToggleProbe,
+    TraceSignal,
+    ToggleLogicAnalyzer,
+    /// Move one of the two logic analyzer cursors (0 or 1) by the given number of ticks.
+    MoveAnalyzerCursor { cursor: usize, ticks: i32 },
+    ExportWaveform,
+    ToggleSurvivalCheck,
+    /// Turn the selected circuit template a quarter turn clockwise.
+    RotateTemplate,
+    StartSelection,
+    FinishSelection,
+    CopySelection,
+    CutSelection,
+    PasteClipboard,
+    /// Turn or mirror the blocks in the selection in place.
+    TransformSelection(BlockTransform),
+    /// Fill, replace or hollow out the selection with the block selected in the block picker.
+    EditSelection(RegionOperation),
+    /// Repeat the selection toward the cursor.
+    StackSelection,
+    /// Grow the world by the given number of blocks to the east and south, or shrink it if the
+    /// number is negative.
+    ResizeWorld(i32),
+    /// Start or stop showing changes to the design since the overlay was turned on.
+    ToggleDiffOverlay,
+    /// Start or stop showing changes to the design compared with the design file given with
+    /// `--diff-base`.
+    LoadDiffBase,
+    /// Start or stop showing the blocks that changed on the current tick.
+    ToggleTickDiff,
 }
 
 pub fn sent_command(mut ev_ui_command: EventReader<UiCommand>, command: UiCommand) -> bool {
@@ -21,6 +51,9 @@ pub enum Action {
     ActivateTool(Tool),
     ExitMode,
     RotateBlock(RelativeDirection),
+    /// Sends a UI command when the binding is invoked. This is for actions that do not need any
+    /// special handling when a key is pressed or released.
+    SendCommand(UiCommand),
     ToggleBlockPicker,
     UseActiveTool,
 }
@@ -56,6 +89,176 @@ impl Default for KeyBindings {
                 binding_style: BindingStyle::Tap,
                 modes: vec![Mode::Normal],
             },
+            Binding {
+                action: Action::ActivateTool(Tool::Probe),
+                key: Key::Keyboard(KeyCode::Q),
+                binding_style: BindingStyle::Hold,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::ActivateTool(Tool::Trace),
+                key: Key::Keyboard(KeyCode::T),
+                binding_style: BindingStyle::Hold,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::SendCommand(UiCommand::ToggleLogicAnalyzer),
+                key: Key::Keyboard(KeyCode::L),
+                binding_style: BindingStyle::Tap,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::SendCommand(UiCommand::MoveAnalyzerCursor {
+                    cursor: 0,
+                    ticks: -1,
+                }),
+                key: Key::Keyboard(KeyCode::LBracket),
+                binding_style: BindingStyle::Tap,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::SendCommand(UiCommand::MoveAnalyzerCursor {
+                    cursor: 0,
+                    ticks: 1,
+                }),
+                key: Key::Keyboard(KeyCode::RBracket),
+                binding_style: BindingStyle::Tap,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::SendCommand(UiCommand::MoveAnalyzerCursor {
+                    cursor: 1,
+                    ticks: -1,
+                }),
+                key: Key::Keyboard(KeyCode::Comma),
+                binding_style: BindingStyle::Tap,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::SendCommand(UiCommand::MoveAnalyzerCursor {
+                    cursor: 1,
+                    ticks: 1,
+                }),
+                key: Key::Keyboard(KeyCode::Period),
+                binding_style: BindingStyle::Tap,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::SendCommand(UiCommand::ToggleSurvivalCheck),
+                key: Key::Keyboard(KeyCode::C),
+                binding_style: BindingStyle::Tap,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::SendCommand(UiCommand::ExportWaveform),
+                key: Key::Keyboard(KeyCode::V),
+                binding_style: BindingStyle::Tap,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::ActivateTool(Tool::Select),
+                key: Key::Keyboard(KeyCode::B),
+                binding_style: BindingStyle::Hold,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::SendCommand(UiCommand::CopySelection),
+                key: Key::Keyboard(KeyCode::Y),
+                binding_style: BindingStyle::Tap,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::SendCommand(UiCommand::CutSelection),
+                key: Key::Keyboard(KeyCode::K),
+                binding_style: BindingStyle::Tap,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::SendCommand(UiCommand::PasteClipboard),
+                key: Key::Keyboard(KeyCode::G),
+                binding_style: BindingStyle::Tap,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::SendCommand(UiCommand::TransformSelection(BlockTransform::Rotate(
+                    1,
+                ))),
+                key: Key::Keyboard(KeyCode::O),
+                binding_style: BindingStyle::Tap,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::SendCommand(UiCommand::TransformSelection(BlockTransform::MirrorX)),
+                key: Key::Keyboard(KeyCode::M),
+                binding_style: BindingStyle::Tap,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::SendCommand(UiCommand::TransformSelection(BlockTransform::MirrorZ)),
+                key: Key::Keyboard(KeyCode::N),
+                binding_style: BindingStyle::Tap,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::SendCommand(UiCommand::EditSelection(RegionOperation::Fill)),
+                key: Key::Keyboard(KeyCode::F),
+                binding_style: BindingStyle::Tap,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::SendCommand(UiCommand::EditSelection(RegionOperation::Replace)),
+                key: Key::Keyboard(KeyCode::J),
+                binding_style: BindingStyle::Tap,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::SendCommand(UiCommand::EditSelection(RegionOperation::Hollow)),
+                key: Key::Keyboard(KeyCode::H),
+                binding_style: BindingStyle::Tap,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::SendCommand(UiCommand::StackSelection),
+                key: Key::Keyboard(KeyCode::U),
+                binding_style: BindingStyle::Tap,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::SendCommand(UiCommand::ResizeWorld(8)),
+                key: Key::Keyboard(KeyCode::Equals),
+                binding_style: BindingStyle::Tap,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::SendCommand(UiCommand::ResizeWorld(-8)),
+                key: Key::Keyboard(KeyCode::Minus),
+                binding_style: BindingStyle::Tap,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::SendCommand(UiCommand::ToggleDiffOverlay),
+                key: Key::Keyboard(KeyCode::D),
+                binding_style: BindingStyle::Tap,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::SendCommand(UiCommand::LoadDiffBase),
+                key: Key::Keyboard(KeyCode::E),
+                binding_style: BindingStyle::Tap,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::SendCommand(UiCommand::ToggleTickDiff),
+                key: Key::Keyboard(KeyCode::I),
+                binding_style: BindingStyle::Tap,
+                modes: vec![Mode::Normal],
+            },
+            Binding {
+                action: Action::SendCommand(UiCommand::RotateTemplate),
+                key: Key::Keyboard(KeyCode::R),
+                binding_style: BindingStyle::Tap,
+                modes: vec![Mode::Normal],
+            },
             // PlacingBlock mode
             Binding {
                 action: Action::RotateBlock(RelativeDirection::Left),
@@ -102,6 +305,10 @@ pub enum Tool {
     #[default]
     Place,
     Destroy,
+    Probe,
+    Trace,
+    /// Drag out a box selection while the tool is in use
+    Select,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -241,6 +448,9 @@ fn dispatch_action(
             let command = match selected_tool.active_tool {
                 Tool::Place => UiCommand::PlaceBlock,
                 Tool::Destroy => UiCommand::DestroyBlock,
+                Tool::Probe => UiCommand::ToggleProbe,
+                Tool::Trace => UiCommand::TraceSignal,
+                Tool::Select => UiCommand::FinishSelection,
             };
             ev_ui_command.send(command);
         }
@@ -253,6 +463,9 @@ fn dispatch_action(
         Action::RotateBlock(dir) => {
             ev_ui_command.send(UiCommand::RotateBlock(Some(dir)));
         }
+        Action::SendCommand(command) => {
+            ev_ui_command.send(command);
+        }
         Action::ToggleBlockPicker => {
             ev_ui_command.send(UiCommand::ToggleBlockPicker);
         }
@@ -271,6 +484,9 @@ fn dispatch_action_start(
             match selected_tool.active_tool {
                 Tool::Place => state.push_mode(Mode::PlacingBlock),
                 Tool::Destroy => (),
+                Tool::Probe => (),
+                Tool::Trace => (),
+                Tool::Select => ev_ui_command.send(UiCommand::StartSelection),
             };
         }
         Action::ActivateTool(tool) => {
@@ -278,6 +494,7 @@ fn dispatch_action_start(
         }
         Action::ExitMode => (),
         Action::RotateBlock(dir) => ev_ui_command.send(UiCommand::RotateBlock(Some(dir))),
+        Action::SendCommand(command) => ev_ui_command.send(command),
         Action::ToggleBlockPicker => {
             ev_ui_command.send(UiCommand::OpenBlockPicker);
         }
@@ -301,6 +518,7 @@ fn dispatch_action_finish(
         Action::ActivateTool(_) => selected_tool.pop_tool(),
         Action::ExitMode => dispatch_action(ev_ui_command, state, selected_tool, action),
         Action::RotateBlock(_) => ev_ui_command.send(UiCommand::RotateBlock(None)),
+        Action::SendCommand(_) => (),
         Action::ToggleBlockPicker => {
             ev_ui_command.send(UiCommand::CloseBlockPicker);
         }
@@ -465,6 +683,57 @@ mod tests {
         )
     }
 
+    #[test]
+    fn toggles_a_probe() {
+        let mut app = initialize_test_app();
+        send_key_down(&mut app, KeyCode::Q);
+        send_key_press(&mut app, MouseButton::Left);
+        assert_eq!(
+            ui_command_events(&app),
+            vec![UiCommand::ToggleProbe],
+            "toggle probe command was sent"
+        )
+    }
+
+    #[test]
+    fn traces_a_signal() {
+        let mut app = initialize_test_app();
+        send_key_down(&mut app, KeyCode::T);
+        send_key_press(&mut app, MouseButton::Left);
+        assert_eq!(
+            ui_command_events(&app),
+            vec![UiCommand::TraceSignal],
+            "trace signal command was sent"
+        )
+    }
+
+    #[test]
+    fn drags_a_selection() {
+        let mut app = initialize_test_app();
+        send_key_down(&mut app, KeyCode::B);
+        send_key_down(&mut app, MouseButton::Left);
+        send_key_up(&mut app, MouseButton::Left);
+        assert_eq!(
+            ui_command_events(&app),
+            vec![UiCommand::StartSelection, UiCommand::FinishSelection],
+            "selection commands were sent"
+        )
+    }
+
+    #[test]
+    fn moves_a_logic_analyzer_cursor() {
+        let mut app = initialize_test_app();
+        send_key_press(&mut app, KeyCode::Period);
+        assert_eq!(
+            ui_command_events(&app),
+            vec![UiCommand::MoveAnalyzerCursor {
+                cursor: 1,
+                ticks: 1
+            }],
+            "move cursor command was sent"
+        )
+    }
+
     fn initialize_test_app() -> App {
         let mut app = App::new();
         app.insert_resource(KeyBindings::default())
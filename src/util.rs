@@ -66,3 +66,15 @@ pub fn vec_to_block_face(vec: Vec3) -> BlockFace {
 pub fn degrees_to_radians(degrees: i32) -> f32 {
     -degrees as f32 / 360.0 * TAU
 }
+
+/// Returns the face on the opposite side of a block.
+pub fn opposite_face(face: BlockFace) -> BlockFace {
+    match face {
+        BlockFace::Down => BlockFace::Up,
+        BlockFace::Up => BlockFace::Down,
+        BlockFace::North => BlockFace::South,
+        BlockFace::South => BlockFace::North,
+        BlockFace::West => BlockFace::East,
+        BlockFace::East => BlockFace::West,
+    }
+}
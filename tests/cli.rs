@@ -0,0 +1,144 @@
+//! Runs the command line tool on the designs in `tests/designs`, and checks its output and exit
+//! codes.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+fn cli(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_redstone-cli"))
+        .args(args)
+        .current_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/designs"))
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> &str {
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+fn stderr(output: &Output) -> &str {
+    std::str::from_utf8(&output.stderr).unwrap()
+}
+
+/// A path in the temp directory that is unique to the test that asks for it.
+fn temp_file(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("redstone-cli-{}-{}", std::process::id(), name))
+}
+
+#[test]
+fn converts_a_design() {
+    let output_file = temp_file("converted.blocks");
+    let output = cli(&["convert", "lamp.blocks", output_file.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(
+        fs::read_to_string(&output_file).unwrap(),
+        "bounds 0 0 0 4 2 2\n\
+         block 0 0 0 lever[face=floor,facing=north,powered=false]\n\
+         block 1 0 0 redstone_wire[east=none,north=none,power=0,south=none,west=none]\n\
+         block 2 0 0 redstone_lamp[lit=false]\n\
+         input toggle 0 0 0 @ 1\n"
+    );
+    fs::remove_file(output_file).unwrap();
+}
+
+#[test]
+fn does_not_convert_a_design_with_blocks_that_cannot_be_placed() {
+    let output_file = temp_file("floating.blocks");
+    let output = cli(&["convert", "floating.blocks", output_file.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(!output_file.exists());
+}
+
+#[test]
+fn simulates_a_design() {
+    let output = cli(&["simulate", "lamp.blocks", "--ticks", "4"]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "tick\tx0_y0_z0\tx2_y0_z0\n\
+         0\t0\t0\n\
+         1\t0\t0\n\
+         2\t15\t15\n\
+         3\t15\t15\n\
+         4\t15\t15\n\
+         # steady state from tick 2\n"
+    );
+}
+
+#[test]
+fn fails_to_simulate_inputs_for_blocks_that_are_not_levers() {
+    let output = cli(&["simulate", "not_a_lever.blocks"]);
+    assert_eq!(output.status.code(), Some(4));
+    assert_eq!(stdout(&output), "");
+}
+
+#[test]
+fn validates_designs() {
+    let output = cli(&["validate", "lamp.blocks"]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "lamp.blocks: ok\n");
+
+    let output = cli(&["validate", "lamp.blocks", "floating.blocks"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(stdout(&output), "lamp.blocks: ok\n");
+    assert_eq!(
+        stderr(&output),
+        "floating.blocks: cannot place \
+         redstone_wire[east=none,north=none,power=0,south=none,west=none] at 0 1 0: \
+         NotAFlatSurface\n"
+    );
+}
+
+#[test]
+fn merges_designs() {
+    let output_file = temp_file("merged.blocks");
+    let output = cli(&[
+        "merge",
+        "lamp.blocks",
+        "lamp_to_sandstone.blocks",
+        "lamp.blocks",
+        "--output",
+        output_file.to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(
+        fs::read_to_string(&output_file).unwrap(),
+        "bounds 0 0 0 4 2 2\n\
+         block 0 0 0 lever[face=floor,facing=north,powered=false]\n\
+         block 1 0 0 redstone_wire[east=none,north=none,power=0,south=none,west=none]\n\
+         block 2 0 0 sandstone\n\
+         input toggle 0 0 0 @ 1\n"
+    );
+
+    let output = cli(&[
+        "merge",
+        "lamp.blocks",
+        "lamp_to_sandstone.blocks",
+        "lamp_removed.blocks",
+        "--output",
+        output_file.to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(5));
+    assert!(stderr(&output).ends_with(
+        "conflict at 2 0 0: base redstone_lamp[lit=false], ours sandstone, theirs empty\n"
+    ));
+    fs::remove_file(output_file).unwrap();
+}
+
+#[test]
+fn rejects_invalid_arguments() {
+    for args in [
+        &["convert", "lamp.blocks"][..],
+        &["simulate"],
+        &["simulate", "lamp.blocks", "--ticks", "many"],
+        &["validate", "--everything", "lamp.blocks"],
+        &["unknown"],
+    ] {
+        let output = cli(args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert_eq!(stdout(&output), "", "{:?}", args);
+    }
+}